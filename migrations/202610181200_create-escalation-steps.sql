@@ -0,0 +1,11 @@
+CREATE TABLE
+    IF NOT EXISTS public.escalation_steps (
+        id character varying(128) PRIMARY KEY NOT NULL,
+        guild_id bigint NOT NULL,
+        warn_count integer NOT NULL,
+        period bigint NOT NULL,
+        punishment_type action_type NOT NULL,
+        duration bigint NOT NULL DEFAULT 0,
+        created_at timestamp without time zone NOT NULL DEFAULT now (),
+        UNIQUE (guild_id, warn_count)
+    );
@@ -0,0 +1,14 @@
+CREATE TABLE
+    IF NOT EXISTS public.spam_filters (
+        guild_id bigint PRIMARY KEY NOT NULL,
+        channel_limit integer NOT NULL DEFAULT 0,
+        guild_limit integer NOT NULL DEFAULT 0,
+        duplicate_channels integer NOT NULL DEFAULT 0,
+        period integer NOT NULL,
+        reason text,
+        punishment_type action_type NOT NULL,
+        duration bigint,
+        day_clear_amount smallint,
+        silent boolean,
+        log_channel_id bigint
+    );
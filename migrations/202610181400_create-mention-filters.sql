@@ -0,0 +1,13 @@
+CREATE TABLE
+    IF NOT EXISTS public.mention_filters (
+        guild_id bigint PRIMARY KEY NOT NULL,
+        message_limit integer NOT NULL DEFAULT 0,
+        window_limit integer NOT NULL DEFAULT 0,
+        period integer NOT NULL,
+        reason text,
+        punishment_type action_type NOT NULL,
+        duration bigint,
+        day_clear_amount smallint,
+        silent boolean,
+        log_channel_id bigint
+    );
@@ -0,0 +1,6 @@
+ALTER TABLE ocr_image_hashes
+ADD COLUMN IF NOT EXISTS perceptual_hash BIGINT;
+
+CREATE INDEX IF NOT EXISTS ocr_image_hashes_perceptual_idx ON ocr_image_hashes (guild_id)
+WHERE
+    perceptual_hash IS NOT NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE automod_rules
+ADD COLUMN IF NOT EXISTS exempt_roles BIGINT[] NOT NULL DEFAULT '{}',
+ADD COLUMN IF NOT EXISTS exempt_users BIGINT[] NOT NULL DEFAULT '{}',
+ADD COLUMN IF NOT EXISTS channels BIGINT[] NOT NULL DEFAULT '{}',
+ADD COLUMN IF NOT EXISTS channels_include_only BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+ALTER TABLE automod_rules
+ADD COLUMN IF NOT EXISTS normalization TEXT[] NOT NULL DEFAULT '{invisible,width,diacritics,confusables}';
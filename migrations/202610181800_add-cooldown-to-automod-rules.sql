@@ -0,0 +1,5 @@
+ALTER TABLE automod_rules
+ADD COLUMN IF NOT EXISTS debounce INTEGER NOT NULL DEFAULT 15,
+ADD COLUMN IF NOT EXISTS max_punishments INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN IF NOT EXISTS punishment_period BIGINT NOT NULL DEFAULT 0,
+ADD COLUMN IF NOT EXISTS delete_during_cooldown BOOLEAN NOT NULL DEFAULT true;
@@ -0,0 +1,17 @@
+CREATE TABLE
+    IF NOT EXISTS public.rule_hits (
+        id bigserial PRIMARY KEY,
+        rule_id character varying(128) NOT NULL,
+        guild_id bigint NOT NULL,
+        user_id bigint NOT NULL,
+        channel_id bigint NOT NULL,
+        message_id bigint NOT NULL,
+        source text NOT NULL,
+        punished boolean NOT NULL,
+        action_id character varying(128),
+        created_at timestamp without time zone NOT NULL DEFAULT now ()
+    );
+
+CREATE INDEX IF NOT EXISTS rule_hits_rule_idx ON rule_hits (rule_id, created_at);
+
+CREATE INDEX IF NOT EXISTS rule_hits_guild_idx ON rule_hits (guild_id, created_at);
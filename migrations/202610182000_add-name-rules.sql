@@ -0,0 +1,5 @@
+ALTER TYPE public.action_type ADD VALUE IF NOT EXISTS 'rename';
+
+ALTER TABLE rule_hits
+ALTER COLUMN channel_id DROP NOT NULL,
+ALTER COLUMN message_id DROP NOT NULL;
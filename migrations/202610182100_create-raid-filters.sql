@@ -0,0 +1,21 @@
+CREATE TABLE
+    IF NOT EXISTS public.raid_filters (
+        guild_id bigint PRIMARY KEY NOT NULL,
+        join_limit integer NOT NULL DEFAULT 0,
+        suspicious_limit integer NOT NULL DEFAULT 0,
+        period integer NOT NULL,
+        min_account_age bigint NOT NULL DEFAULT 0,
+        raise_verification boolean NOT NULL DEFAULT true,
+        pause_invites boolean NOT NULL DEFAULT true,
+        lock_channels bigint[] NOT NULL DEFAULT '{}'
+    );
+
+CREATE TABLE
+    IF NOT EXISTS public.raid_lockdowns (
+        guild_id bigint PRIMARY KEY NOT NULL,
+        suspects bigint[] NOT NULL DEFAULT '{}',
+        previous_verification smallint,
+        invites_paused boolean NOT NULL DEFAULT false,
+        locked_channels bigint[] NOT NULL DEFAULT '{}',
+        created_at timestamp without time zone NOT NULL DEFAULT now ()
+    );
@@ -0,0 +1,8 @@
+CREATE TABLE
+    IF NOT EXISTS public.account_age_gates (
+        guild_id bigint PRIMARY KEY NOT NULL,
+        min_age bigint NOT NULL,
+        action text NOT NULL CHECK (action IN ('kick', 'quarantine', 'flag')),
+        quarantine_role_id bigint,
+        message text
+    );
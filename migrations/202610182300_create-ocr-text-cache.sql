@@ -0,0 +1,9 @@
+CREATE TABLE
+    IF NOT EXISTS public.ocr_text_cache (
+        image_hash character varying(64) PRIMARY KEY NOT NULL,
+        text text NOT NULL,
+        steps text[] NOT NULL DEFAULT '{}',
+        created_at timestamp without time zone NOT NULL DEFAULT now ()
+    );
+
+CREATE INDEX IF NOT EXISTS ocr_text_cache_created_idx ON ocr_text_cache (created_at);
@@ -0,0 +1,2 @@
+ALTER TABLE automod_rules
+ADD COLUMN IF NOT EXISTS condition JSONB;
@@ -0,0 +1,6 @@
+ALTER TABLE actions
+ADD COLUMN IF NOT EXISTS batch_id VARCHAR(128);
+
+CREATE INDEX IF NOT EXISTS actions_batch_id_idx ON actions (batch_id)
+WHERE
+    batch_id IS NOT NULL;
@@ -0,0 +1,9 @@
+CREATE TABLE
+    IF NOT EXISTS public.raid_alerts (
+        message_id bigint PRIMARY KEY NOT NULL,
+        guild_id bigint NOT NULL,
+        suspects bigint[] NOT NULL DEFAULT '{}',
+        created_at timestamp without time zone NOT NULL DEFAULT now ()
+    );
+
+CREATE INDEX IF NOT EXISTS raid_alerts_guild_id_idx ON raid_alerts (guild_id);
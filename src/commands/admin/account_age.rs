@@ -0,0 +1,308 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::{Token, lex},
+    transformers::Transformers,
+    utils::{
+        age_gate::{AgeGate, AgeGateAction, DEFAULT_AGE_GATE_MESSAGE},
+        consume_pgsql_error, consume_serenity_error,
+    },
+};
+use aegis_macros::command;
+
+pub struct AccountAge;
+
+impl AccountAge {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for AccountAge {
+    fn get_name(&self) -> &'static str {
+        "account_age"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures the minimum account age to join the server"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures the minimum account age of members joining the server. \
+        `account_age set <age> <kick/quarantine/flag> [role]` handles members whose Discord account is younger than `age`, \
+        e.g. `account_age set 7d quarantine @Quarantine`. `kick` kicks them, `quarantine` gives them the role passed \
+        and `flag` only logs them. Every gated join is logged to the member log. \
+        Kicked and quarantined members receive a DM explaining why, `account_age message <text>` customizes it \
+        where `{server}`, `{age}` and `{required}` are replaced with the server name, the account age and the minimum age. \
+        `account_age message reset` restores the default DM, `account_age disable` turns the gate off \
+        and running the command without arguments shows the current configuration."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("set/message/disable", false),
+            CommandSyntax::Consume("options"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::string] subcommand: Option<String>,
+        #[transformers::string_consume] options: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let description = match subcommand.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("show") => match handler.age_gate_cache.lock().await.get(guild_id.get()) {
+                Some(gate) => format!(
+                    "**ACCOUNT AGE GATE**\n-# {}\n```\n{}\n```",
+                    gate.describe(),
+                    gate.message.as_deref().unwrap_or(DEFAULT_AGE_GATE_MESSAGE)
+                ),
+                None => String::from("**ACCOUNT AGE GATE**\n-# Disabled"),
+            },
+            Some("disable") => {
+                trace.point("deleting_gate");
+                if let Err(err) = sqlx::query("DELETE FROM account_age_gates WHERE guild_id = $1")
+                    .bind(guild_id.get() as i64)
+                    .execute(&*SQL)
+                    .await
+                {
+                    consume_pgsql_error("AGE GATE DISABLE".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+                handler.age_gate_cache.lock().await.remove(guild_id.get());
+
+                String::from("**ACCOUNT AGE GATE DISABLED**")
+            }
+            Some("message") => {
+                let Some(text) = options else {
+                    return Err(CommandError {
+                        title: String::from("Missing message"),
+                        hint: Some(String::from(
+                            "pass the DM to send or `reset` for the default one",
+                        )),
+                        arg: None,
+                    });
+                };
+
+                if text.len() > 1000 {
+                    return Err(CommandError {
+                        title: String::from("The message may be at most 1000 characters long"),
+                        hint: None,
+                        arg: _options_arg,
+                    });
+                }
+
+                let message = (!text.eq_ignore_ascii_case("reset")).then_some(text);
+
+                trace.point("updating_database");
+                let res =
+                    sqlx::query("UPDATE account_age_gates SET message = $2 WHERE guild_id = $1")
+                        .bind(guild_id.get() as i64)
+                        .bind(message.as_deref())
+                        .execute(&*SQL)
+                        .await;
+
+                match res {
+                    Ok(r) if r.rows_affected() == 0 => {
+                        return Err(CommandError {
+                            title: String::from("The account age gate is disabled"),
+                            hint: Some(String::from("use `account_age set` to enable it first")),
+                            arg: None,
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        consume_pgsql_error("AGE GATE MESSAGE".into(), err);
+                        return Err(CommandError::new("Could not update the database"));
+                    }
+                }
+
+                {
+                    let mut cache = handler.age_gate_cache.lock().await;
+                    if let Some(mut gate) = cache.get(guild_id.get()) {
+                        gate.message = message.clone();
+                        cache.set(guild_id.get(), gate);
+                    }
+                }
+
+                format!(
+                    "**ACCOUNT AGE GATE MESSAGE UPDATED**\n```\n{}\n```",
+                    message.as_deref().unwrap_or(DEFAULT_AGE_GATE_MESSAGE)
+                )
+            }
+            Some("set") => {
+                let Some(input) = options else {
+                    return Err(CommandError {
+                        title: String::from("Missing minimum age and action"),
+                        hint: Some(String::from(
+                            "e.g. `account_age set 7d quarantine @Quarantine`",
+                        )),
+                        arg: None,
+                    });
+                };
+
+                let mut args = lex(input).into_iter().peekable();
+                let min_age = match Transformers::duration(&ctx, &msg, &mut args).await {
+                    Ok(Token {
+                        contents: Some(CommandArgument::Duration(d)),
+                        ..
+                    }) if d.num_seconds() > 0 => d,
+                    Err(TransformerError::CommandError(err)) => {
+                        return Err(CommandError {
+                            arg: _options_arg,
+                            ..err
+                        });
+                    }
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Expected a minimum age above 0"),
+                            hint: Some(String::from("e.g. `account_age set 7d kick`")),
+                            arg: _options_arg,
+                        });
+                    }
+                };
+
+                let action = match args.next().map(|t| t.raw.to_lowercase()).as_deref() {
+                    Some("kick") => AgeGateAction::Kick,
+                    Some("flag") => AgeGateAction::Flag,
+                    Some("quarantine") => {
+                        let role_id = args
+                            .next()
+                            .and_then(|t| {
+                                t.raw
+                                    .trim_start_matches("<@&")
+                                    .trim_end_matches('>')
+                                    .parse::<u64>()
+                                    .ok()
+                            })
+                            .unwrap_or(0);
+
+                        let exists = role_id != 0
+                            && guild_id
+                                .roles(&ctx)
+                                .await
+                                .is_ok_and(|roles| roles.keys().any(|id| id.get() == role_id));
+                        if !exists {
+                            return Err(CommandError {
+                                title: String::from(
+                                    "Expected a role of this server to quarantine with",
+                                ),
+                                hint: Some(String::from(
+                                    "e.g. `account_age set 7d quarantine @Quarantine`",
+                                )),
+                                arg: _options_arg,
+                            });
+                        }
+
+                        AgeGateAction::Quarantine(role_id)
+                    }
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Expected an action"),
+                            hint: Some(String::from("use kick, quarantine or flag")),
+                            arg: _options_arg,
+                        });
+                    }
+                };
+
+                trace.point("updating_database");
+                let res = sqlx::query(
+                    "INSERT INTO account_age_gates (guild_id, min_age, action, quarantine_role_id) \
+                     VALUES ($1, $2, $3, $4) \
+                     ON CONFLICT (guild_id) DO UPDATE SET min_age = $2, action = $3, quarantine_role_id = $4 \
+                     RETURNING message",
+                )
+                .bind(guild_id.get() as i64)
+                .bind(min_age.num_seconds())
+                .bind(action.name())
+                .bind(match action {
+                    AgeGateAction::Quarantine(role_id) => Some(role_id as i64),
+                    _ => None,
+                })
+                .fetch_one(&*SQL)
+                .await;
+
+                let message = match res {
+                    Ok(row) => sqlx::Row::get::<Option<String>, _>(&row, "message"),
+                    Err(err) => {
+                        consume_pgsql_error("AGE GATE SET".into(), err);
+                        return Err(CommandError::new("Could not update the database"));
+                    }
+                };
+
+                let gate = AgeGate {
+                    min_age,
+                    action,
+                    message,
+                };
+                let description = format!("**ACCOUNT AGE GATE UPDATED**\n-# {}", gate.describe());
+                handler
+                    .age_gate_cache
+                    .lock()
+                    .await
+                    .set(guild_id.get(), gate);
+                description
+            }
+            Some(_) => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("use set, message, disable or no subcommand")),
+                    arg: _subcommand_arg,
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("ACCOUNT AGE RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, Message, Permissions},
+    async_trait,
+};
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::attachment_filter::AttachmentFilter,
+};
+use aegis_macros::command;
+
+pub struct CreateAttachmentRule;
+
+impl CreateAttachmentRule {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for CreateAttachmentRule {
+    fn get_name(&self) -> &'static str {
+        "create_attachment_rule"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Creates a new automoderation rule for attachments"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Creates a new automoderation rule for attachments. \
+        The bot will then check the files of every message against the conditions and take actions automatically. \
+        `ext:exe,scr` matches file extensions, `mime:application/zip,video/*` matches the content type detected from the file itself, \
+        so renamed files are caught as well. `mime:executable` and `mime:archive` cover the common executable and archive formats. \
+        `size:8mb` matches files larger than the size and `count:5` messages with more than 5 attachments. \
+        A message matches if any condition matches, e.g. `create_attachment_rule files ext:exe,bat,scr mime:executable size:25mb`. \
+        Use `rule_exempt` to apply the rule in specific channels only. \
+        Members with the manage messages permission are not affected by attachment rules."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("name", true),
+            CommandSyntax::Consume("conditions"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::some_string] name: String,
+        #[transformers::string_consume] rule: String,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        if name.len() >= 100 {
+            return Err(CommandError {
+                title: String::from("name argument can only be a max of 100 characters long"),
+                hint: None,
+                arg: Some(_name_arg),
+            });
+        }
+
+        if rule.len() >= 500 {
+            return Err(CommandError {
+                title: String::from("rule argument can only be a max of 500 characters long"),
+                hint: None,
+                arg: Some(_rule_arg),
+            });
+        }
+
+        if let Err(err) = AttachmentFilter::parse(&rule) {
+            return Err(CommandError {
+                title: String::from("Invalid attachment conditions"),
+                hint: Some(err),
+                arg: Some(_rule_arg),
+            });
+        }
+
+        super::rule_creation::create_rule(&ctx, &msg, handler, "attachment", name, rule, trace)
+            .await
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
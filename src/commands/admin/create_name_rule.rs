@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, Message, Permissions},
+    async_trait,
+};
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+};
+use aegis_macros::command;
+
+pub struct CreateNameRule;
+
+impl CreateNameRule {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for CreateNameRule {
+    fn get_name(&self) -> &'static str {
+        "create_name_rule"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Creates a new automoderation rule for member names"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Creates a new automoderation rule for member names. \
+        The bot will then automatically check the username, display name and nickname of every joining member \
+        and every member changing their name for the selected strings and take actions automatically. \
+        The rename punishment replaces the nickname with a placeholder. \
+        Using slashes at the start and end of a rule will be interpreted as Regex using the Rust Regex crate. \
+        Otherwise simple string matching is used (case insensitive). \
+        Members with the manage nicknames permission are not affected by name rules."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("name", true),
+            CommandSyntax::String("rule", true),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::some_string] name: String,
+        #[transformers::some_string] rule: String,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        if name.len() >= 100 {
+            return Err(CommandError {
+                title: String::from("name argument can only be a max of 100 characters long"),
+                hint: None,
+                arg: Some(_name_arg),
+            });
+        }
+
+        if rule.len() >= 500 {
+            return Err(CommandError {
+                title: String::from("rule argument can only be a max of 500 characters long"),
+                hint: None,
+                arg: Some(_rule_arg),
+            });
+        }
+
+        super::rule_creation::validate_pattern(
+            &msg,
+            handler,
+            &rule,
+            &_rule_arg,
+            "check the pattern for unbalanced brackets or unsupported syntax",
+        )?;
+
+        super::rule_creation::create_rule(&ctx, &msg, handler, "name", name, rule, trace).await
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
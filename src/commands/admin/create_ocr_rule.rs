@@ -1,40 +1,229 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use serenity::{
     all::{
-        ActionRowComponent, ButtonStyle, ComponentInteractionDataKind, Context, CreateActionRow,
-        CreateAllowedMentions, CreateButton, CreateEmbed, CreateInputText,
-        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal,
-        CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMessage,
-        InputTextStyle, Message, Permissions,
+        Context, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage, Message,
+        Permissions,
     },
     async_trait,
 };
 
 use crate::{
-    SQL,
+    OCR_POOL,
     commands::{
         Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
         CommandSyntax, TransformerFnArc,
     },
     constants::BRAND_BLUE,
     event_handler::{CommandError, Handler},
-    lexer::Token,
+    lexer::{Token, lex},
     transformers::Transformers,
     utils::{
-        clamp_chars, consume_pgsql_error, consume_serenity_error,
-        rule_cache::{Punishment, Rule},
-        tinyid,
+        clamp_chars, consume_serenity_error, ocr::image_urls, ocr_pool::OcrPriority,
+        rule_cache::Rule, trace::TraceContext,
     },
 };
 use aegis_macros::command;
 
+/// Patterns and samples a single `test` compares
+const MAX_TEST_PATTERNS: usize = 10;
+const MAX_TEST_SAMPLES: usize = 10;
+
+/// Shown with invalid regex patterns
+const OCR_PATTERN_HINT: &str = "try patterns against sample text first with `create_ocr_rule test`";
+
 pub struct CreateOcrRule;
 
 impl CreateOcrRule {
     pub fn new() -> Self {
         Self {}
     }
+
+    /// `create_ocr_rule test <patterns...> [-- samples...]`, matches every pattern against every sample
+    /// the same way a stored rule would, without storing anything
+    async fn test(
+        ctx: &Context,
+        msg: &Message,
+        handler: &Handler,
+        first: &Token,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.map(|id| id.get()).unwrap_or(1);
+        let input = msg
+            .content
+            .strip_prefix(handler.prefix.as_str())
+            .unwrap_or_default();
+        let tokens = lex(input.to_string())
+            .into_iter()
+            .filter(|t| t.iteration >= first.iteration)
+            .collect::<Vec<_>>();
+
+        let (pattern_tokens, sample_tokens) =
+            match tokens.iter().position(|t| t.raw == "--" && !t.quoted) {
+                Some(i) => (&tokens[..i], &tokens[i + 1..]),
+                None => (&tokens[..], &[][..]),
+            };
+
+        if pattern_tokens.len() > MAX_TEST_PATTERNS {
+            return Err(CommandError {
+                title: format!("At most {MAX_TEST_PATTERNS} patterns can be tested at once"),
+                hint: None,
+                arg: pattern_tokens.get(MAX_TEST_PATTERNS).cloned(),
+            });
+        }
+
+        for token in pattern_tokens {
+            if token.raw.len() >= 500 {
+                return Err(CommandError {
+                    title: String::from("patterns can only be a max of 500 characters long"),
+                    hint: None,
+                    arg: Some(token.clone()),
+                });
+            }
+
+            super::rule_creation::validate_pattern(
+                msg,
+                handler,
+                &token.raw,
+                token,
+                OCR_PATTERN_HINT,
+            )?;
+        }
+
+        let rules = pattern_tokens
+            .iter()
+            .map(
+                |t| match t.raw.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+                    Some(pattern) => Rule::preview(guild_id, pattern.to_string(), true, true),
+                    None => Rule::preview(guild_id, t.raw.clone(), false, true),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        // (label, text)
+        let mut samples: Vec<(String, String)> = sample_tokens
+            .iter()
+            .take(MAX_TEST_SAMPLES)
+            .enumerate()
+            .map(|(i, t)| (format!("Sample {}", i + 1), t.raw.clone()))
+            .collect();
+
+        if samples.is_empty() {
+            let Some(reply) = msg.referenced_message.as_deref() else {
+                return Err(CommandError {
+                    title: String::from("Missing samples"),
+                    hint: Some(String::from(
+                        "pass quoted samples after `--` or reply to a message with an image",
+                    )),
+                    arg: None,
+                });
+            };
+
+            trace.point("running_ocr");
+            let urls = image_urls(&reply.attachments, &reply.embeds, &reply.sticker_items);
+            for (i, url) in urls.into_iter().take(MAX_TEST_SAMPLES).enumerate() {
+                let Ok(res) = reqwest::get(url).await else {
+                    continue;
+                };
+                let Ok(bytes) = res.bytes().await else {
+                    continue;
+                };
+
+                match OCR_POOL
+                    .extract(guild_id, OcrPriority::High, bytes.to_vec())
+                    .await
+                {
+                    Ok(output) => samples.push((format!("Image {}", i + 1), output.text)),
+                    Err(err) => {
+                        return Err(CommandError {
+                            title: String::from("failed to ocr the replied to image"),
+                            hint: Some(err.to_string()),
+                            arg: None,
+                        });
+                    }
+                }
+            }
+
+            if samples.is_empty() && !reply.content.is_empty() {
+                samples.push((String::from("Message"), reply.content.clone()));
+            }
+
+            if samples.is_empty() {
+                return Err(CommandError::new(
+                    "The replied to message has no images or text to test against",
+                ));
+            }
+        }
+
+        trace.point("matching_patterns");
+        let mut patterns = String::new();
+        for (token, rule) in pattern_tokens.iter().zip(&rules) {
+            let hits = samples
+                .iter()
+                .filter(|(_, text)| rule.matches(text))
+                .map(|(label, _)| label.as_str())
+                .collect::<Vec<_>>();
+
+            patterns.push_str(&format!(
+                "`{}`\n-# {}\n",
+                token.raw.replace('`', "'"),
+                if hits.is_empty() {
+                    String::from("No hits")
+                } else {
+                    format!("Hit: {}", hits.join(", "))
+                }
+            ));
+        }
+
+        let header = format!(
+            "**OCR RULE TEST**\n-# Patterns: {} | Samples: {}\n{patterns}",
+            rules.len(),
+            samples.len()
+        );
+        let sample_list = samples
+            .iter()
+            .map(|(label, text)| {
+                format!(
+                    "**{label}**\n```\n{}\n```",
+                    if text.is_empty() {
+                        String::from("(no text)")
+                    } else {
+                        clamp_chars(text.replace('`', "'"), 300)
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut reply = CreateMessage::new()
+            .reference_message(msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if header.len() + sample_list.len() > 4000 {
+            let full = samples
+                .iter()
+                .map(|(label, text)| format!("{label}\n{text}\n"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            reply = reply
+                .add_embed(CreateEmbed::new().description(header).color(BRAND_BLUE))
+                .add_file(CreateAttachment::bytes(full.into_bytes(), "samples.txt"));
+        } else {
+            reply = reply.add_embed(
+                CreateEmbed::new()
+                    .description(format!("{header}\n{sample_list}"))
+                    .color(BRAND_BLUE),
+            );
+        }
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(ctx, reply).await {
+            consume_serenity_error("OCR RULE TEST RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -52,9 +241,12 @@ impl Command for CreateOcrRule {
         The bot will then automatically scan images for the selected strings and take actions automatically. \
         Using slashes at the start and end of a rule will be interpreted as Regex using the Rust Regex crate. \
         Otherwise simple string matching is used (case insensitive). \
+        Regex patterns are limited in size and nesting depth, invalid ones are rejected with the broken part marked. \
         Note that OCR is still fairly inaccurate and can cause false positives, use with caution. \
         The `ocr_check` command runs an image through OCR. This will output a string with those potential inaccuracies. \
-        Use that string for determining rules."
+        Use that string for determining rules. \
+        `create_ocr_rule test <patterns...> -- <samples...>` tries up to 10 patterns against quoted sample strings \
+        and shows which patterns hit which sample. Without samples the images of the replied to message are run through OCR instead."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -82,6 +274,10 @@ impl Command for CreateOcrRule {
         #[transformers::some_string] rule: String,
         trace: &mut TraceContext,
     ) -> Result<(), CommandError> {
+        if name.eq_ignore_ascii_case("test") {
+            return Self::test(&ctx, &msg, handler, &_rule_arg, trace).await;
+        }
+
         if name.len() >= 100 {
             return Err(CommandError {
                 title: String::from("name argument can only be a max of 100 characters long"),
@@ -98,463 +294,9 @@ impl Command for CreateOcrRule {
             });
         }
 
-        let db_id = tinyid().await;
-        let inner = if let Some(stripped) = rule.strip_prefix('/').and_then(|s| s.strip_suffix('/'))
-        {
-            stripped
-        } else {
-            &rule
-        };
-        let is_regex = rule.starts_with('/') && rule.ends_with('/');
-
-        let select_menu = CreateActionRow::SelectMenu(
-            CreateSelectMenu::new(
-                "punishment_type",
-                CreateSelectMenuKind::String {
-                    options: vec![
-                        CreateSelectMenuOption::new("Warn", "warn"),
-                        CreateSelectMenuOption::new("Kick", "kick"),
-                        CreateSelectMenuOption::new("Ban", "ban"),
-                        CreateSelectMenuOption::new("Softban", "softban"),
-                        CreateSelectMenuOption::new("Mute", "mute"),
-                        CreateSelectMenuOption::new("Log Only", "log"),
-                    ],
-                },
-            )
-            .placeholder("Select a punishment type"),
-        );
-        let cancel_button = CreateActionRow::Buttons(vec![
-            CreateButton::new("cancel")
-                .label("Cancel")
-                .style(ButtonStyle::Danger),
-        ]);
-
-        let components = vec![select_menu.clone(), cancel_button.clone()];
-        let disabled_components = vec![
-            CreateActionRow::SelectMenu(
-                CreateSelectMenu::new(
-                    "punishment_type",
-                    CreateSelectMenuKind::String {
-                        options: vec![CreateSelectMenuOption::new("Disabled", "disabled")],
-                    },
-                )
-                .disabled(true),
-            ),
-            CreateActionRow::Buttons(vec![
-                CreateButton::new("cancel")
-                    .label("Cancel")
-                    .style(ButtonStyle::Danger)
-                    .disabled(true),
-            ]),
-        ];
-
-        let reply = CreateMessage::new()
-            .add_embed(
-                CreateEmbed::new()
-                    .description(format!(
-                        "**CREATE OCR RULE**\nPlease select the punishment that will be applied when this rule is triggered.",
-                    ))
-                    .color(BRAND_BLUE),
-            )
-            .components(components.clone())
-            .reference_message(&msg)
-            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
-
-        let mut new_msg = match msg.channel_id.send_message(&ctx, reply).await {
-            Ok(m) => m,
-            Err(err) => {
-                consume_serenity_error(String::from("DLOG RESPONSE"), err);
-                return Ok(());
-            }
-        };
-
-        trace.point("awaiting_user_interaction");
-        'outer: loop {
-            let interaction = match new_msg
-                .await_component_interaction(&ctx.shard)
-                .timeout(Duration::from_secs(60 * 5))
-                .await
-            {
-                Some(i) => i,
-                None => {
-                    let _ = new_msg
-                        .edit(&ctx, EditMessage::new().components(disabled_components))
-                        .await;
-                    return Ok(());
-                }
-            };
-
-            if interaction.user.id != msg.author.id {
-                if let Err(err) = interaction
-                    .create_response(
-                        &ctx,
-                        CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::new()
-                                .content("You are not the author of the original message!")
-                                .ephemeral(true),
-                        ),
-                    )
-                    .await
-                {
-                    consume_serenity_error(
-                        String::from("CREATE OCR RULE INTERACTION RESPONSE"),
-                        err,
-                    );
-                }
-
-                continue;
-            }
-
-            if interaction.data.custom_id.as_str() == "cancel" {
-                let _ = new_msg
-                    .edit(&ctx, EditMessage::new().components(disabled_components))
-                    .await;
-                let _ = interaction
-                    .create_response(
-                        &ctx,
-                        CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::new()
-                                .content("Rule creation cancelled.")
-                                .ephemeral(true),
-                        ),
-                    )
-                    .await;
-                return Ok(());
-            }
-
-            if interaction.data.custom_id.as_str() == "punishment_type" {
-                trace.point("processing_punishment_interaction");
-                let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind
-                else {
-                    continue;
-                };
-
-                let selected = values.first().cloned().unwrap_or_default();
-                if selected.is_empty() {
-                    continue;
-                }
-
-                let modal = match selected.as_str() {
-                    "warn" | "kick" => {
-                        CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
-                            .components(vec![
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Paragraph,
-                                        "Reason",
-                                        "reason",
-                                    )
-                                    .required(false)
-                                    .max_length(500),
-                                ),
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Short,
-                                        "Silent (y/n)",
-                                        "silent",
-                                    )
-                                    .required(false),
-                                ),
-                            ])
-                    }
-                    "ban" | "softban" => {
-                        let mut components = vec![
-                            CreateActionRow::InputText(
-                                CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
-                                    .required(false)
-                                    .max_length(500),
-                            ),
-                            CreateActionRow::InputText(
-                                CreateInputText::new(
-                                    InputTextStyle::Short,
-                                    "Clear Days (0-7)",
-                                    "days",
-                                )
-                                .required(false),
-                            ),
-                            CreateActionRow::InputText(
-                                CreateInputText::new(
-                                    InputTextStyle::Short,
-                                    "Silent (y/n)",
-                                    "silent",
-                                )
-                                .required(false),
-                            ),
-                        ];
-                        if selected == "ban" {
-                            components.insert(
-                                1,
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Short,
-                                        "Duration (e.g. 10m, 1d)",
-                                        "duration",
-                                    )
-                                    .required(false),
-                                ),
-                            );
-                        }
-                        CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
-                            .components(components)
-                    }
-                    "mute" => {
-                        CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
-                            .components(vec![
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Paragraph,
-                                        "Reason",
-                                        "reason",
-                                    )
-                                    .required(false)
-                                    .max_length(500),
-                                ),
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Short,
-                                        "Duration (e.g. 10m, 1d)",
-                                        "duration",
-                                    )
-                                    .required(true),
-                                ),
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Short,
-                                        "Silent (y/n)",
-                                        "silent",
-                                    )
-                                    .required(false),
-                                ),
-                            ])
-                    }
-                    "log" => {
-                        CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
-                            .components(vec![
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Paragraph,
-                                        "Reason",
-                                        "reason",
-                                    )
-                                    .required(false)
-                                    .max_length(500),
-                                ),
-                                CreateActionRow::InputText(
-                                    CreateInputText::new(
-                                        InputTextStyle::Short,
-                                        "Log Channel ID",
-                                        "channel_id",
-                                    )
-                                    .required(true),
-                                ),
-                            ])
-                    }
-                    _ => continue,
-                };
+        super::rule_creation::validate_pattern(&msg, handler, &rule, &_rule_arg, OCR_PATTERN_HINT)?;
 
-                if let Err(err) = interaction
-                    .create_response(&ctx, CreateInteractionResponse::Modal(modal))
-                    .await
-                {
-                    consume_serenity_error("CREATE OCR RULE MODAL CREATE".into(), err);
-                    break;
-                }
-
-                let _ = new_msg
-                    .edit(
-                        &ctx,
-                        EditMessage::new().components(disabled_components.clone()),
-                    )
-                    .await;
-
-                loop {
-                    let interaction = match new_msg
-                        .await_modal_interaction(&ctx.shard)
-                        .timeout(Duration::from_secs(600))
-                        .await
-                    {
-                        Some(s) => s,
-                        None => {
-                            break;
-                        }
-                    };
-
-                    if interaction.data.custom_id != format!("{}-modal", selected) {
-                        continue 'outer;
-                    }
-
-                    let mut reason = String::from("No reason provided");
-                    let mut days = 1u8;
-                    let mut silent = false;
-                    let mut duration = 0i64;
-                    let mut channel_id = 0i64;
-
-                    for component in &interaction.data.components {
-                        let Some(component) = component.components.first() else {
-                            continue;
-                        };
-
-                        match component {
-                            ActionRowComponent::InputText(component)
-                                if component.custom_id == "reason" =>
-                            {
-                                reason = component
-                                    .value
-                                    .clone()
-                                    .unwrap_or(String::from("No reason provided"));
-                            }
-                            ActionRowComponent::InputText(component)
-                                if component.custom_id == "days" =>
-                            {
-                                let str = component.value.clone().unwrap_or_default();
-                                let mut iter = crate::lexer::lex(str).into_iter().peekable();
-                                if let Ok(token) = Transformers::i32(&ctx, &msg, &mut iter).await {
-                                    if let Some(CommandArgument::i32(n)) = token.contents {
-                                        days = (n as u8).clamp(0, 7);
-                                    }
-                                }
-                            }
-                            ActionRowComponent::InputText(component)
-                                if component.custom_id == "silent" =>
-                            {
-                                let str = component.value.clone().unwrap_or_default();
-                                let mut iter = crate::lexer::lex(str).into_iter().peekable();
-                                if let Ok(token) = Transformers::bool(&ctx, &msg, &mut iter).await {
-                                    if let Some(CommandArgument::bool(b)) = token.contents {
-                                        silent = b;
-                                    }
-                                }
-                            }
-                            ActionRowComponent::InputText(component)
-                                if component.custom_id == "duration" =>
-                            {
-                                let str = component.value.clone().unwrap_or_default();
-                                let mut iter = crate::lexer::lex(str).into_iter().peekable();
-                                if let Ok(token) =
-                                    Transformers::duration(&ctx, &msg, &mut iter).await
-                                {
-                                    if let Some(CommandArgument::Duration(d)) = token.contents {
-                                        duration = d.num_seconds();
-                                    }
-                                }
-                            }
-                            ActionRowComponent::InputText(component)
-                                if component.custom_id == "channel_id" =>
-                            {
-                                let str = component.value.clone().unwrap_or_default();
-                                let mut iter = crate::lexer::lex(str).into_iter().peekable();
-                                if let Ok(token) =
-                                    Transformers::guild_channel(&ctx, &msg, &mut iter).await
-                                {
-                                    if let Some(CommandArgument::GuildChannel(c)) = token.contents {
-                                        channel_id = c.id.get() as i64;
-                                    }
-                                }
-                            }
-                            _ => continue,
-                        };
-                    }
-
-                    trace.point("updating_database");
-                    if let Err(err) = sqlx::query(
-                        "INSERT INTO automod_rules \
-                         (id, guild_id, name, type, rule, is_regex, reason, \
-                          punishment_type, day_clear_amount, duration, silent, log_channel_id) \
-                         VALUES ($1, $2, $3, 'ocr', $4, $5, $6, CAST($7 AS action_type), $8, $9, $10, $11)"
-                    )
-                    .bind(db_id.clone())
-                    .bind(msg.guild_id.map(|id| id.get()).unwrap_or(1) as i64)
-                    .bind(name.clone())
-                    .bind(inner)
-                    .bind(is_regex)
-                    .bind(reason.clone())
-                    .bind(selected.clone())
-                    .bind(days as i16)
-                    .bind(duration)
-                    .bind(silent)
-                    .bind(channel_id)
-                    .execute(&*SQL).await {
-                        consume_pgsql_error("OCR RULE CREATE".into(), err);
-                        return Err(CommandError {
-                            title: String::from("Could not update the database"),
-                            hint: None,
-                            arg: None,
-                        });
-                    }
-
-                    {
-                        let mut lock = handler.rule_cache.lock().await;
-                        let punish = match selected.as_str() {
-                            "ban" => Punishment::Ban {
-                                reason: reason.clone(),
-                                day_clear_amount: days,
-                                duration: duration as u64,
-                                silent,
-                            },
-                            "softban" => Punishment::Softban {
-                                reason: reason.clone(),
-                                day_clear_amount: days,
-                                silent,
-                            },
-                            "kick" => Punishment::Kick {
-                                reason: reason.clone(),
-                                silent,
-                            },
-                            "mute" => Punishment::Mute {
-                                reason: reason.clone(),
-                                duration: duration as u64,
-                                silent,
-                            },
-                            "log" => Punishment::Log {
-                                reason: reason.clone(),
-                                channel_id: channel_id as u64,
-                            },
-                            _ => Punishment::Warn {
-                                reason: reason.clone(),
-                                silent,
-                            },
-                        };
-                        lock.insert_ocr(Rule {
-                            name: name.clone(),
-                            id: db_id.clone(),
-                            pattern: inner.to_string(),
-                            is_regex,
-                            guild_id: msg.guild_id.map(|id| id.get()).unwrap_or(1),
-                            punishment: punish,
-                        })
-                    }
-
-                    let reply = CreateInteractionResponseMessage::new()
-                        .add_embed(
-                            CreateEmbed::new()
-                                .description(format!(
-                                    "**CREATED OCR RULE {}**\n-# ID: `{}` | Type: {} | Reason: {}\n```\n{}\n```",
-                                    name.to_uppercase(),
-                                    db_id,
-                                    selected,
-                                    clamp_chars(reason, 25),
-                                    rule
-                                ))
-                                .color(BRAND_BLUE)
-                        )
-                        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
-
-                    if let Err(err) = interaction
-                        .create_response(&ctx, CreateInteractionResponse::Message(reply))
-                        .await
-                    {
-                        consume_serenity_error("SEND CREATE OCR RULE RESPONSE".into(), err);
-                        return Ok(());
-                    }
-
-                    break;
-                }
-
-                break;
-            }
-        }
-
-        Ok(())
+        super::rule_creation::create_rule(&ctx, &msg, handler, "ocr", name, rule, trace).await
     }
 
     fn get_permissions(&self) -> CommandPermissions {
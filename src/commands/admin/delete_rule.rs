@@ -38,7 +38,8 @@ impl Command for DeleteRule {
     }
 
     fn get_full(&self) -> &'static str {
-        "Deletes an existing automoderation rule. Run the rules command to list all existing rules and their IDs."
+        "Deletes an existing automoderation rule. Run the rules command to list all existing rules and their IDs. \
+        The hits recorded for the rule are kept as part of the moderation history."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
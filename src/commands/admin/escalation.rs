@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    moderation::escalation::{ESCALATION_PUNISHMENTS, get_escalation_steps},
+    transformers::Transformers,
+    utils::{consume_pgsql_error, consume_serenity_error, time_string, tinyid},
+};
+use aegis_macros::command;
+
+pub struct Escalation;
+
+impl Escalation {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Escalation {
+    fn get_name(&self) -> &'static str {
+        "escalation"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Manages the servers punishment escalation ladder"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Manages the servers punishment escalation ladder. \
+        Each step applies a punishment once a member reaches the given amount of active warns within a period, \
+        e.g. `escalation add 3 30d mute 1d` mutes a member for a day on their third warn within 30 days. \
+        Steps are checked on every new warn, including warns issued by automod rules. \
+        Available punishments: mute, kick, softban and ban. \
+        Use `escalation remove <id>` to delete a step, or run the command without arguments to list the ladder."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("add/remove", false),
+            CommandSyntax::Or(
+                Box::new(CommandSyntax::Number("warns", false)),
+                Box::new(CommandSyntax::String("id", false)),
+            ),
+            CommandSyntax::Duration("period", false),
+            CommandSyntax::String("punishment", false),
+            CommandSyntax::Duration("duration", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::string] subcommand: Option<String>,
+        #[transformers::string] target: Option<String>,
+        #[transformers::string] period: Option<String>,
+        #[transformers::string] punishment: Option<String>,
+        #[transformers::string] duration: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let description = match subcommand.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("list") => {
+                trace.point("fetching_steps");
+                let steps = match get_escalation_steps(guild_id).await {
+                    Ok(s) => s,
+                    Err(err) => {
+                        consume_pgsql_error("ESCALATION LIST".into(), err);
+                        return Err(CommandError::new("Could not fetch escalation steps"));
+                    }
+                };
+
+                if steps.is_empty() {
+                    String::from("**ESCALATION LADDER**\n-# No steps defined")
+                } else {
+                    let lines = steps
+                        .iter()
+                        .map(|s| format!("`{}` {}", s.id, s.describe()))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    format!("**ESCALATION LADDER**\n-# Steps: {}\n{lines}", steps.len())
+                }
+            }
+            Some("add") => {
+                let warn_count = match target.as_deref().map(str::parse::<i32>) {
+                    Some(Ok(n)) if n > 0 => n,
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Expected a positive amount of warns"),
+                            hint: Some(String::from("e.g. `escalation add 3 30d mute 1d`")),
+                            arg: _target_arg,
+                        });
+                    }
+                };
+
+                let (Some(_), Some(period_token)) = (&period, _period_arg) else {
+                    return Err(CommandError::new("Missing period argument"));
+                };
+                let period = match Transformers::duration(
+                    &ctx,
+                    &msg,
+                    &mut vec![period_token].into_iter().peekable(),
+                )
+                .await
+                {
+                    Ok(Token {
+                        contents: Some(CommandArgument::Duration(d)),
+                        ..
+                    }) if !d.is_zero() => d,
+                    Err(TransformerError::CommandError(err)) => return Err(err),
+                    _ => return Err(CommandError::new("Period must be longer than 0 seconds")),
+                };
+
+                let punishment_type = punishment.unwrap_or_default().to_lowercase();
+                if !ESCALATION_PUNISHMENTS.contains(&punishment_type.as_str()) {
+                    return Err(CommandError {
+                        title: String::from("Unknown punishment"),
+                        hint: Some(format!("use one of {}", ESCALATION_PUNISHMENTS.join(", "))),
+                        arg: _punishment_arg,
+                    });
+                }
+
+                let duration = match _duration_arg {
+                    Some(token)
+                        if duration.is_some()
+                            && matches!(punishment_type.as_str(), "mute" | "ban") =>
+                    {
+                        match Transformers::duration(
+                            &ctx,
+                            &msg,
+                            &mut vec![token].into_iter().peekable(),
+                        )
+                        .await
+                        {
+                            Ok(Token {
+                                contents: Some(CommandArgument::Duration(d)),
+                                ..
+                            }) => d,
+                            Err(TransformerError::CommandError(err)) => return Err(err),
+                            _ => chrono::Duration::zero(),
+                        }
+                    }
+                    _ => chrono::Duration::zero(),
+                };
+
+                let db_id = tinyid().await;
+
+                trace.point("inserting_step");
+                if let Err(err) = sqlx::query(
+                    "INSERT INTO escalation_steps (id, guild_id, warn_count, period, punishment_type, duration) VALUES ($1, $2, $3, $4, $5::action_type, $6)",
+                )
+                .bind(&db_id)
+                .bind(guild_id.get() as i64)
+                .bind(warn_count)
+                .bind(period.num_seconds())
+                .bind(&punishment_type)
+                .bind(duration.num_seconds())
+                .execute(&*SQL)
+                .await
+                {
+                    if let sqlx::Error::Database(ref db_err) = err
+                        && db_err.is_unique_violation()
+                    {
+                        return Err(CommandError {
+                            title: format!("A step for {warn_count} warns already exists"),
+                            hint: Some(String::from("remove the existing step first")),
+                            arg: _target_arg,
+                        });
+                    }
+                    consume_pgsql_error("ESCALATION ADD".into(), err);
+                    return Err(CommandError::new("Could not create escalation step"));
+                }
+
+                let action = match punishment_type.as_str() {
+                    "mute" | "ban" => format!("{punishment_type} {}", time_string(duration)),
+                    _ => punishment_type.clone(),
+                };
+
+                format!(
+                    "**ESCALATION STEP CREATED**\n-# ID: `{db_id}`\n{warn_count} warns within {} → {action}",
+                    time_string(period).trim_start_matches("for ")
+                )
+            }
+            Some("remove") => {
+                let Some(id) = target else {
+                    return Err(CommandError::new("Missing id argument"));
+                };
+
+                trace.point("deleting_step");
+                let res =
+                    sqlx::query("DELETE FROM escalation_steps WHERE id = $1 AND guild_id = $2")
+                        .bind(&id)
+                        .bind(guild_id.get() as i64)
+                        .execute(&*SQL)
+                        .await;
+
+                match res {
+                    Ok(r) if r.rows_affected() == 0 => {
+                        return Err(CommandError {
+                            title: String::from("Escalation step not found"),
+                            hint: Some(String::from(
+                                "run the command without arguments for a list of steps",
+                            )),
+                            arg: _target_arg,
+                        });
+                    }
+                    Ok(_) => format!("**ESCALATION STEP DELETED**\n-# ID: `{id}`"),
+                    Err(err) => {
+                        consume_pgsql_error("ESCALATION REMOVE".into(), err);
+                        return Err(CommandError::new("Could not delete escalation step"));
+                    }
+                }
+            }
+            Some(_) => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("use add, remove or list")),
+                    arg: _subcommand_arg,
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("ESCALATION RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage, Message,
+        Permissions,
+    },
+    async_trait,
+};
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{consume_pgsql_error, consume_serenity_error},
+};
+use aegis_macros::command;
+
+use super::rule_transfer::{RuleFile, fetch_rule_entries};
+
+pub struct ExportRules;
+
+impl ExportRules {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for ExportRules {
+    fn get_name(&self) -> &'static str {
+        "export_rules"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Exports the servers automod rules to a file"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Exports all automod rules of the server as a JSON (default) or TOML file. \
+        The file contains the name, type, pattern and punishment of every rule and can be loaded into another server with `import_rules`. \
+        Rule exemptions are not exported since roles, users and channels differ between servers."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![CommandSyntax::String("json/toml", false)]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::string] format: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap().get();
+        let format = format
+            .map(|f| f.to_lowercase())
+            .unwrap_or(String::from("json"));
+
+        if format != "json" && format != "toml" {
+            return Err(CommandError {
+                title: String::from("Unknown format"),
+                hint: Some(String::from("use json or toml")),
+                arg: _format_arg,
+            });
+        }
+
+        trace.point("fetching_rules");
+        let rules = match fetch_rule_entries(guild_id).await {
+            Ok(r) => r.into_iter().map(|(_, entry)| entry).collect::<Vec<_>>(),
+            Err(err) => {
+                consume_pgsql_error("EXPORT RULES".into(), err);
+                return Err(CommandError::new("Could not query the database"));
+            }
+        };
+
+        if rules.is_empty() {
+            return Err(CommandError {
+                title: String::from("This server has no automod rules"),
+                hint: Some(String::from(
+                    "create one with create_text_rule or create_ocr_rule",
+                )),
+                arg: None,
+            });
+        }
+
+        let count = rules.len();
+        let file = RuleFile { rules };
+        let contents = if format == "toml" {
+            toml::to_string_pretty(&file).map_err(|err| err.to_string())
+        } else {
+            serde_json::to_string_pretty(&file).map_err(|err| err.to_string())
+        };
+
+        let contents = match contents {
+            Ok(c) => c,
+            Err(err) => {
+                return Err(CommandError {
+                    title: String::from("Could not serialize the rules"),
+                    hint: Some(err),
+                    arg: None,
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**RULES EXPORTED**\n-# Rules: {count} | Format: {format}"
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .add_file(CreateAttachment::bytes(
+                contents.into_bytes(),
+                format!("rules_{guild_id}.{format}"),
+            ))
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("EXPORT RULES RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
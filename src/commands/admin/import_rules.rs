@@ -0,0 +1,538 @@
+use std::{collections::HashMap, time::Duration};
+
+use serenity::{
+    all::{
+        ButtonStyle, Context, CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbed,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
+        Message, Permissions,
+    },
+    async_trait,
+};
+use sqlx::types::Json;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    utils::{
+        clamp_chars, consume_pgsql_error, consume_serenity_error,
+        rule_cache::{Rule, RuleExemptions},
+        tinyid,
+    },
+};
+use aegis_macros::command;
+
+use super::rule_transfer::{RuleEntry, RuleFile, fetch_rule_entries};
+
+const MAX_RULE_FILE_SIZE: u32 = 1024 * 1024;
+
+pub struct ImportRules;
+
+/// The canonical step names, so differently ordered or cased names in the file are stored the same way
+fn normalization_names(entry: &RuleEntry) -> Vec<String> {
+    entry
+        .normalization()
+        .iter()
+        .map(|step| step.name().to_string())
+        .collect()
+}
+
+impl ImportRules {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[allow(clippy::result_large_err)]
+    fn parse(filename: &str, contents: &str) -> Result<RuleFile, CommandError> {
+        let parsed = if filename.to_lowercase().ends_with(".toml") {
+            toml::from_str::<RuleFile>(contents).map_err(|err| err.to_string())
+        } else {
+            serde_json::from_str::<RuleFile>(contents).map_err(|err| err.to_string())
+        };
+
+        parsed.map_err(|err| CommandError {
+            title: String::from("Could not parse the rules file"),
+            hint: Some(clamp_chars(err, 200)),
+            arg: None,
+        })
+    }
+
+    fn cached_rule(
+        id: String,
+        guild_id: u64,
+        entry: &RuleEntry,
+        exemptions: RuleExemptions,
+    ) -> Rule {
+        let (pattern, is_regex) = entry.split_pattern();
+
+        Rule {
+            name: entry.name.clone(),
+            id,
+            pattern: pattern.to_string(),
+            is_regex,
+            guild_id,
+            punishment: entry.punishment(),
+            exemptions,
+            normalization: entry.normalization(),
+            cooldown: entry.cooldown(),
+            condition: entry.condition(),
+            fuzzy: entry.kind == "ocr",
+            regexes: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for ImportRules {
+    fn get_name(&self) -> &'static str {
+        "import_rules"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Imports automod rules from a file"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Imports automod rules from an attached JSON or TOML file created with `export_rules`. \
+        Rules are matched to the existing ones by type and name, new rules are added and changed rules are updated. \
+        Rules of the server that are missing from the file are kept. \
+        All rules are validated and a diff is shown which has to be confirmed before anything is changed."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let Some(attachment) = msg.attachments.first() else {
+            return Err(CommandError {
+                title: String::from("Missing rules file"),
+                hint: Some(String::from("attach a file created with export_rules")),
+                arg: None,
+            });
+        };
+
+        if attachment.size > MAX_RULE_FILE_SIZE {
+            return Err(CommandError::new("The rules file can be at most 1 MB"));
+        }
+
+        trace.point("fetching_file");
+        let contents = match reqwest::get(attachment.url.clone()).await {
+            Ok(res) => res.text().await.ok(),
+            Err(_) => None,
+        };
+        let Some(contents) = contents else {
+            return Err(CommandError::new("Failed to download the rules file"));
+        };
+
+        let mut file = Self::parse(&attachment.filename, &contents)?;
+        if file.rules.is_empty() {
+            return Err(CommandError::new(
+                "The rules file does not contain any rules",
+            ));
+        }
+
+        trace.point("validating_rules");
+        let channels = guild_id.channels(&ctx).await.unwrap_or_default();
+        let mut errors = vec![];
+
+        for (i, entry) in file.rules.iter().enumerate() {
+            let result = entry.validate().and_then(|_| match entry.log_channel_id {
+                Some(id) if !channels.keys().any(|c| c.get() == id) => {
+                    Err(format!("log channel `{id}` does not exist in this server"))
+                }
+                _ => Ok(()),
+            });
+
+            let duplicate = file.rules[..i]
+                .iter()
+                .any(|other| other.kind == entry.kind && other.name == entry.name);
+
+            match result {
+                Err(err) => errors.push(format!("`{}`: {err}", entry.name)),
+                Ok(_) if duplicate => errors.push(format!(
+                    "`{}`: there is another {} rule with the same name",
+                    entry.name, entry.kind
+                )),
+                Ok(_) => {}
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(CommandError {
+                title: format!("The rules file contains {} invalid rules", errors.len()),
+                hint: Some(clamp_chars(errors.join(", "), 300)),
+                arg: None,
+            });
+        }
+
+        for entry in &mut file.rules {
+            entry.normalization = normalization_names(entry);
+            entry.condition = entry.condition().map(|c| c.describe());
+        }
+
+        trace.point("fetching_rules");
+        let existing = match fetch_rule_entries(guild_id.get()).await {
+            Ok(r) => r,
+            Err(err) => {
+                consume_pgsql_error("IMPORT RULES FETCH".into(), err);
+                return Err(CommandError::new("Could not query the database"));
+            }
+        };
+
+        let mut added = vec![];
+        let mut changed = vec![];
+        let mut unchanged = 0;
+
+        for entry in file.rules {
+            match existing
+                .iter()
+                .find(|(_, old)| old.kind == entry.kind && old.name == entry.name)
+            {
+                None => added.push(entry),
+                Some((id, old)) if *old != entry => changed.push((id.clone(), old, entry)),
+                Some(_) => unchanged += 1,
+            }
+        }
+
+        let stats = format!(
+            "Added: {} | Changed: {} | Unchanged: {unchanged}",
+            added.len(),
+            changed.len()
+        );
+
+        if added.is_empty() && changed.is_empty() {
+            let reply = CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!("**RULES UP TO DATE**\n-# {stats}"))
+                        .color(BRAND_BLUE),
+                )
+                .reference_message(&msg)
+                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+                consume_serenity_error("IMPORT RULES RESPONSE".into(), err);
+            }
+
+            return Ok(());
+        }
+
+        let diff = added
+            .iter()
+            .map(|entry| format!("+ {}", entry.describe()))
+            .chain(
+                changed
+                    .iter()
+                    .map(|(_, old, new)| format!("- {}\n+ {}", old.describe(), new.describe())),
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+            .replace("```", "\\`\\`\\`");
+
+        let buttons = |disabled: bool| {
+            vec![CreateActionRow::Buttons(vec![
+                CreateButton::new("import")
+                    .label("Import")
+                    .style(ButtonStyle::Success)
+                    .disabled(disabled),
+                CreateButton::new("cancel")
+                    .label("Cancel")
+                    .style(ButtonStyle::Danger)
+                    .disabled(disabled),
+            ])]
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**IMPORT RULES**\n-# {stats}\n-# Rules missing from the file are kept\n```diff\n{}\n```",
+                        clamp_chars(diff, 3500)
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .components(buttons(false))
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        let mut new_msg = match msg.channel_id.send_message(&ctx, reply).await {
+            Ok(m) => m,
+            Err(err) => {
+                consume_serenity_error("IMPORT RULES RESPONSE".into(), err);
+                return Ok(());
+            }
+        };
+
+        trace.point("awaiting_user_interaction");
+        let interaction = loop {
+            let Some(interaction) = new_msg
+                .await_component_interaction(&ctx.shard)
+                .timeout(Duration::from_secs(60 * 5))
+                .await
+            else {
+                let _ = new_msg
+                    .edit(&ctx, EditMessage::new().components(buttons(true)))
+                    .await;
+                return Ok(());
+            };
+
+            if interaction.user.id != msg.author.id {
+                if let Err(err) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("You are not the author of the original message!")
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await
+                {
+                    consume_serenity_error("IMPORT RULES INTERACTION RESPONSE".into(), err);
+                }
+
+                continue;
+            }
+
+            break interaction;
+        };
+
+        if interaction.data.custom_id.as_str() != "import" {
+            let _ = interaction
+                .create_response(
+                    &ctx,
+                    CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::new()
+                            .embed(
+                                CreateEmbed::new()
+                                    .description(format!("**IMPORT CANCELLED**\n-# {stats}"))
+                                    .color(BRAND_BLUE),
+                            )
+                            .components(buttons(true)),
+                    ),
+                )
+                .await;
+            return Ok(());
+        }
+
+        trace.point("updating_database");
+        let mut ids = vec![];
+        for _ in &added {
+            ids.push(tinyid().await);
+        }
+
+        let result = async {
+            let mut tx = SQL.begin().await?;
+
+            for (id, entry) in ids.iter().zip(&added) {
+                let (pattern, is_regex) = entry.split_pattern();
+                sqlx::query(
+                    "INSERT INTO automod_rules \
+                     (id, guild_id, name, type, rule, is_regex, reason, \
+                      punishment_type, day_clear_amount, duration, silent, log_channel_id, normalization, \
+                      debounce, max_punishments, punishment_period, delete_during_cooldown, condition) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, CAST($8 AS action_type), $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)",
+                )
+                .bind(id)
+                .bind(guild_id.get() as i64)
+                .bind(&entry.name)
+                .bind(&entry.kind)
+                .bind(pattern)
+                .bind(is_regex)
+                .bind(&entry.reason)
+                .bind(&entry.punishment)
+                .bind(entry.day_clear_amount as i16)
+                .bind(entry.duration as i64)
+                .bind(entry.silent)
+                .bind(entry.log_channel_id.unwrap_or(0) as i64)
+                .bind(&entry.normalization)
+                .bind(entry.debounce as i32)
+                .bind(entry.max_punishments as i32)
+                .bind(entry.punishment_period as i64)
+                .bind(entry.delete_during_cooldown)
+                .bind(entry.condition().map(Json))
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            for (id, _, entry) in &changed {
+                let (pattern, is_regex) = entry.split_pattern();
+                sqlx::query(
+                    "UPDATE automod_rules SET rule = $1, is_regex = $2, reason = $3, \
+                     punishment_type = CAST($4 AS action_type), day_clear_amount = $5, duration = $6, \
+                     silent = $7, log_channel_id = $8, normalization = $9, debounce = $10, max_punishments = $11, \
+                     punishment_period = $12, delete_during_cooldown = $13, condition = $14 WHERE id = $15",
+                )
+                .bind(pattern)
+                .bind(is_regex)
+                .bind(&entry.reason)
+                .bind(&entry.punishment)
+                .bind(entry.day_clear_amount as i16)
+                .bind(entry.duration as i64)
+                .bind(entry.silent)
+                .bind(entry.log_channel_id.unwrap_or(0) as i64)
+                .bind(&entry.normalization)
+                .bind(entry.debounce as i32)
+                .bind(entry.max_punishments as i32)
+                .bind(entry.punishment_period as i64)
+                .bind(entry.delete_during_cooldown)
+                .bind(entry.condition().map(Json))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+
+                // images matched by the old pattern have to be checked against the new one
+                sqlx::query("DELETE FROM ocr_image_hashes WHERE rule_id = $1")
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+
+            tx.commit().await
+        }
+        .await;
+
+        if let Err(err) = result {
+            consume_pgsql_error("IMPORT RULES".into(), err);
+            let _ = new_msg
+                .edit(&ctx, EditMessage::new().components(buttons(true)))
+                .await;
+            return Err(CommandError::new("Could not update the database"));
+        }
+
+        {
+            let mut lock = handler.rule_cache.lock().await;
+
+            for (id, entry) in ids.into_iter().zip(&added) {
+                lock.insert(
+                    &entry.kind,
+                    Self::cached_rule(id, guild_id.get(), entry, RuleExemptions::default()),
+                );
+            }
+
+            // removing also drops the image hashes matched by the old pattern
+            for (id, _, entry) in &changed {
+                let exemptions = lock
+                    .get_by_id(id)
+                    .map(|r| r.exemptions.clone())
+                    .unwrap_or_default();
+                lock.remove(id);
+                lock.insert(
+                    &entry.kind,
+                    Self::cached_rule(id.clone(), guild_id.get(), entry, exemptions),
+                );
+            }
+        }
+
+        if let Err(err) = interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(
+                            CreateEmbed::new()
+                                .description(format!("**RULES IMPORTED**\n-# {stats}"))
+                                .color(BRAND_BLUE),
+                        )
+                        .components(vec![]),
+                ),
+            )
+            .await
+        {
+            consume_serenity_error("IMPORT RULES RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_and_toml() {
+        let json = r#"{"rules": [{"name": "nitro", "type": "text", "pattern": "/n[i1]tro/", "punishment": "kick"}]}"#;
+        let toml = "[[rules]]\nname = \"nitro\"\ntype = \"text\"\npattern = \"/n[i1]tro/\"\npunishment = \"kick\"\n";
+
+        let from_json = ImportRules::parse("rules.json", json).unwrap();
+        let from_toml = ImportRules::parse("RULES.TOML", toml).unwrap();
+        assert_eq!(from_json.rules, from_toml.rules);
+
+        let entry = &from_json.rules[0];
+        assert_eq!(entry.debounce, 15);
+        assert!(entry.delete_during_cooldown);
+        assert_eq!(
+            entry.normalization,
+            vec!["invisible", "width", "diacritics", "confusables"]
+        );
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        let err = ImportRules::parse("rules.json", "{\"rules\": [{\"name\": 1}]}").unwrap_err();
+        assert_eq!(err.title, "Could not parse the rules file");
+        assert!(err.hint.is_some());
+
+        assert!(ImportRules::parse("rules.toml", "{\"rules\": []}").is_err());
+        assert!(
+            ImportRules::parse("rules.txt", "{}")
+                .unwrap()
+                .rules
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn builds_cached_rules() {
+        let file = ImportRules::parse(
+            "rules.json",
+            r#"{"rules": [{"name": "nitro", "type": "ocr", "pattern": "/n[i1]tro/", "punishment": "mute", "duration": 60, "condition": "/n[i1]tro/ AND gift"}]}"#,
+        )
+        .unwrap();
+
+        let rule = ImportRules::cached_rule(
+            String::from("id"),
+            1,
+            &file.rules[0],
+            RuleExemptions::default(),
+        );
+        assert_eq!(rule.pattern, "n[i1]tro");
+        assert!(rule.is_regex);
+        assert!(rule.fuzzy);
+        assert_eq!(rule.punishment.kind(), "mute");
+        assert!(rule.condition.is_some());
+    }
+}
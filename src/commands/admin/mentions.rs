@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage, Message, Permissions,
+    },
+    async_trait,
+};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        clamp_chars, consume_pgsql_error, consume_serenity_error, mention_cache::MentionFilter,
+    },
+};
+use aegis_macros::command;
+
+pub struct Mentions;
+
+impl Mentions {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Mentions {
+    fn get_name(&self) -> &'static str {
+        "mentions"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures the servers mass mention filter"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures the servers mass mention filter. \
+        `mentions set <message> <period limit> <period>` triggers once a member mentions `message` unique users or roles in a single message, \
+        or `period limit` across their messages within `period`. An `@everyone` or `@here` attempt counts as one mention. \
+        Use 0 to turn a single check off, e.g. `mentions set 8 15 30s`. \
+        The offending message is deleted and you will be asked for the punishment to apply. \
+        `mentions disable` turns the filter off, running the command without arguments shows the current configuration. \
+        Members with the manage messages permission are exempt."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("set/disable", false),
+            CommandSyntax::Number("message", false),
+            CommandSyntax::Number("period limit", false),
+            CommandSyntax::Duration("period", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::string] subcommand: Option<String>,
+        #[transformers::i32] message_limit: Option<i32>,
+        #[transformers::i32] window_limit: Option<i32>,
+        #[transformers::string] period: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let description = match subcommand.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("show") => {
+                let filter = handler.mention_cache.lock().await.get(guild_id.get());
+                match filter {
+                    Some(f) => format!(
+                        "**MENTION FILTER**\n-# {} | Type: {} | Reason: {}",
+                        f.describe(),
+                        f.punishment.kind(),
+                        clamp_chars(f.punishment.reason().to_string(), 25)
+                    ),
+                    None => String::from("**MENTION FILTER**\n-# Disabled"),
+                }
+            }
+            Some("disable") => {
+                trace.point("deleting_filter");
+                if let Err(err) = sqlx::query("DELETE FROM mention_filters WHERE guild_id = $1")
+                    .bind(guild_id.get() as i64)
+                    .execute(&*SQL)
+                    .await
+                {
+                    consume_pgsql_error("MENTIONS DISABLE".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+
+                handler.mention_cache.lock().await.remove(guild_id.get());
+                String::from("**MENTION FILTER DISABLED**")
+            }
+            Some("set") => {
+                let limits = [
+                    (message_limit, &_message_limit_arg),
+                    (window_limit, &_window_limit_arg),
+                ];
+                for (limit, arg) in &limits {
+                    if !limit.is_some_and(|n| n >= 0) {
+                        return Err(CommandError {
+                            title: String::from("Expected a limit of 0 or more"),
+                            hint: Some(String::from("e.g. `mentions set 8 15 30s`")),
+                            arg: (*arg).clone(),
+                        });
+                    }
+                }
+
+                let (Some(_), Some(period_token)) = (&period, _period_arg) else {
+                    return Err(CommandError::new("Missing period argument"));
+                };
+                let period = match Transformers::duration(
+                    &ctx,
+                    &msg,
+                    &mut vec![period_token].into_iter().peekable(),
+                )
+                .await
+                {
+                    Ok(Token {
+                        contents: Some(CommandArgument::Duration(d)),
+                        ..
+                    }) if d.num_seconds() > 0 && d.num_seconds() <= 3600 => d,
+                    Err(TransformerError::CommandError(err)) => return Err(err),
+                    _ => {
+                        return Err(CommandError::new(
+                            "Period must be between 1 second and 1 hour",
+                        ));
+                    }
+                };
+
+                let (message_limit, window_limit) =
+                    (message_limit.unwrap_or(0), window_limit.unwrap_or(0));
+
+                if message_limit == 0 && window_limit == 0 {
+                    return Err(CommandError {
+                        title: String::from("At least one limit has to be enabled"),
+                        hint: Some(String::from(
+                            "use `mentions disable` to turn the filter off",
+                        )),
+                        arg: None,
+                    });
+                }
+
+                let Some(selection) = super::punishment_select::select_punishment(
+                    &ctx,
+                    &msg,
+                    "MENTION FILTER",
+                    "the mention filter",
+                    trace,
+                )
+                .await?
+                else {
+                    return Ok(());
+                };
+
+                trace.point("updating_database");
+                if let Err(err) = sqlx::query(
+                    "INSERT INTO mention_filters \
+                     (guild_id, message_limit, window_limit, period, reason, \
+                      punishment_type, day_clear_amount, duration, silent, log_channel_id) \
+                     VALUES ($1, $2, $3, $4, $5, CAST($6 AS action_type), $7, $8, $9, $10) \
+                     ON CONFLICT (guild_id) DO UPDATE SET \
+                     message_limit = $2, window_limit = $3, period = $4, reason = $5, \
+                     punishment_type = CAST($6 AS action_type), day_clear_amount = $7, duration = $8, silent = $9, log_channel_id = $10",
+                )
+                .bind(guild_id.get() as i64)
+                .bind(message_limit)
+                .bind(window_limit)
+                .bind(period.num_seconds() as i32)
+                .bind(selection.reason.clone())
+                .bind(selection.kind.clone())
+                .bind(selection.days as i16)
+                .bind(selection.duration)
+                .bind(selection.silent)
+                .bind(selection.channel_id)
+                .execute(&*SQL)
+                .await
+                {
+                    consume_pgsql_error("MENTIONS SET".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+
+                let filter = MentionFilter {
+                    message_limit: message_limit as u32,
+                    window_limit: window_limit as u32,
+                    period: period.to_std().unwrap_or_default(),
+                    punishment: selection.punishment(),
+                };
+                let details = filter.describe();
+                handler
+                    .mention_cache
+                    .lock()
+                    .await
+                    .set(guild_id.get(), filter);
+
+                let reply = CreateInteractionResponseMessage::new()
+                    .add_embed(
+                        CreateEmbed::new()
+                            .description(format!(
+                                "**MENTION FILTER UPDATED**\n-# {details} | Type: {} | Reason: {}",
+                                selection.kind,
+                                clamp_chars(selection.reason.clone(), 25)
+                            ))
+                            .color(BRAND_BLUE),
+                    )
+                    .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+                if let Err(err) = selection
+                    .interaction
+                    .create_response(&ctx, CreateInteractionResponse::Message(reply))
+                    .await
+                {
+                    consume_serenity_error("MENTIONS SET RESPONSE".into(), err);
+                }
+
+                return Ok(());
+            }
+            Some(_) => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("use set, disable or no subcommand")),
+                    arg: _subcommand_arg,
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("MENTIONS RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -10,6 +10,18 @@ pub use ocr_check::OcrCheck;
 mod create_ocr_rule;
 pub use create_ocr_rule::CreateOcrRule;
 
+mod create_text_rule;
+pub use create_text_rule::CreateTextRule;
+
+mod create_name_rule;
+pub use create_name_rule::CreateNameRule;
+
+mod create_attachment_rule;
+pub use create_attachment_rule::CreateAttachmentRule;
+
+mod punishment_select;
+mod rule_creation;
+
 mod rules;
 pub use rules::Rules;
 
@@ -21,3 +33,47 @@ pub use encrypt::Encrypt;
 
 mod sticky;
 pub use sticky::Sticky;
+
+mod escalation;
+pub use escalation::Escalation;
+
+mod spam;
+pub use spam::Spam;
+
+mod mentions;
+pub use mentions::Mentions;
+
+mod raid;
+pub use raid::Raid;
+
+mod raid_lockdown;
+pub use raid_lockdown::RaidLockdown;
+
+mod account_age;
+pub use account_age::AccountAge;
+
+mod rule_exempt;
+pub use rule_exempt::RuleExempt;
+
+mod rule_dry_run;
+pub use rule_dry_run::RuleDryRun;
+
+mod rule_normalize;
+pub use rule_normalize::RuleNormalize;
+
+mod rule_cooldown;
+pub use rule_cooldown::RuleCooldown;
+
+mod rule_stats;
+pub use rule_stats::RuleStats;
+
+mod rule_condition;
+pub use rule_condition::RuleCondition;
+
+mod rule_transfer;
+
+mod export_rules;
+pub use export_rules::ExportRules;
+
+mod import_rules;
+pub use import_rules::ImportRules;
@@ -4,6 +4,7 @@ use serenity::{
 };
 
 use crate::{
+    OCR_POOL,
     commands::{
         Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
         CommandSyntax, TransformerFnArc,
@@ -11,7 +12,7 @@ use crate::{
     constants::BRAND_BLUE,
     event_handler::{CommandError, Handler},
     lexer::Token,
-    utils::{consume_serenity_error, ocr::extract_text_from_bytes},
+    utils::{consume_serenity_error, ocr_pool::OcrPriority},
 };
 use aegis_macros::command;
 
@@ -81,12 +82,16 @@ impl Command for OcrCheck {
         };
 
         trace.point("processing_ocr");
-        let image_str = match extract_text_from_bytes(&bytes).await {
+        let guild_id = msg.guild_id.map_or(0, |g| g.get());
+        let output = match OCR_POOL
+            .extract(guild_id, OcrPriority::High, bytes.to_vec())
+            .await
+        {
             Ok(d) => d,
-            Err(_) => {
+            Err(err) => {
                 return Err(CommandError {
                     title: String::from("failed to ocr provided attachment"),
-                    hint: None,
+                    hint: Some(err.to_string()),
                     arg: None,
                 });
             }
@@ -97,11 +102,16 @@ impl Command for OcrCheck {
             .send_message(
                 &ctx,
                 CreateMessage::new()
-                    .add_embed(
-                        CreateEmbed::new()
-                            .color(BRAND_BLUE)
-                            .description(format!("**OCR CHECK**\n```\n{image_str}\n```")),
-                    )
+                    .add_embed(CreateEmbed::new().color(BRAND_BLUE).description(format!(
+                        "**OCR CHECK**\n-# Preprocessing: {}{}\n```\n{}\n```",
+                        if output.steps.is_empty() {
+                            String::from("none")
+                        } else {
+                            output.steps.join(", ")
+                        },
+                        if output.cached { " | Cached text" } else { "" },
+                        output.text
+                    )))
                     .reference_message(&msg),
             )
             .await
@@ -0,0 +1,372 @@
+use std::time::Duration;
+
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, ComponentInteractionDataKind, Context, CreateActionRow,
+    CreateAllowedMentions, CreateButton, CreateEmbed, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateModal, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption, EditMessage, InputTextStyle, Message,
+    ModalInteraction,
+};
+
+use crate::{
+    commands::CommandArgument,
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    transformers::Transformers,
+    utils::{consume_serenity_error, rule_cache::Punishment, trace::TraceContext},
+};
+
+/// The punishment picked through [`select_punishment`], flattened the way the automod tables store it
+pub struct PunishmentSelection {
+    /// The `action_type` of the punishment (`log` for log only)
+    pub kind: String,
+    pub reason: String,
+    pub days: u8,
+    pub duration: i64,
+    pub silent: bool,
+    pub channel_id: i64,
+    /// The submitted modal, which still has to be responded to
+    pub interaction: ModalInteraction,
+}
+
+impl PunishmentSelection {
+    pub fn punishment(&self) -> Punishment {
+        Punishment::from_parts(
+            &self.kind,
+            self.reason.clone(),
+            self.days,
+            self.duration as u64,
+            self.silent,
+            self.channel_id as u64,
+        )
+    }
+}
+
+/// Lets the command author pick a punishment through a select menu and modal.
+/// `subject` finishes the prompt sentence, e.g. `this rule`.
+/// Returns `None` if the selection was cancelled or timed out.
+pub async fn select_punishment(
+    ctx: &Context,
+    msg: &Message,
+    title: &str,
+    subject: &str,
+    trace: &mut TraceContext,
+) -> Result<Option<PunishmentSelection>, CommandError> {
+    let select_menu = CreateActionRow::SelectMenu(
+        CreateSelectMenu::new(
+            "punishment_type",
+            CreateSelectMenuKind::String {
+                options: vec![
+                    CreateSelectMenuOption::new("Warn", "warn"),
+                    CreateSelectMenuOption::new("Kick", "kick"),
+                    CreateSelectMenuOption::new("Ban", "ban"),
+                    CreateSelectMenuOption::new("Softban", "softban"),
+                    CreateSelectMenuOption::new("Mute", "mute"),
+                    CreateSelectMenuOption::new("Rename", "rename"),
+                    CreateSelectMenuOption::new("Log Only", "log"),
+                ],
+            },
+        )
+        .placeholder("Select a punishment type"),
+    );
+    let cancel_button = CreateActionRow::Buttons(vec![
+        CreateButton::new("cancel")
+            .label("Cancel")
+            .style(ButtonStyle::Danger),
+    ]);
+
+    let components = vec![select_menu.clone(), cancel_button.clone()];
+    let disabled_components = vec![
+        CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(
+                "punishment_type",
+                CreateSelectMenuKind::String {
+                    options: vec![CreateSelectMenuOption::new("Disabled", "disabled")],
+                },
+            )
+            .disabled(true),
+        ),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new("cancel")
+                .label("Cancel")
+                .style(ButtonStyle::Danger)
+                .disabled(true),
+        ]),
+    ];
+
+    let reply = CreateMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**{title}**\nPlease select the punishment that will be applied when {subject} is triggered."
+                ))
+                .color(BRAND_BLUE),
+        )
+        .components(components.clone())
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+    let mut new_msg = match msg.channel_id.send_message(ctx, reply).await {
+        Ok(m) => m,
+        Err(err) => {
+            consume_serenity_error(String::from("PUNISHMENT SELECT RESPONSE"), err);
+            return Ok(None);
+        }
+    };
+
+    trace.point("awaiting_user_interaction");
+    loop {
+        let interaction = match new_msg
+            .await_component_interaction(&ctx.shard)
+            .timeout(Duration::from_secs(60 * 5))
+            .await
+        {
+            Some(i) => i,
+            None => {
+                let _ = new_msg
+                    .edit(ctx, EditMessage::new().components(disabled_components))
+                    .await;
+                return Ok(None);
+            }
+        };
+
+        if interaction.user.id != msg.author.id {
+            if let Err(err) = interaction
+                .create_response(
+                    ctx,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("You are not the author of the original message!")
+                            .ephemeral(true),
+                    ),
+                )
+                .await
+            {
+                consume_serenity_error(String::from("PUNISHMENT SELECT INTERACTION RESPONSE"), err);
+            }
+
+            continue;
+        }
+
+        if interaction.data.custom_id.as_str() == "cancel" {
+            let _ = new_msg
+                .edit(ctx, EditMessage::new().components(disabled_components))
+                .await;
+            let _ = interaction
+                .create_response(
+                    ctx,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("Cancelled.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await;
+            return Ok(None);
+        }
+
+        if interaction.data.custom_id.as_str() == "punishment_type" {
+            trace.point("processing_punishment_interaction");
+            let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind
+            else {
+                continue;
+            };
+
+            let selected = values.first().cloned().unwrap_or_default();
+            if selected.is_empty() {
+                continue;
+            }
+
+            let modal = match selected.as_str() {
+                "warn" | "kick" | "rename" => {
+                    CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
+                        .components(vec![
+                            CreateActionRow::InputText(
+                                CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+                                    .required(false)
+                                    .max_length(500),
+                            ),
+                            CreateActionRow::InputText(
+                                CreateInputText::new(
+                                    InputTextStyle::Short,
+                                    "Silent (y/n)",
+                                    "silent",
+                                )
+                                .required(false),
+                            ),
+                        ])
+                }
+                "ban" | "softban" => {
+                    let mut components = vec![
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+                                .required(false)
+                                .max_length(500),
+                        ),
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Short, "Clear Days (0-7)", "days")
+                                .required(false),
+                        ),
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Short, "Silent (y/n)", "silent")
+                                .required(false),
+                        ),
+                    ];
+                    if selected == "ban" {
+                        components.insert(
+                            1,
+                            CreateActionRow::InputText(
+                                CreateInputText::new(
+                                    InputTextStyle::Short,
+                                    "Duration (e.g. 10m, 1d)",
+                                    "duration",
+                                )
+                                .required(false),
+                            ),
+                        );
+                    }
+                    CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
+                        .components(components)
+                }
+                "mute" => CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
+                    .components(vec![
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+                                .required(false)
+                                .max_length(500),
+                        ),
+                        CreateActionRow::InputText(
+                            CreateInputText::new(
+                                InputTextStyle::Short,
+                                "Duration (e.g. 10m, 1d)",
+                                "duration",
+                            )
+                            .required(true),
+                        ),
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Short, "Silent (y/n)", "silent")
+                                .required(false),
+                        ),
+                    ]),
+                "log" => CreateModal::new(format!("{}-modal", selected), selected.to_uppercase())
+                    .components(vec![
+                        CreateActionRow::InputText(
+                            CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+                                .required(false)
+                                .max_length(500),
+                        ),
+                        CreateActionRow::InputText(
+                            CreateInputText::new(
+                                InputTextStyle::Short,
+                                "Log Channel ID",
+                                "channel_id",
+                            )
+                            .required(true),
+                        ),
+                    ]),
+                _ => continue,
+            };
+
+            if let Err(err) = interaction
+                .create_response(ctx, CreateInteractionResponse::Modal(modal))
+                .await
+            {
+                consume_serenity_error("PUNISHMENT SELECT MODAL CREATE".into(), err);
+                break;
+            }
+
+            let _ = new_msg
+                .edit(
+                    ctx,
+                    EditMessage::new().components(disabled_components.clone()),
+                )
+                .await;
+
+            let Some(interaction) = new_msg
+                .await_modal_interaction(&ctx.shard)
+                .timeout(Duration::from_secs(600))
+                .await
+            else {
+                break;
+            };
+
+            if interaction.data.custom_id != format!("{}-modal", selected) {
+                continue;
+            }
+
+            let mut reason = String::from("No reason provided");
+            let mut days = 1u8;
+            let mut silent = false;
+            let mut duration = 0i64;
+            let mut channel_id = 0i64;
+
+            for component in &interaction.data.components {
+                let Some(component) = component.components.first() else {
+                    continue;
+                };
+
+                match component {
+                    ActionRowComponent::InputText(component) if component.custom_id == "reason" => {
+                        reason = component
+                            .value
+                            .clone()
+                            .unwrap_or(String::from("No reason provided"));
+                    }
+                    ActionRowComponent::InputText(component) if component.custom_id == "days" => {
+                        let str = component.value.clone().unwrap_or_default();
+                        let mut iter = crate::lexer::lex(str).into_iter().peekable();
+                        if let Ok(token) = Transformers::i32(ctx, msg, &mut iter).await
+                            && let Some(CommandArgument::i32(n)) = token.contents
+                        {
+                            days = (n as u8).clamp(0, 7);
+                        }
+                    }
+                    ActionRowComponent::InputText(component) if component.custom_id == "silent" => {
+                        let str = component.value.clone().unwrap_or_default();
+                        let mut iter = crate::lexer::lex(str).into_iter().peekable();
+                        if let Ok(token) = Transformers::bool(ctx, msg, &mut iter).await
+                            && let Some(CommandArgument::bool(b)) = token.contents
+                        {
+                            silent = b;
+                        }
+                    }
+                    ActionRowComponent::InputText(component)
+                        if component.custom_id == "duration" =>
+                    {
+                        let str = component.value.clone().unwrap_or_default();
+                        let mut iter = crate::lexer::lex(str).into_iter().peekable();
+                        if let Ok(token) = Transformers::duration(ctx, msg, &mut iter).await
+                            && let Some(CommandArgument::Duration(d)) = token.contents
+                        {
+                            duration = d.num_seconds();
+                        }
+                    }
+                    ActionRowComponent::InputText(component)
+                        if component.custom_id == "channel_id" =>
+                    {
+                        let str = component.value.clone().unwrap_or_default();
+                        let mut iter = crate::lexer::lex(str).into_iter().peekable();
+                        if let Ok(token) = Transformers::guild_channel(ctx, msg, &mut iter).await
+                            && let Some(CommandArgument::GuildChannel(c)) = token.contents
+                        {
+                            channel_id = c.id.get() as i64;
+                        }
+                    }
+                    _ => continue,
+                };
+            }
+
+            return Ok(Some(PunishmentSelection {
+                kind: selected,
+                reason,
+                days,
+                duration,
+                silent,
+                channel_id,
+                interaction,
+            }));
+        }
+    }
+
+    Ok(None)
+}
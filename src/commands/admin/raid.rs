@@ -0,0 +1,274 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    moderation,
+    transformers::Transformers,
+    utils::{
+        consume_pgsql_error, consume_serenity_error,
+        raid_cache::{RaidFilter, RaidLockdown},
+    },
+};
+use aegis_macros::command;
+
+pub struct Raid;
+
+impl Raid {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Raid {
+    fn get_name(&self) -> &'static str {
+        "raid"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures the servers raid detection"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures the servers raid detection. \
+        `raid set <joins> <period> [suspicious] [account age]` triggers once `joins` members join within `period`, \
+        or `suspicious` of them are suspicious. A join is suspicious if the account is younger than `account age` \
+        or shares its name or avatar with another recent join. Use 0 to turn a single check off, e.g. `raid set 10 30s 5 1d`. \
+        Once triggered the server enters raid mode, the lockdown configured through `raid_lockdown` is applied \
+        and an alert listing the suspects is posted to the member log, from where they can be banned or the raid dismissed. \
+        `raid end` dismisses an ongoing raid and lifts the lockdown, `raid disable` turns the detection off. \
+        Running the command without arguments shows the current configuration."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("set/disable/end", false),
+            CommandSyntax::Number("joins", false),
+            CommandSyntax::Duration("period", false),
+            CommandSyntax::Number("suspicious", false),
+            CommandSyntax::Duration("account age", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::string] subcommand: Option<String>,
+        #[transformers::i32] join_limit: Option<i32>,
+        #[transformers::string] period: Option<String>,
+        #[transformers::i32] suspicious_limit: Option<i32>,
+        #[transformers::string] account_age: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let description = match subcommand.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("show") => {
+                let cache = handler.raid_cache.lock().await;
+                let status = if cache.is_active(guild_id.get()) {
+                    "\n-# A raid is ongoing, use `raid end` to dismiss it"
+                } else {
+                    ""
+                };
+                match cache.get(guild_id.get()) {
+                    Some(f) => format!("**RAID DETECTION**\n-# {}{status}", f.describe()),
+                    None => format!("**RAID DETECTION**\n-# Disabled{status}"),
+                }
+            }
+            Some("disable") => {
+                trace.point("deleting_filter");
+                if let Err(err) = sqlx::query("DELETE FROM raid_filters WHERE guild_id = $1")
+                    .bind(guild_id.get() as i64)
+                    .execute(&*SQL)
+                    .await
+                {
+                    consume_pgsql_error("RAID DISABLE".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+
+                handler.raid_cache.lock().await.remove(guild_id.get());
+                String::from(
+                    "**RAID DETECTION DISABLED**\n-# An ongoing raid stays active until it is dismissed",
+                )
+            }
+            Some("end") => {
+                trace.point("ending_lockdown");
+                handler.raid_cache.lock().await.end(guild_id.get());
+                match moderation::raid::end_lockdown(&ctx, guild_id).await {
+                    Some(suspects) => format!(
+                        "**RAID ENDED**\n-# Lockdown lifted | Suspects: {}",
+                        suspects.len()
+                    ),
+                    None => {
+                        return Err(CommandError::new("This server is not in raid mode"));
+                    }
+                }
+            }
+            Some("set") => {
+                let limits = [
+                    (join_limit, &_join_limit_arg),
+                    (suspicious_limit.or(Some(0)), &_suspicious_limit_arg),
+                ];
+                for (limit, arg) in &limits {
+                    if !limit.is_some_and(|n| n >= 0) {
+                        return Err(CommandError {
+                            title: String::from("Expected a limit of 0 or more"),
+                            hint: Some(String::from("e.g. `raid set 10 30s 5 1d`")),
+                            arg: (*arg).clone(),
+                        });
+                    }
+                }
+
+                let (Some(_), Some(period_token)) = (&period, _period_arg) else {
+                    return Err(CommandError::new("Missing period argument"));
+                };
+                let period = match Transformers::duration(
+                    &ctx,
+                    &msg,
+                    &mut vec![period_token].into_iter().peekable(),
+                )
+                .await
+                {
+                    Ok(Token {
+                        contents: Some(CommandArgument::Duration(d)),
+                        ..
+                    }) if d.num_seconds() > 0 && d.num_seconds() <= 3600 => d,
+                    Err(TransformerError::CommandError(err)) => return Err(err),
+                    _ => {
+                        return Err(CommandError::new(
+                            "Period must be between 1 second and 1 hour",
+                        ));
+                    }
+                };
+
+                let account_age = match (&account_age, _account_age_arg) {
+                    (Some(_), Some(token)) => match Transformers::duration(
+                        &ctx,
+                        &msg,
+                        &mut vec![token].into_iter().peekable(),
+                    )
+                    .await
+                    {
+                        Ok(Token {
+                            contents: Some(CommandArgument::Duration(d)),
+                            ..
+                        }) if d.num_seconds() >= 0 => d.num_seconds(),
+                        Err(TransformerError::CommandError(err)) => return Err(err),
+                        _ => return Err(CommandError::new("Invalid account age")),
+                    },
+                    _ => 0,
+                };
+
+                let (join_limit, suspicious_limit) =
+                    (join_limit.unwrap_or(0), suspicious_limit.unwrap_or(0));
+
+                if join_limit == 0 && suspicious_limit == 0 {
+                    return Err(CommandError {
+                        title: String::from("At least one limit has to be enabled"),
+                        hint: Some(String::from("use `raid disable` to turn the detection off")),
+                        arg: None,
+                    });
+                }
+
+                trace.point("updating_database");
+                let row = match sqlx::query(
+                    "INSERT INTO raid_filters (guild_id, join_limit, suspicious_limit, period, min_account_age) \
+                     VALUES ($1, $2, $3, $4, $5) \
+                     ON CONFLICT (guild_id) DO UPDATE SET \
+                     join_limit = $2, suspicious_limit = $3, period = $4, min_account_age = $5 \
+                     RETURNING raise_verification, pause_invites, lock_channels",
+                )
+                .bind(guild_id.get() as i64)
+                .bind(join_limit)
+                .bind(suspicious_limit)
+                .bind(period.num_seconds() as i32)
+                .bind(account_age)
+                .fetch_one(&*SQL)
+                .await
+                {
+                    Ok(row) => row,
+                    Err(err) => {
+                        consume_pgsql_error("RAID SET".into(), err);
+                        return Err(CommandError::new("Could not update the database"));
+                    }
+                };
+
+                let filter = RaidFilter {
+                    join_limit: join_limit as u32,
+                    suspicious_limit: suspicious_limit as u32,
+                    period: period.to_std().unwrap_or_default(),
+                    min_account_age: std::time::Duration::from_secs(account_age as u64),
+                    lockdown: RaidLockdown {
+                        raise_verification: row.get("raise_verification"),
+                        pause_invites: row.get("pause_invites"),
+                        lock_channels: row
+                            .get::<Vec<i64>, _>("lock_channels")
+                            .into_iter()
+                            .map(|id| id as u64)
+                            .collect(),
+                    },
+                };
+                let details = filter.describe();
+                handler.raid_cache.lock().await.set(guild_id.get(), filter);
+
+                format!("**RAID DETECTION UPDATED**\n-# {details}")
+            }
+            Some(_) => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("use set, disable, end or no subcommand")),
+                    arg: _subcommand_arg,
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RAID RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{
+        ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions,
+    },
+    async_trait,
+};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{consume_pgsql_error, consume_serenity_error, raid_cache},
+};
+use aegis_macros::command;
+
+pub struct RaidLockdown;
+
+impl RaidLockdown {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for RaidLockdown {
+    fn get_name(&self) -> &'static str {
+        "raid_lockdown"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures what is locked down during a raid"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures what is changed once the raid detection triggers. \
+        `verification` raises the verification level to high, `invites` pauses the invites of the server \
+        and every channel passed is locked for @everyone, e.g. `raid_lockdown verification invites #general #media`. \
+        `none` only posts the raid alert. Everything is reverted once the raid is dismissed. \
+        Running the command without arguments shows the current lockdown, the raid detection has to be set up through `raid set` first."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![CommandSyntax::Consume("lockdown")]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::string_consume] lockdown: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let Some(mut filter) = handler.raid_cache.lock().await.get(guild_id.get()) else {
+            return Err(CommandError {
+                title: String::from("The raid detection is disabled"),
+                hint: Some(String::from("use `raid set` to enable it first")),
+                arg: None,
+            });
+        };
+
+        let description = match lockdown {
+            None => format!("**RAID LOCKDOWN**\n-# {}", filter.lockdown.describe()),
+            Some(input) => {
+                let mut new_lockdown = raid_cache::RaidLockdown::default();
+                for word in input.split_whitespace() {
+                    match word.to_lowercase().as_str() {
+                        "verification" => new_lockdown.raise_verification = true,
+                        "invites" => new_lockdown.pause_invites = true,
+                        "none" => {}
+                        other => {
+                            let id = other
+                                .trim_start_matches("<#")
+                                .trim_end_matches('>')
+                                .parse::<u64>()
+                                .ok()
+                                .filter(|id| *id != 0);
+
+                            let channel = match id {
+                                Some(id) => ChannelId::new(id)
+                                    .to_channel(&ctx)
+                                    .await
+                                    .ok()
+                                    .and_then(|c| c.guild())
+                                    .filter(|c| c.guild_id == guild_id),
+                                None => None,
+                            };
+
+                            let Some(channel) = channel else {
+                                return Err(CommandError {
+                                    title: format!("Unknown lockdown option `{other}`"),
+                                    hint: Some(String::from(
+                                        "use verification, invites, none or channels of this server",
+                                    )),
+                                    arg: _lockdown_arg,
+                                });
+                            };
+
+                            if !new_lockdown.lock_channels.contains(&channel.id.get()) {
+                                new_lockdown.lock_channels.push(channel.id.get());
+                            }
+                        }
+                    }
+                }
+
+                trace.point("updating_database");
+                if let Err(err) = sqlx::query(
+                    "UPDATE raid_filters SET raise_verification = $2, pause_invites = $3, lock_channels = $4 WHERE guild_id = $1",
+                )
+                .bind(guild_id.get() as i64)
+                .bind(new_lockdown.raise_verification)
+                .bind(new_lockdown.pause_invites)
+                .bind(
+                    new_lockdown
+                        .lock_channels
+                        .iter()
+                        .map(|id| *id as i64)
+                        .collect::<Vec<_>>(),
+                )
+                .execute(&*SQL)
+                .await
+                {
+                    consume_pgsql_error("RAID LOCKDOWN SET".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+
+                let details = new_lockdown.describe();
+                filter.lockdown = new_lockdown;
+                handler.raid_cache.lock().await.set(guild_id.get(), filter);
+
+                format!("**RAID LOCKDOWN UPDATED**\n-# {details}")
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RAID LOCKDOWN RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
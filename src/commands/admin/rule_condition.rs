@@ -0,0 +1,276 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::{Row, types::Json};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        consume_pgsql_error, consume_serenity_error, rule_cache::db_clear_image_hashes,
+        rule_condition,
+    },
+};
+use aegis_macros::command;
+
+pub struct RuleCondition;
+
+impl RuleCondition {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// The condition exactly as typed, the consumed argument loses its quotes
+    fn raw_condition(msg: &Message, handler: &Handler, arg: &Token) -> String {
+        msg.content
+            .strip_prefix(handler.prefix.as_str())
+            .unwrap_or_default()
+            .chars()
+            .skip(arg.position)
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+}
+
+#[async_trait]
+impl Command for RuleCondition {
+    fn get_name(&self) -> &'static str {
+        "rule_condition"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Combines several patterns of an automod rule with AND, OR and NOT"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Replaces the pattern of an OCR, text or name rule with a condition combining several patterns. \
+        Patterns are combined with `AND`, `OR` and `NOT`, grouped with parentheses and written like rule patterns, \
+        plain words or quoted text are matched like the rules own pattern (fuzzy for OCR rules) and `/slashes/` mark a regex. \
+        `2 OF (a, b, c)` matches if at least two of the listed conditions match. \
+        e.g. `rule_condition abc123 steam AND (gift OR nitro) AND NOT official`. \
+        `AND` binds stronger than `OR` and a condition has to contain at least one pattern that must be present. \
+        `rule_condition <id> clear` goes back to the original pattern, running the command with only the rule ID shows the current condition."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("id", true),
+            CommandSyntax::Consume("condition"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::some_string] id: String,
+        #[transformers::string_consume] condition: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        trace.point("fetching_rule");
+        let row = sqlx::query(
+            "SELECT name, type, rule, is_regex, condition FROM automod_rules WHERE id = $1 AND guild_id = $2",
+        )
+        .bind(id.as_str())
+        .bind(guild_id.get() as i64)
+        .fetch_optional(&*SQL)
+        .await;
+
+        let row = match row {
+            Ok(Some(r)) => r,
+            Ok(None) => {
+                return Err(CommandError {
+                    title: format!("No rule with ID `{id}` found in this server"),
+                    hint: Some(String::from("use +rules to list all rules")),
+                    arg: Some(_id_arg),
+                });
+            }
+            Err(err) => {
+                consume_pgsql_error("RULE CONDITION FETCH".into(), err);
+                return Err(CommandError {
+                    title: String::from("Could not query the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        let rule_name: String = row.get("name");
+        let pattern = {
+            let rule: String = row.get("rule");
+            if row.get("is_regex") {
+                format!("/{rule}/")
+            } else {
+                rule
+            }
+        };
+
+        let (Some(_), Some(arg)) = (condition, _condition_arg) else {
+            let current = row
+                .get::<Option<serde_json::Value>, _>("condition")
+                .and_then(|value| {
+                    serde_json::from_value::<rule_condition::RuleCondition>(value).ok()
+                });
+
+            let description = match current {
+                Some(current) => format!(
+                    "-# ID: `{id}` | Patterns: {}\n```\n{}\n```",
+                    current.pattern_count(),
+                    current.describe()
+                ),
+                None => format!(
+                    "-# ID: `{id}` | No condition, the rule matches its pattern\n```\n{pattern}\n```"
+                ),
+            };
+
+            let reply = CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**{} CONDITION**\n{description}",
+                            rule_name.to_uppercase()
+                        ))
+                        .color(BRAND_BLUE),
+                )
+                .reference_message(&msg)
+                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+                consume_serenity_error("RULE CONDITION RESPONSE".into(), err);
+            }
+
+            return Ok(());
+        };
+
+        if row.get::<String, _>("type") == "attachment" {
+            return Err(CommandError {
+                title: String::from("Attachment rules can not have a condition"),
+                hint: Some(String::from(
+                    "conditions combine the patterns of OCR, text and name rules",
+                )),
+                arg: Some(_id_arg),
+            });
+        }
+
+        let input = Self::raw_condition(&msg, handler, &arg);
+        let new_condition = if input.eq_ignore_ascii_case("clear") {
+            None
+        } else {
+            if input.len() >= 500 {
+                return Err(CommandError {
+                    title: String::from("conditions can only be a max of 500 characters long"),
+                    hint: None,
+                    arg: Some(arg),
+                });
+            }
+
+            match rule_condition::RuleCondition::parse(&input) {
+                Ok(parsed) => Some(parsed),
+                Err(err) => {
+                    let arg = match err.span {
+                        Some((start, length)) => Token {
+                            position: arg.position + start,
+                            length: input
+                                .chars()
+                                .skip(start)
+                                .take(length)
+                                .map(char::len_utf8)
+                                .sum::<usize>()
+                                .max(1),
+                            quoted: false,
+                            ..arg
+                        },
+                        None => arg,
+                    };
+
+                    return Err(CommandError {
+                        title: format!("Invalid condition: {}", err.reason),
+                        hint: Some(String::from(
+                            "e.g. `steam AND (gift OR nitro) AND NOT official` or `2 OF (gift, nitro, \"free steam\")`",
+                        )),
+                        arg: Some(arg),
+                    });
+                }
+            }
+        };
+
+        trace.point("updating_database");
+        if let Err(err) = sqlx::query("UPDATE automod_rules SET condition = $1 WHERE id = $2")
+            .bind(new_condition.clone().map(Json))
+            .bind(id.as_str())
+            .execute(&*SQL)
+            .await
+        {
+            consume_pgsql_error("RULE CONDITION UPDATE".into(), err);
+            return Err(CommandError::new("Could not update the database"));
+        }
+        db_clear_image_hashes(&id).await;
+
+        let description = match &new_condition {
+            Some(condition) => format!(
+                "**{} CONDITION UPDATED**\n-# ID: `{id}` | Patterns: {}\n```\n{}\n```",
+                rule_name.to_uppercase(),
+                condition.pattern_count(),
+                condition.describe()
+            ),
+            None => format!(
+                "**{} CONDITION CLEARED**\n-# ID: `{id}` | The rule matches its pattern again\n```\n{pattern}\n```",
+                rule_name.to_uppercase()
+            ),
+        };
+
+        handler
+            .rule_cache
+            .lock()
+            .await
+            .set_condition(&id, new_condition);
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RULE CONDITION RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::TimeDelta;
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{consume_pgsql_error, consume_serenity_error, rule_cache},
+};
+use aegis_macros::command;
+
+pub struct RuleCooldown;
+
+impl RuleCooldown {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn parse_duration(
+        ctx: &Context,
+        msg: &Message,
+        token: Token,
+        max: TimeDelta,
+    ) -> Result<Duration, CommandError> {
+        match Transformers::duration(ctx, msg, &mut vec![token].into_iter().peekable()).await {
+            Ok(Token {
+                contents: Some(CommandArgument::Duration(d)),
+                ..
+            }) if d >= TimeDelta::zero() && d <= max => Ok(d.to_std().unwrap_or_default()),
+            Err(TransformerError::CommandError(err)) => Err(err),
+            _ => Err(CommandError::new(format!(
+                "Durations can be at most {} days",
+                max.num_days()
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for RuleCooldown {
+    fn get_name(&self) -> &'static str {
+        "rule_cooldown"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures how often an automod rule punishes the same user"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures how often an automod rule may punish the same user. \
+        `rule_cooldown <id> <debounce> [max] [period] [delete/ignore]` sets the minimum time between two punishments, \
+        the maximum amount of punishments per user within the period (0 for no limit) \
+        and whether matched messages are still deleted while the rule can not punish the user, \
+        e.g. `rule_cooldown abc123 30s 3 1d delete`. \
+        Running the command with only the rule ID shows the current configuration. \
+        New rules use a debounce of 15 seconds without a limit and keep deleting messages."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("id", true),
+            CommandSyntax::Duration("debounce", false),
+            CommandSyntax::Number("max", false),
+            CommandSyntax::Duration("period", false),
+            CommandSyntax::String("delete/ignore", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::some_string] id: String,
+        #[transformers::string] debounce: Option<String>,
+        #[transformers::i32] max: Option<i32>,
+        #[transformers::string] period: Option<String>,
+        #[transformers::string] mode: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        trace.point("fetching_rule");
+        let row = sqlx::query(
+            "SELECT name, debounce, max_punishments, punishment_period, delete_during_cooldown FROM automod_rules WHERE id = $1 AND guild_id = $2",
+        )
+        .bind(id.as_str())
+        .bind(guild_id.get() as i64)
+        .fetch_optional(&*SQL)
+        .await;
+
+        let row = match row {
+            Ok(Some(r)) => r,
+            Ok(None) => {
+                return Err(CommandError {
+                    title: format!("No rule with ID `{id}` found in this server"),
+                    hint: Some(String::from("use +rules to list all rules")),
+                    arg: Some(_id_arg),
+                });
+            }
+            Err(err) => {
+                consume_pgsql_error("RULE COOLDOWN FETCH".into(), err);
+                return Err(CommandError {
+                    title: String::from("Could not query the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        let rule_name: String = row.get("name");
+        let mut cooldown = rule_cache::RuleCooldown::from_row(&row);
+
+        let title = if let (Some(_), Some(debounce_token)) = (&debounce, _debounce_arg) {
+            cooldown.debounce =
+                Self::parse_duration(&ctx, &msg, debounce_token, TimeDelta::days(1)).await?;
+
+            match max {
+                Some(n) if n < 0 => {
+                    return Err(CommandError {
+                        title: String::from("Expected a limit of 0 or more"),
+                        hint: Some(String::from("use 0 to allow unlimited punishments")),
+                        arg: _max_arg,
+                    });
+                }
+                Some(n) => cooldown.max_punishments = n as u32,
+                None => {}
+            }
+
+            if let (Some(_), Some(period_token)) = (&period, _period_arg) {
+                cooldown.period =
+                    Self::parse_duration(&ctx, &msg, period_token, TimeDelta::days(365)).await?;
+            }
+
+            if cooldown.max_punishments != 0 && cooldown.period.is_zero() {
+                return Err(CommandError {
+                    title: String::from("A punishment limit needs a period"),
+                    hint: Some(String::from("e.g. `rule_cooldown <id> 30s 3 1d`")),
+                    arg: None,
+                });
+            }
+
+            match mode.as_deref().map(str::to_lowercase).as_deref() {
+                Some("delete") => cooldown.delete_during_cooldown = true,
+                Some("ignore") => cooldown.delete_during_cooldown = false,
+                None => {}
+                Some(_) => {
+                    return Err(CommandError {
+                        title: String::from("Expected delete or ignore"),
+                        hint: None,
+                        arg: _mode_arg,
+                    });
+                }
+            }
+
+            trace.point("updating_database");
+            if let Err(err) = sqlx::query(
+                "UPDATE automod_rules SET debounce = $1, max_punishments = $2, punishment_period = $3, delete_during_cooldown = $4 WHERE id = $5",
+            )
+            .bind(cooldown.debounce.as_secs() as i32)
+            .bind(cooldown.max_punishments as i32)
+            .bind(cooldown.period.as_secs() as i64)
+            .bind(cooldown.delete_during_cooldown)
+            .bind(id.as_str())
+            .execute(&*SQL)
+            .await
+            {
+                consume_pgsql_error("RULE COOLDOWN UPDATE".into(), err);
+                return Err(CommandError::new("Could not update the database"));
+            }
+
+            handler
+                .rule_cache
+                .lock()
+                .await
+                .set_cooldown(&id, cooldown.clone());
+
+            "COOLDOWN UPDATED"
+        } else {
+            "COOLDOWN"
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} {title}**\n-# ID: `{id}`\n{}",
+                        rule_name.to_uppercase(),
+                        cooldown.describe()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RULE COOLDOWN RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use serenity::all::{
+    Context, CreateAllowedMentions, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, Message,
+};
+
+use crate::{
+    SQL,
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    utils::{
+        clamp_chars, consume_pgsql_error, consume_serenity_error,
+        normalize::NormalizeStep,
+        rule_cache::{Rule, RuleCooldown, RuleExemptions},
+        rule_regex::build_rule_regex,
+        tinyid,
+        trace::TraceContext,
+    },
+};
+
+/// Rejects regex rules (`/pattern/`) that don't compile within the limits of [`build_rule_regex`].
+/// The error points at the broken part of the pattern if the argument was typed without escapes,
+/// `hint` tells how the calling command's rules can be tried out.
+#[allow(clippy::result_large_err)]
+pub fn validate_pattern(
+    msg: &Message,
+    handler: &Handler,
+    rule: &str,
+    arg: &Token,
+    hint: &str,
+) -> Result<(), CommandError> {
+    let Some(pattern) = rule.strip_prefix('/').and_then(|s| s.strip_suffix('/')) else {
+        return Ok(());
+    };
+    let Err(rejection) = build_rule_regex(pattern) else {
+        return Ok(());
+    };
+
+    let input = msg
+        .content
+        .strip_prefix(handler.prefix.as_str())
+        .unwrap_or_default();
+    let verbatim = input
+        .chars()
+        .skip(arg.position)
+        .take(rule.chars().count())
+        .eq(rule.chars());
+
+    let arg = match rejection.span {
+        Some((start, length)) if verbatim => Token {
+            position: arg.position + 1 + start,
+            length: length.max(1),
+            quoted: false,
+            ..arg.clone()
+        },
+        _ => arg.clone(),
+    };
+
+    Err(CommandError {
+        title: format!("Invalid regex: {}", rejection.reason),
+        hint: Some(String::from(hint)),
+        arg: Some(arg),
+    })
+}
+
+/// Runs the interactive punishment selection shared by the rule creation commands and stores the new rule.
+/// `kind` is the value stored in the `automod_rules.type` column (e.g. `ocr`, `text`).
+pub async fn create_rule(
+    ctx: &Context,
+    msg: &Message,
+    handler: &Handler,
+    kind: &'static str,
+    name: String,
+    rule: String,
+    trace: &mut TraceContext,
+) -> Result<(), CommandError> {
+    let db_id = tinyid().await;
+    let inner = if let Some(stripped) = rule.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+        stripped
+    } else {
+        &rule
+    };
+    let is_regex = rule.starts_with('/') && rule.ends_with('/');
+
+    let title = format!("CREATE {} RULE", kind.to_uppercase());
+    let Some(selection) =
+        super::punishment_select::select_punishment(ctx, msg, &title, "this rule", trace).await?
+    else {
+        return Ok(());
+    };
+
+    trace.point("updating_database");
+    if let Err(err) = sqlx::query(
+        "INSERT INTO automod_rules \
+         (id, guild_id, name, type, rule, is_regex, reason, \
+          punishment_type, day_clear_amount, duration, silent, log_channel_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, CAST($8 AS action_type), $9, $10, $11, $12)",
+    )
+    .bind(db_id.clone())
+    .bind(msg.guild_id.map(|id| id.get()).unwrap_or(1) as i64)
+    .bind(name.clone())
+    .bind(kind)
+    .bind(inner)
+    .bind(is_regex)
+    .bind(selection.reason.clone())
+    .bind(selection.kind.clone())
+    .bind(selection.days as i16)
+    .bind(selection.duration)
+    .bind(selection.silent)
+    .bind(selection.channel_id)
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("RULE CREATE".into(), err);
+        return Err(CommandError {
+            title: String::from("Could not update the database"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    {
+        let mut lock = handler.rule_cache.lock().await;
+        lock.insert(
+            kind,
+            Rule {
+                name: name.clone(),
+                id: db_id.clone(),
+                pattern: inner.to_string(),
+                is_regex,
+                guild_id: msg.guild_id.map(|id| id.get()).unwrap_or(1),
+                punishment: selection.punishment(),
+                exemptions: RuleExemptions::default(),
+                normalization: NormalizeStep::DEFAULT.to_vec(),
+                cooldown: RuleCooldown::default(),
+                condition: None,
+                fuzzy: kind == "ocr",
+                regexes: HashMap::new(),
+            },
+        );
+    }
+
+    let reply = CreateInteractionResponseMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**CREATED {} RULE {}**\n-# ID: `{}` | Type: {} | Reason: {}\n```\n{}\n```",
+                    kind.to_uppercase(),
+                    name.to_uppercase(),
+                    db_id,
+                    selection.kind,
+                    clamp_chars(selection.reason.clone(), 25),
+                    rule
+                ))
+                .color(BRAND_BLUE),
+        )
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+    if let Err(err) = selection
+        .interaction
+        .create_response(ctx, CreateInteractionResponse::Message(reply))
+        .await
+    {
+        consume_serenity_error("SEND CREATE RULE RESPONSE".into(), err);
+    }
+
+    Ok(())
+}
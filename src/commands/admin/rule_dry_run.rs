@@ -0,0 +1,457 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{TimeDelta, Utc};
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage, Message,
+        Permissions,
+    },
+    async_trait,
+    futures::{StreamExt, stream},
+};
+use sqlx::Row;
+
+use crate::{
+    ENCRYPTION_KEYS, OCR_POOL, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        cache::partials::PartialAttachment,
+        clamp_chars, consume_pgsql_error, consume_serenity_error,
+        encryption::decrypt,
+        image_hash::sha256_hex,
+        ocr_pool::OcrPriority,
+        rule_cache::{Rule, db_check_image_hash},
+        rule_regex::build_rule_regex,
+        time_string,
+    },
+};
+use aegis_macros::command;
+
+/// Most recent stored messages a text dry run checks
+const DRY_RUN_MESSAGE_LIMIT: i64 = 10000;
+/// Most recent stored images an OCR dry run downloads and runs through OCR
+const DRY_RUN_IMAGE_LIMIT: usize = 50;
+const DRY_RUN_OCR_CONCURRENCY: usize = 4;
+const IMAGE_EXTENSIONS: [&str; 5] = [".png", ".jpg", ".jpeg", ".webp", ".gif"];
+
+struct DryRunMatch {
+    message_id: u64,
+    channel_id: u64,
+    author_id: u64,
+    author_name: String,
+    created_at: i64,
+    content: String,
+    /// The existing rule an OCR match was already flagged by according to `ocr_image_hashes`
+    flagged_by: Option<String>,
+}
+
+#[derive(Default)]
+struct DryRunReport {
+    scanned: usize,
+    /// Stored images that could not be downloaded anymore
+    unavailable: usize,
+    matches: Vec<DryRunMatch>,
+}
+
+pub struct RuleDryRun;
+
+impl RuleDryRun {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    async fn run_text(
+        guild_id: u64,
+        rule: &Rule,
+        since: TimeDelta,
+    ) -> Result<DryRunReport, CommandError> {
+        let rows = sqlx::query(
+            "SELECT message_id, channel_id, author_id, author_name, content, created_at FROM message_store \
+             WHERE guild_id = $1 AND created_at >= $2 AND content IS NOT NULL \
+             ORDER BY created_at DESC LIMIT $3",
+        )
+        .bind(guild_id as i64)
+        .bind(Utc::now() - since)
+        .bind(DRY_RUN_MESSAGE_LIMIT)
+        .fetch_all(&*SQL)
+        .await
+        .map_err(|err| {
+            consume_pgsql_error("RULE DRY RUN FETCH".into(), err);
+            CommandError::new("Could not query the database")
+        })?;
+
+        let key = ENCRYPTION_KEYS.lock().await.get(&guild_id).copied();
+        let scanned = rows.len();
+        let rule = rule.clone();
+
+        // matching thousands of messages is CPU bound, so it runs off the async workers
+        let matches = tokio::task::spawn_blocking(move || {
+            rows.into_iter()
+                .filter_map(|row| {
+                    let bytes: Vec<u8> = row.get("content");
+                    let content = match key {
+                        Some(key) if !bytes.is_empty() => decrypt(&key, &bytes)
+                            .unwrap_or_else(|| String::from_utf8(bytes).unwrap_or_default()),
+                        _ => String::from_utf8(bytes).unwrap_or_default(),
+                    };
+
+                    if content.is_empty() || !rule.matches(&content) {
+                        return None;
+                    }
+
+                    Some(DryRunMatch {
+                        message_id: row.get::<i64, _>("message_id") as u64,
+                        channel_id: row.get::<i64, _>("channel_id") as u64,
+                        author_id: row.get::<i64, _>("author_id") as u64,
+                        author_name: row.get("author_name"),
+                        created_at: row
+                            .get::<chrono::DateTime<Utc>, _>("created_at")
+                            .timestamp(),
+                        content,
+                        flagged_by: None,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .await
+        .map_err(|_| CommandError::new("The dry run failed, please try again later"))?;
+
+        let report = DryRunReport {
+            scanned,
+            matches,
+            ..Default::default()
+        };
+
+        Ok(report)
+    }
+
+    async fn run_ocr(
+        guild_id: u64,
+        rule: &Rule,
+        since: TimeDelta,
+    ) -> Result<DryRunReport, CommandError> {
+        let rows = sqlx::query(
+            "SELECT message_id, channel_id, author_id, author_name, attachment_urls, created_at FROM message_store \
+             WHERE guild_id = $1 AND created_at >= $2 AND jsonb_typeof(attachment_urls) = 'array' \
+             AND jsonb_array_length(attachment_urls) > 0 \
+             ORDER BY created_at DESC LIMIT $3",
+        )
+        .bind(guild_id as i64)
+        .bind(Utc::now() - since)
+        .bind(DRY_RUN_IMAGE_LIMIT as i64)
+        .fetch_all(&*SQL)
+        .await
+        .map_err(|err| {
+            consume_pgsql_error("RULE DRY RUN FETCH".into(), err);
+            CommandError::new("Could not query the database")
+        })?;
+
+        let images = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(index, row)| {
+                let attachments: Vec<PartialAttachment> = serde_json::from_value(
+                    row.try_get("attachment_urls")
+                        .unwrap_or(serde_json::Value::Null),
+                )
+                .unwrap_or_default();
+
+                attachments
+                    .into_iter()
+                    .filter(|a| {
+                        let name = a.name.to_lowercase();
+                        IMAGE_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+                    })
+                    .map(move |a| (index, a.url))
+            })
+            .take(DRY_RUN_IMAGE_LIMIT)
+            .collect::<Vec<_>>();
+
+        let mut report = DryRunReport {
+            scanned: images.len(),
+            ..Default::default()
+        };
+
+        // (row index, OCR text, existing rule that flagged the image), None if the image could not be downloaded
+        let results = stream::iter(images)
+            .map(|(index, url)| async move {
+                let bytes = reqwest::get(url)
+                    .await
+                    .ok()
+                    .filter(|res| res.status().is_success())?
+                    .bytes()
+                    .await
+                    .ok()?;
+
+                let flagged_by = db_check_image_hash(guild_id, &sha256_hex(&bytes)).await;
+                let text = OCR_POOL
+                    .extract(guild_id, OcrPriority::Low, bytes.to_vec())
+                    .await
+                    .map(|output| output.text)
+                    .unwrap_or_default();
+
+                Some((index, text, flagged_by))
+            })
+            .buffer_unordered(DRY_RUN_OCR_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        for result in results {
+            let Some((index, text, flagged_by)) = result else {
+                report.unavailable += 1;
+                continue;
+            };
+            let row = &rows[index];
+
+            if text.is_empty() || !rule.matches(&text) {
+                continue;
+            }
+
+            report.matches.push(DryRunMatch {
+                message_id: row.get::<i64, _>("message_id") as u64,
+                channel_id: row.get::<i64, _>("channel_id") as u64,
+                author_id: row.get::<i64, _>("author_id") as u64,
+                author_name: row.get("author_name"),
+                created_at: row
+                    .get::<chrono::DateTime<Utc>, _>("created_at")
+                    .timestamp(),
+                content: text,
+                flagged_by,
+            });
+        }
+
+        report
+            .matches
+            .sort_by_key(|m| std::cmp::Reverse(m.created_at));
+
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl Command for RuleDryRun {
+    fn get_name(&self) -> &'static str {
+        "rule_dry_run"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Tests a rule against the servers message history"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Tests a rule against the stored message history of the server without taking any action. \
+        `rule_dry_run text <rule> [period]` checks the content of stored messages, \
+        `rule_dry_run ocr <rule> [period]` runs the most recent stored images through OCR again. \
+        Rules use the same syntax as the rule creation commands, the period defaults to 7 days. \
+        The report shows how many messages would have matched, which users would have been punished and a few samples, \
+        the full list of matches is attached as a file."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("text/ocr", true),
+            CommandSyntax::String("rule", true),
+            CommandSyntax::Duration("period", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] kind: String,
+        #[transformers::some_string] rule: String,
+        #[transformers::string] period: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap().get();
+
+        let kind = kind.to_lowercase();
+        if kind != "text" && kind != "ocr" {
+            return Err(CommandError {
+                title: String::from("Unknown rule type"),
+                hint: Some(String::from("use text or ocr")),
+                arg: Some(_kind_arg),
+            });
+        }
+
+        let since = match (&period, _period_arg) {
+            (Some(_), Some(period_token)) => match Transformers::duration(
+                &ctx,
+                &msg,
+                &mut vec![period_token].into_iter().peekable(),
+            )
+            .await
+            {
+                Ok(Token {
+                    contents: Some(CommandArgument::Duration(d)),
+                    ..
+                }) if d.num_seconds() > 0 && d.num_days() <= 90 => d,
+                Err(TransformerError::CommandError(err)) => return Err(err),
+                _ => {
+                    return Err(CommandError::new(
+                        "Period must be between 1 second and 90 days",
+                    ));
+                }
+            },
+            _ => TimeDelta::days(7),
+        };
+
+        let (pattern, is_regex) = match rule.strip_prefix('/').and_then(|s| s.strip_suffix('/')) {
+            Some(stripped) => (stripped.to_string(), true),
+            None => (rule.clone(), false),
+        };
+
+        if is_regex && let Err(err) = build_rule_regex(&pattern) {
+            return Err(CommandError {
+                title: String::from("Invalid regex"),
+                hint: Some(clamp_chars(err.reason, 200)),
+                arg: Some(_rule_arg),
+            });
+        }
+
+        let preview = Rule::preview(guild_id, pattern, is_regex, kind == "ocr");
+
+        trace.point("running_dry_run");
+        let report = if kind == "ocr" {
+            Self::run_ocr(guild_id, &preview, since).await?
+        } else {
+            Self::run_text(guild_id, &preview, since).await?
+        };
+
+        let mut per_user: HashMap<u64, (String, usize)> = HashMap::new();
+        for m in &report.matches {
+            per_user
+                .entry(m.author_id)
+                .or_insert_with(|| (m.author_name.clone(), 0))
+                .1 += 1;
+        }
+        let mut per_user = per_user.into_iter().collect::<Vec<_>>();
+        per_user.sort_by_key(|(_, (_, count))| std::cmp::Reverse(*count));
+
+        let mut stats = format!(
+            "Type: {kind} | Period: {} | Scanned: {} {} | Matches: {} | Users: {}",
+            time_string(since).trim_start_matches("for "),
+            report.scanned,
+            if kind == "ocr" { "images" } else { "messages" },
+            report.matches.len(),
+            per_user.len()
+        );
+        if kind == "ocr" {
+            let flagged = report
+                .matches
+                .iter()
+                .filter(|m| m.flagged_by.is_some())
+                .count();
+            stats.push_str(&format!(
+                " | Unavailable: {} | Already flagged: {flagged}",
+                report.unavailable
+            ));
+        }
+
+        let mut description = format!(
+            "**RULE DRY RUN**\n-# {stats}\n-# No actions were taken\n```\n{}\n```",
+            clamp_chars(rule.replace("```", "\\`\\`\\`"), 200)
+        );
+
+        if !per_user.is_empty() {
+            description.push_str("\n**Would punish**\n");
+            for (id, (name, count)) in per_user.iter().take(10) {
+                description.push_str(&format!(
+                    "<@{id}> `{}` - {count} match{}\n",
+                    clamp_chars(name.replace('`', ""), 32),
+                    if *count == 1 { "" } else { "es" }
+                ));
+            }
+            if per_user.len() > 10 {
+                description.push_str(&format!("-# and {} more\n", per_user.len() - 10));
+            }
+
+            description.push_str("\n**Samples**\n");
+            for m in report.matches.iter().take(3) {
+                let flagged = m
+                    .flagged_by
+                    .as_ref()
+                    .map(|id| format!(" | Flagged by: `{id}`"))
+                    .unwrap_or_default();
+
+                description.push_str(&format!(
+                    "-# <t:{0}:d> <t:{0}:T> | <@{1}> in <#{2}> | ID: `{3}`{flagged}\n```\n{4}\n```\n",
+                    m.created_at,
+                    m.author_id,
+                    m.channel_id,
+                    m.message_id,
+                    clamp_chars(m.content.replace("```", "\\`\\`\\`"), 150)
+                ));
+            }
+        }
+
+        let mut reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if !report.matches.is_empty() {
+            let full = report
+                .matches
+                .iter()
+                .map(|m| {
+                    format!(
+                        "[{}] {} ({}) in {} | message {}{}\n{}\n",
+                        chrono::DateTime::from_timestamp(m.created_at, 0)
+                            .map(|t| t.to_rfc3339())
+                            .unwrap_or_default(),
+                        m.author_name,
+                        m.author_id,
+                        m.channel_id,
+                        m.message_id,
+                        m.flagged_by
+                            .as_ref()
+                            .map(|id| format!(" | flagged by {id}"))
+                            .unwrap_or_default(),
+                        m.content
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            reply = reply.add_file(CreateAttachment::bytes(full.into_bytes(), "dry_run.txt"));
+        }
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RULE DRY RUN RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
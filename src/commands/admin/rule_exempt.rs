@@ -0,0 +1,263 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{consume_pgsql_error, consume_serenity_error, rule_cache::RuleExemptions},
+};
+use aegis_macros::command;
+
+enum ExemptTarget {
+    Role(u64),
+    Channel(u64),
+    User(u64),
+}
+
+pub struct RuleExempt;
+
+impl RuleExempt {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Resolves a role, channel or user mention. Plain IDs are looked up as role, then channel, otherwise treated as a user
+    async fn resolve_target(ctx: &Context, msg: &Message, input: &str) -> Option<ExemptTarget> {
+        let digits = |s: &str| s.trim_end_matches('>').parse::<u64>().ok();
+
+        if let Some(id) = input.strip_prefix("<@&") {
+            return digits(id).map(ExemptTarget::Role);
+        }
+        if let Some(id) = input.strip_prefix("<#") {
+            return digits(id).map(ExemptTarget::Channel);
+        }
+        if let Some(id) = input.strip_prefix("<@!").or(input.strip_prefix("<@")) {
+            return digits(id).map(ExemptTarget::User);
+        }
+
+        let id = input.parse::<u64>().ok()?;
+        let guild_id = msg.guild_id?;
+
+        if let Ok(roles) = guild_id.roles(ctx).await
+            && roles.keys().any(|r| r.get() == id)
+        {
+            return Some(ExemptTarget::Role(id));
+        }
+
+        if let Ok(channels) = guild_id.channels(ctx).await
+            && channels.keys().any(|c| c.get() == id)
+        {
+            return Some(ExemptTarget::Channel(id));
+        }
+
+        Some(ExemptTarget::User(id))
+    }
+}
+
+#[async_trait]
+impl Command for RuleExempt {
+    fn get_name(&self) -> &'static str {
+        "rule_exempt"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Manages the exemptions of an automod rule"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Manages the roles, users and channels an automod rule does not apply to. \
+        `rule_exempt <id> add <target>` and `rule_exempt <id> remove <target>` take a role, channel or user mention or ID. \
+        `rule_exempt <id> mode include` turns the channel list into the only channels the rule applies in, \
+        `rule_exempt <id> mode exclude` turns it back into exempt channels. \
+        Running the command with only the rule ID shows the current exemptions. \
+        Members with the manage messages permission are always exempt."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("id", true),
+            CommandSyntax::String("add/remove/mode", false),
+            CommandSyntax::String("target", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::some_string] id: String,
+        #[transformers::string] action: Option<String>,
+        #[transformers::string] target: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        trace.point("fetching_rule");
+        let row = sqlx::query(
+            "SELECT name, exempt_roles, exempt_users, channels, channels_include_only FROM automod_rules WHERE id = $1 AND guild_id = $2",
+        )
+        .bind(id.as_str())
+        .bind(guild_id.get() as i64)
+        .fetch_optional(&*SQL)
+        .await;
+
+        let row = match row {
+            Ok(Some(r)) => r,
+            Ok(None) => {
+                return Err(CommandError {
+                    title: format!("No rule with ID `{id}` found in this server"),
+                    hint: Some(String::from("use +rules to list all rules")),
+                    arg: Some(_id_arg),
+                });
+            }
+            Err(err) => {
+                consume_pgsql_error("RULE EXEMPT FETCH".into(), err);
+                return Err(CommandError {
+                    title: String::from("Could not query the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        let rule_name: String = row.get("name");
+        let mut exemptions = RuleExemptions::from_row(&row);
+
+        let title = match action.as_deref().map(str::to_lowercase).as_deref() {
+            None => "EXEMPTIONS",
+            Some(op @ ("add" | "remove")) => {
+                let Some(input) = target else {
+                    return Err(CommandError::new("Missing target argument"));
+                };
+
+                let Some(resolved) = Self::resolve_target(&ctx, &msg, &input).await else {
+                    return Err(CommandError {
+                        title: String::from("Expected a role, channel or user"),
+                        hint: Some(String::from("provide a mention or an ID")),
+                        arg: _target_arg,
+                    });
+                };
+
+                let (list, target_id) = match resolved {
+                    ExemptTarget::Role(target_id) => (&mut exemptions.roles, target_id),
+                    ExemptTarget::Channel(target_id) => (&mut exemptions.channels, target_id),
+                    ExemptTarget::User(target_id) => (&mut exemptions.users, target_id),
+                };
+
+                if op == "add" {
+                    if !list.contains(&target_id) {
+                        list.push(target_id);
+                    }
+                    "EXEMPTION ADDED"
+                } else {
+                    list.retain(|i| *i != target_id);
+                    "EXEMPTION REMOVED"
+                }
+            }
+            Some("mode") => {
+                exemptions.include_only = match target.as_deref().map(str::to_lowercase).as_deref()
+                {
+                    Some("include") => true,
+                    Some("exclude") => false,
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Expected include or exclude"),
+                            hint: None,
+                            arg: _target_arg,
+                        });
+                    }
+                };
+                "CHANNEL MODE UPDATED"
+            }
+            Some(_) => {
+                return Err(CommandError {
+                    title: String::from("Unknown action"),
+                    hint: Some(String::from("use add, remove or mode")),
+                    arg: _action_arg,
+                });
+            }
+        };
+
+        if action.is_some() {
+            trace.point("updating_database");
+            let to_i64 = |ids: &[u64]| ids.iter().map(|id| *id as i64).collect::<Vec<i64>>();
+
+            if let Err(err) = sqlx::query(
+                "UPDATE automod_rules SET exempt_roles = $1, exempt_users = $2, channels = $3, channels_include_only = $4 WHERE id = $5",
+            )
+            .bind(to_i64(&exemptions.roles))
+            .bind(to_i64(&exemptions.users))
+            .bind(to_i64(&exemptions.channels))
+            .bind(exemptions.include_only)
+            .bind(id.as_str())
+            .execute(&*SQL)
+            .await
+            {
+                consume_pgsql_error("RULE EXEMPT UPDATE".into(), err);
+                return Err(CommandError::new("Could not update the database"));
+            }
+
+            handler
+                .rule_cache
+                .lock()
+                .await
+                .set_exemptions(&id, exemptions.clone());
+        }
+
+        let details = if exemptions.is_empty() {
+            String::from("No exemptions")
+        } else {
+            exemptions.describe()
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} {title}**\n-# ID: `{id}`\n{details}",
+                        rule_name.to_uppercase()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RULE EXEMPT RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
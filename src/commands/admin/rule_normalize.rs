@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        consume_pgsql_error, consume_serenity_error,
+        normalize::{NormalizeStep, describe_steps, parse_steps},
+        rule_cache::db_clear_image_hashes,
+    },
+};
+use aegis_macros::command;
+
+pub struct RuleNormalize;
+
+impl RuleNormalize {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for RuleNormalize {
+    fn get_name(&self) -> &'static str {
+        "rule_normalize"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures the text normalization of an automod rule"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures how message text and OCR output are normalized before an automod rule is matched against them. \
+        Available steps: `invisible` removes zero-width characters, `width` maps fullwidth and fancy font letters to normal ones, \
+        `diacritics` strips accents and zalgo, `confusables` maps cyrillic and greek look-alikes to latin letters \
+        and `leetspeak` maps digits and symbols such as `3` or `@` to letters. \
+        `rule_normalize <id> <steps>` sets the steps, e.g. `rule_normalize abc123 invisible width leetspeak`, \
+        `all`, `none` and `default` are accepted as well. Running the command with only the rule ID shows the current steps."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("id", true),
+            CommandSyntax::Consume("steps"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::some_string] id: String,
+        #[transformers::string_consume] steps: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        trace.point("fetching_rule");
+        let row = sqlx::query(
+            "SELECT name, normalization FROM automod_rules WHERE id = $1 AND guild_id = $2",
+        )
+        .bind(id.as_str())
+        .bind(guild_id.get() as i64)
+        .fetch_optional(&*SQL)
+        .await;
+
+        let row = match row {
+            Ok(Some(r)) => r,
+            Ok(None) => {
+                return Err(CommandError {
+                    title: format!("No rule with ID `{id}` found in this server"),
+                    hint: Some(String::from("use +rules to list all rules")),
+                    arg: Some(_id_arg),
+                });
+            }
+            Err(err) => {
+                consume_pgsql_error("RULE NORMALIZE FETCH".into(), err);
+                return Err(CommandError {
+                    title: String::from("Could not query the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        let rule_name: String = row.get("name");
+
+        let Some(input) = steps else {
+            let current = parse_steps(&row.get::<Vec<String>, _>("normalization"));
+
+            let reply = CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**{} NORMALIZATION**\n-# ID: `{id}`\n{}",
+                            rule_name.to_uppercase(),
+                            describe_steps(&current)
+                        ))
+                        .color(BRAND_BLUE),
+                )
+                .reference_message(&msg)
+                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+                consume_serenity_error("RULE NORMALIZE RESPONSE".into(), err);
+            }
+
+            return Ok(());
+        };
+
+        let mut new_steps = vec![];
+        for name in input
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|s| !s.is_empty())
+        {
+            match name.to_lowercase().as_str() {
+                "all" => new_steps.extend(NormalizeStep::ALL),
+                "default" => new_steps.extend(NormalizeStep::DEFAULT),
+                "none" => {}
+                other => match other.parse::<NormalizeStep>() {
+                    Ok(step) => new_steps.push(step),
+                    Err(_) => {
+                        return Err(CommandError {
+                            title: format!("Unknown normalization step `{other}`"),
+                            hint: Some(String::from(
+                                "use invisible, width, diacritics, confusables, leetspeak, all, default or none",
+                            )),
+                            arg: _steps_arg,
+                        });
+                    }
+                },
+            }
+        }
+        new_steps.sort();
+        new_steps.dedup();
+
+        trace.point("updating_database");
+        if let Err(err) = sqlx::query("UPDATE automod_rules SET normalization = $1 WHERE id = $2")
+            .bind(
+                new_steps
+                    .iter()
+                    .map(|step| step.name().to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .bind(id.as_str())
+            .execute(&*SQL)
+            .await
+        {
+            consume_pgsql_error("RULE NORMALIZE UPDATE".into(), err);
+            return Err(CommandError::new("Could not update the database"));
+        }
+        db_clear_image_hashes(&id).await;
+
+        let description = describe_steps(&new_steps);
+        handler
+            .rule_cache
+            .lock()
+            .await
+            .set_normalization(&id, new_steps);
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} NORMALIZATION UPDATED**\n-# ID: `{id}`\n{description}",
+                        rule_name.to_uppercase()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RULE NORMALIZE RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{clamp_chars, consume_pgsql_error, consume_serenity_error},
+};
+use aegis_macros::command;
+
+/// A punishment was reversed if the punished user got an unban or unmute for it,
+/// i.e. one recorded after the punishment and before the next punishment of the same type.
+/// The user is taken from the rule hit, as not every action stores the target in `user_id`.
+const REVERSED: &str = "EXISTS (\
+        SELECT 1 FROM actions u WHERE u.guild_id = a.guild_id AND u.user_id = h.user_id \
+        AND u.created_at >= a.created_at \
+        AND ((a.type = 'ban' AND u.type = 'unban') OR (a.type = 'mute' AND u.type = 'unmute')) \
+        AND NOT EXISTS (\
+            SELECT 1 FROM actions p WHERE p.guild_id = a.guild_id AND p.type = a.type AND p.id <> a.id \
+            AND (p.user_id = h.user_id OR p.moderator_id = h.user_id) \
+            AND p.created_at > a.created_at AND p.created_at <= u.created_at))";
+
+const HIT_COUNTS: &str = "COUNT(h.id) FILTER (WHERE h.created_at >= NOW() - INTERVAL '1 day') AS day, \
+    COUNT(h.id) FILTER (WHERE h.created_at >= NOW() - INTERVAL '7 days') AS week, \
+    COUNT(h.id) FILTER (WHERE h.created_at >= NOW() - INTERVAL '30 days') AS month, \
+    COUNT(h.id) AS total, \
+    COUNT(h.id) FILTER (WHERE h.punished) AS punished";
+
+pub struct RuleStats;
+
+impl RuleStats {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+struct HitCounts {
+    day: i64,
+    week: i64,
+    month: i64,
+    total: i64,
+    punished: i64,
+    reversed: i64,
+}
+
+impl HitCounts {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        Self {
+            day: row.get("day"),
+            week: row.get("week"),
+            month: row.get("month"),
+            total: row.get("total"),
+            punished: row.get("punished"),
+            reversed: row.get("reversed"),
+        }
+    }
+
+    /// e.g. `24h: 2 | 7d: 5 | 30d: 9 | Total: 12 | Punished: 10 | Reversed: 1`
+    fn describe(&self) -> String {
+        format!(
+            "24h: {} | 7d: {} | 30d: {} | Total: {} | Punished: {} | Reversed: {}",
+            self.day, self.week, self.month, self.total, self.punished, self.reversed
+        )
+    }
+}
+
+#[async_trait]
+impl Command for RuleStats {
+    fn get_name(&self) -> &'static str {
+        "rule_stats"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Shows how often the automod rules are triggered"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Shows how often the automod rules of this server are triggered. \
+        Without arguments every rule is listed with its hits in the last 24 hours, 7 days and 30 days, \
+        how many of them were punished and how many punishments were reversed later on by an unban or unmute. \
+        Hits that were not punished were skipped by the rules cooldown. \
+        `rule_stats <id>` additionally shows how the hits were detected, the top offenders of the last 30 days \
+        and the most recent reversals, which are a good hint for false positives."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![CommandSyntax::String("id", false)]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::string] id: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let description = match id {
+            None => {
+                trace.point("fetching_overview");
+                let rows = sqlx::query(&format!(
+                    "SELECT r.id, r.name, r.type, {HIT_COUNTS}, COUNT(a.id) FILTER (WHERE {REVERSED}) AS reversed \
+                     FROM automod_rules r \
+                     LEFT JOIN rule_hits h ON h.rule_id = r.id \
+                     LEFT JOIN actions a ON a.id = h.action_id \
+                     WHERE r.guild_id = $1 \
+                     GROUP BY r.id, r.name, r.type \
+                     ORDER BY month DESC, total DESC, r.created_at"
+                ))
+                .bind(guild_id.get() as i64)
+                .fetch_all(&*SQL)
+                .await;
+
+                let rows = match rows {
+                    Ok(rows) => rows,
+                    Err(err) => {
+                        consume_pgsql_error("RULE STATS OVERVIEW".into(), err);
+                        return Err(CommandError {
+                            title: String::from("Could not query the database"),
+                            hint: Some(String::from("please try again later")),
+                            arg: None,
+                        });
+                    }
+                };
+
+                if rows.is_empty() {
+                    String::from("**RULE STATISTICS**\n-# This server has no automod rules")
+                } else {
+                    let mut lines = vec![];
+                    let mut idle = vec![];
+
+                    for row in &rows {
+                        let id: String = row.get("id");
+                        let name: String = row.get("name");
+                        let kind: String = row.get("type");
+                        let counts = HitCounts::from_row(row);
+
+                        if counts.month == 0 {
+                            idle.push(format!("`{id}` {}", clamp_chars(name, 25)));
+                            continue;
+                        }
+
+                        lines.push(format!(
+                            "**{}** `{id}` ({kind})\n-# {}",
+                            clamp_chars(name, 40),
+                            counts.describe()
+                        ));
+                    }
+
+                    let mut description = String::from("**RULE STATISTICS**");
+                    for line in lines {
+                        if description.len() + line.len() > 3500 {
+                            description.push_str("\n-# ...");
+                            break;
+                        }
+                        description.push('\n');
+                        description.push_str(&line);
+                    }
+
+                    if !idle.is_empty() {
+                        description.push_str(&format!(
+                            "\n\n**NO HITS IN 30 DAYS**\n-# {}",
+                            clamp_chars(idle.join(", "), 500)
+                        ));
+                    }
+
+                    description
+                }
+            }
+            Some(id) => {
+                trace.point("fetching_rule");
+                let row = sqlx::query(&format!(
+                    "SELECT r.name, {HIT_COUNTS}, COUNT(a.id) FILTER (WHERE {REVERSED}) AS reversed \
+                     FROM automod_rules r \
+                     LEFT JOIN rule_hits h ON h.rule_id = r.id \
+                     LEFT JOIN actions a ON a.id = h.action_id \
+                     WHERE r.id = $1 AND r.guild_id = $2 \
+                     GROUP BY r.id, r.name"
+                ))
+                .bind(id.as_str())
+                .bind(guild_id.get() as i64)
+                .fetch_optional(&*SQL)
+                .await;
+
+                let row = match row {
+                    Ok(Some(r)) => r,
+                    Ok(None) => {
+                        return Err(CommandError {
+                            title: format!("No rule with ID `{id}` found in this server"),
+                            hint: Some(String::from("use +rules to list all rules")),
+                            arg: _id_arg,
+                        });
+                    }
+                    Err(err) => {
+                        consume_pgsql_error("RULE STATS FETCH".into(), err);
+                        return Err(CommandError {
+                            title: String::from("Could not query the database"),
+                            hint: Some(String::from("please try again later")),
+                            arg: None,
+                        });
+                    }
+                };
+
+                let rule_name: String = row.get("name");
+                let counts = HitCounts::from_row(&row);
+
+                trace.point("fetching_details");
+                let sources = sqlx::query(
+                    "SELECT source, COUNT(*) AS hits FROM rule_hits WHERE rule_id = $1 GROUP BY source ORDER BY hits DESC",
+                )
+                .bind(id.as_str())
+                .fetch_all(&*SQL)
+                .await;
+
+                let offenders = sqlx::query(
+                    "SELECT user_id, COUNT(*) AS hits FROM rule_hits \
+                     WHERE rule_id = $1 AND created_at >= NOW() - INTERVAL '30 days' \
+                     GROUP BY user_id ORDER BY hits DESC LIMIT 5",
+                )
+                .bind(id.as_str())
+                .fetch_all(&*SQL)
+                .await;
+
+                let reversals = sqlx::query(&format!(
+                    "SELECT a.id, a.user_id, h.created_at FROM rule_hits h \
+                     JOIN actions a ON a.id = h.action_id \
+                     WHERE h.rule_id = $1 AND {REVERSED} \
+                     ORDER BY h.created_at DESC LIMIT 5"
+                ))
+                .bind(id.as_str())
+                .fetch_all(&*SQL)
+                .await;
+
+                let (sources, offenders, reversals) = match (sources, offenders, reversals) {
+                    (Ok(s), Ok(o), Ok(r)) => (s, o, r),
+                    (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+                        consume_pgsql_error("RULE STATS DETAILS".into(), err);
+                        return Err(CommandError {
+                            title: String::from("Could not query the database"),
+                            hint: Some(String::from("please try again later")),
+                            arg: None,
+                        });
+                    }
+                };
+
+                let sources = sources
+                    .iter()
+                    .map(|row| {
+                        format!(
+                            "{}: {}",
+                            row.get::<String, _>("source"),
+                            row.get::<i64, _>("hits")
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let offenders = offenders
+                    .iter()
+                    .map(|row| {
+                        format!(
+                            "<@{}> - {} hits",
+                            row.get::<i64, _>("user_id"),
+                            row.get::<i64, _>("hits")
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let reversals = reversals
+                    .iter()
+                    .map(|row| {
+                        let created_at: sqlx::types::chrono::NaiveDateTime = row.get("created_at");
+                        format!(
+                            "`{}` <@{}> <t:{}:R>",
+                            row.get::<String, _>("id"),
+                            row.get::<i64, _>("user_id"),
+                            created_at.and_utc().timestamp()
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let list = |items: Vec<String>| {
+                    if items.is_empty() {
+                        String::from("-# none")
+                    } else {
+                        items.join("\n")
+                    }
+                };
+
+                format!(
+                    "**{} STATISTICS**\n-# ID: `{id}`\n-# {}\n-# Sources: {}\n\n**TOP OFFENDERS (30 DAYS)**\n{}\n\n**RECENT REVERSALS**\n{}",
+                    rule_name.to_uppercase(),
+                    counts.describe(),
+                    if sources.is_empty() {
+                        String::from("none")
+                    } else {
+                        sources.join(" | ")
+                    },
+                    list(offenders),
+                    list(reversals)
+                )
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("RULE STATS RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
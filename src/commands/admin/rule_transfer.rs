@@ -0,0 +1,385 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    database::ActionType,
+    utils::{
+        attachment_filter::AttachmentFilter,
+        normalize::{NormalizeStep, describe_steps, parse_steps},
+        rule_cache::{Punishment, RuleCooldown},
+        rule_condition::RuleCondition,
+        rule_regex::build_rule_regex,
+    },
+};
+
+pub const RULE_KINDS: [&str; 4] = ["ocr", "text", "name", "attachment"];
+pub const RULE_PUNISHMENTS: [&str; 7] = ["warn", "kick", "ban", "softban", "mute", "log", "rename"];
+
+/// The file format used to move a guilds automod rules between guilds
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RuleFile {
+    #[serde(default)]
+    pub rules: Vec<RuleEntry>,
+}
+
+/// A single automod rule without anything guild specific besides the log channel.
+/// Regex patterns keep the slashes used by the rule creation commands.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleEntry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub pattern: String,
+    pub punishment: String,
+    #[serde(default)]
+    pub reason: String,
+    /// Seconds, 0 being permanent
+    #[serde(default)]
+    pub duration: u64,
+    #[serde(default)]
+    pub day_clear_amount: u8,
+    #[serde(default)]
+    pub silent: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_channel_id: Option<u64>,
+    /// Normalization step names, see [`NormalizeStep`]
+    #[serde(default = "default_normalization")]
+    pub normalization: Vec<String>,
+    /// Seconds between two punishments of the same user
+    #[serde(default = "default_debounce")]
+    pub debounce: u32,
+    /// Punishments per user within `punishment_period`, 0 for no limit
+    #[serde(default)]
+    pub max_punishments: u32,
+    /// Seconds
+    #[serde(default)]
+    pub punishment_period: u64,
+    #[serde(default = "default_delete_during_cooldown")]
+    pub delete_during_cooldown: bool,
+    /// Written condition replacing the pattern, see [`RuleCondition`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<String>,
+}
+
+fn default_debounce() -> u32 {
+    RuleCooldown::default().debounce.as_secs() as u32
+}
+
+fn default_delete_during_cooldown() -> bool {
+    RuleCooldown::default().delete_during_cooldown
+}
+
+fn default_normalization() -> Vec<String> {
+    NormalizeStep::DEFAULT
+        .iter()
+        .map(|step| step.name().to_string())
+        .collect()
+}
+
+impl RuleEntry {
+    /// The pattern without regex slashes and whether it is a regex
+    pub fn split_pattern(&self) -> (&str, bool) {
+        match self
+            .pattern
+            .strip_prefix('/')
+            .and_then(|s| s.strip_suffix('/'))
+        {
+            Some(stripped) => (stripped, true),
+            None => (self.pattern.as_str(), false),
+        }
+    }
+
+    pub fn punishment(&self) -> Punishment {
+        Punishment::from_parts(
+            &self.punishment,
+            self.reason.clone(),
+            self.day_clear_amount,
+            self.duration,
+            self.silent,
+            self.log_channel_id.unwrap_or(0),
+        )
+    }
+
+    pub fn normalization(&self) -> Vec<NormalizeStep> {
+        parse_steps(&self.normalization)
+    }
+
+    pub fn cooldown(&self) -> RuleCooldown {
+        RuleCooldown {
+            debounce: Duration::from_secs(self.debounce as u64),
+            max_punishments: self.max_punishments,
+            period: Duration::from_secs(self.punishment_period),
+            delete_during_cooldown: self.delete_during_cooldown,
+        }
+    }
+
+    /// The parsed condition, None if there is none or it is invalid
+    pub fn condition(&self) -> Option<RuleCondition> {
+        self.condition
+            .as_deref()
+            .and_then(|c| RuleCondition::parse(c).ok())
+    }
+
+    /// Returns why the rule can not be imported
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() || self.name.len() >= 100 {
+            return Err(String::from(
+                "name has to be between 1 and 100 characters long",
+            ));
+        }
+        if self.pattern.is_empty() || self.pattern.len() >= 500 {
+            return Err(String::from(
+                "pattern has to be between 1 and 500 characters long",
+            ));
+        }
+        if !RULE_KINDS.contains(&self.kind.as_str()) {
+            return Err(format!(
+                "unknown type `{}`, expected ocr, text, name or attachment",
+                self.kind
+            ));
+        }
+        if !RULE_PUNISHMENTS.contains(&self.punishment.as_str()) {
+            return Err(format!(
+                "unknown punishment `{}`, expected one of {}",
+                self.punishment,
+                RULE_PUNISHMENTS.join(", ")
+            ));
+        }
+        if self.punishment == "log" && self.log_channel_id.is_none() {
+            return Err(String::from("log punishments need a log_channel_id"));
+        }
+        if self.day_clear_amount > 7 {
+            return Err(String::from("day_clear_amount can be at most 7"));
+        }
+        if self.duration > i64::MAX as u64 {
+            return Err(String::from("duration is too large"));
+        }
+        if self.debounce > i32::MAX as u32 || self.max_punishments > i32::MAX as u32 {
+            return Err(String::from("debounce or max_punishments is too large"));
+        }
+        if self.punishment_period > i64::MAX as u64 {
+            return Err(String::from("punishment_period is too large"));
+        }
+        if self.max_punishments != 0 && self.punishment_period == 0 {
+            return Err(String::from(
+                "max_punishments needs a punishment_period of at least 1 second",
+            ));
+        }
+
+        if let Some(unknown) = self
+            .normalization
+            .iter()
+            .find(|name| name.parse::<NormalizeStep>().is_err())
+        {
+            return Err(format!("unknown normalization step `{unknown}`"));
+        }
+
+        if let Some(condition) = &self.condition {
+            if self.kind == "attachment" {
+                return Err(String::from("attachment rules can not have a condition"));
+            }
+            if condition.len() >= 500 {
+                return Err(String::from(
+                    "condition has to be less than 500 characters long",
+                ));
+            }
+            RuleCondition::parse(condition)
+                .map_err(|err| format!("invalid condition: {}", err.reason))?;
+        }
+
+        if self.kind == "attachment" {
+            AttachmentFilter::parse(&self.pattern)
+                .map_err(|err| format!("invalid attachment conditions: {err}"))?;
+            return Ok(());
+        }
+
+        let (pattern, is_regex) = self.split_pattern();
+        if is_regex && let Err(err) = build_rule_regex(pattern) {
+            return Err(format!("invalid regex: {}", err.reason));
+        }
+
+        Ok(())
+    }
+
+    /// A single line summary used in the import diff
+    pub fn describe(&self) -> String {
+        let mut punishment = self.punishment.clone();
+        if matches!(self.punishment.as_str(), "ban" | "mute") && self.duration != 0 {
+            punishment.push_str(&format!(" {}s", self.duration));
+        }
+        if self.silent {
+            punishment.push_str(" silent");
+        }
+        if self.normalization() != NormalizeStep::DEFAULT {
+            punishment.push_str(&format!(
+                " normalize: {}",
+                describe_steps(&self.normalization())
+            ));
+        }
+        if self.cooldown() != RuleCooldown::default() {
+            punishment.push_str(&format!(" | {}", self.cooldown().describe()));
+        }
+
+        format!(
+            "[{}] {}: {} -> {punishment} ({})",
+            self.kind,
+            self.name,
+            self.condition.as_deref().unwrap_or(&self.pattern),
+            self.reason
+        )
+    }
+}
+
+/// Fetches the automod rules of a guild as (rule id, entry) pairs
+pub async fn fetch_rule_entries(guild_id: u64) -> Result<Vec<(String, RuleEntry)>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT id, name, type, rule, is_regex, reason, punishment_type, duration, day_clear_amount, silent, log_channel_id, normalization, \
+         debounce, max_punishments, punishment_period, delete_during_cooldown, condition \
+         FROM automod_rules WHERE guild_id = $1 ORDER BY created_at",
+    )
+    .bind(guild_id as i64)
+    .fetch_all(&*SQL)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let rule: String = row.get("rule");
+            let punishment_type: ActionType = row.get("punishment_type");
+
+            (
+                row.get("id"),
+                RuleEntry {
+                    name: row.get("name"),
+                    kind: row.get("type"),
+                    pattern: if row.get("is_regex") {
+                        format!("/{rule}/")
+                    } else {
+                        rule
+                    },
+                    punishment: punishment_type.to_string(),
+                    reason: row.get("reason"),
+                    duration: row.get::<Option<i64>, _>("duration").unwrap_or(0) as u64,
+                    day_clear_amount: row.get::<Option<i16>, _>("day_clear_amount").unwrap_or(0)
+                        as u8,
+                    silent: row.get::<Option<bool>, _>("silent").unwrap_or(false),
+                    log_channel_id: row
+                        .get::<Option<i64>, _>("log_channel_id")
+                        .filter(|id| *id != 0)
+                        .map(|id| id as u64),
+                    normalization: row.get("normalization"),
+                    debounce: row.get::<i32, _>("debounce") as u32,
+                    max_punishments: row.get::<i32, _>("max_punishments") as u32,
+                    punishment_period: row.get::<i64, _>("punishment_period") as u64,
+                    delete_during_cooldown: row.get("delete_during_cooldown"),
+                    condition: row
+                        .get::<Option<serde_json::Value>, _>("condition")
+                        .and_then(|value| serde_json::from_value::<RuleCondition>(value).ok())
+                        .map(|c| c.describe()),
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: &str, pattern: &str, punishment: &str) -> RuleEntry {
+        RuleEntry {
+            name: String::from("rule"),
+            kind: kind.to_string(),
+            pattern: pattern.to_string(),
+            punishment: punishment.to_string(),
+            reason: String::from("spam"),
+            duration: 0,
+            day_clear_amount: 0,
+            silent: false,
+            log_channel_id: None,
+            normalization: default_normalization(),
+            debounce: default_debounce(),
+            max_punishments: 0,
+            punishment_period: 0,
+            delete_during_cooldown: default_delete_during_cooldown(),
+            condition: None,
+        }
+    }
+
+    #[test]
+    fn splits_regex_patterns() {
+        assert_eq!(
+            entry("text", "/a/b/", "warn").split_pattern(),
+            ("a/b", true)
+        );
+        assert_eq!(
+            entry("text", "nitro", "warn").split_pattern(),
+            ("nitro", false)
+        );
+        assert_eq!(entry("text", "/", "warn").split_pattern(), ("/", false));
+    }
+
+    #[test]
+    fn accepts_valid_entries() {
+        assert!(entry("text", "nitro", "warn").validate().is_ok());
+        assert!(entry("ocr", "/n[i1]tro/", "ban").validate().is_ok());
+        assert!(entry("attachment", "ext:exe", "kick").validate().is_ok());
+
+        let mut conditional = entry("text", "unused", "warn");
+        conditional.condition = Some(String::from("steam AND NOT official"));
+        assert!(conditional.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_entries() {
+        let invalid = |modify: fn(&mut RuleEntry)| {
+            let mut entry = entry("text", "nitro", "warn");
+            modify(&mut entry);
+            entry.validate().unwrap_err()
+        };
+
+        assert!(invalid(|e| e.name.clear()).starts_with("name"));
+        assert!(invalid(|e| e.kind = String::from("voice")).starts_with("unknown type"));
+        assert!(invalid(|e| e.punishment = String::from("jail")).starts_with("unknown punishment"));
+        assert_eq!(
+            invalid(|e| e.punishment = String::from("log")),
+            "log punishments need a log_channel_id"
+        );
+        assert_eq!(
+            invalid(|e| e.day_clear_amount = 8),
+            "day_clear_amount can be at most 7"
+        );
+        assert!(invalid(|e| e.max_punishments = 3).starts_with("max_punishments needs"));
+        assert_eq!(
+            invalid(|e| e.normalization = vec![String::from("rot13")]),
+            "unknown normalization step `rot13`"
+        );
+        assert!(invalid(|e| e.pattern = String::from("/n[itro/")).starts_with("invalid regex"));
+        assert!(
+            invalid(|e| e.condition = Some(String::from("NOT nitro")))
+                .starts_with("invalid condition")
+        );
+        assert!(
+            invalid(|e| e.kind = String::from("attachment"))
+                .starts_with("invalid attachment conditions")
+        );
+    }
+
+    #[test]
+    fn describes_entries() {
+        let mut entry = entry("text", "nitro", "ban");
+        assert_eq!(entry.describe(), "[text] rule: nitro -> ban (spam)");
+
+        entry.duration = 3600;
+        entry.silent = true;
+        entry.normalization = vec![String::from("leetspeak")];
+        entry.condition = Some(String::from("nitro AND gift"));
+        assert_eq!(
+            entry.describe(),
+            "[text] rule: nitro AND gift -> ban 3600s silent normalize: leetspeak (spam)"
+        );
+    }
+}
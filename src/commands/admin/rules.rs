@@ -10,6 +10,7 @@ use serenity::{
     },
     async_trait,
 };
+use sqlx::FromRow;
 use tracing::warn;
 
 use crate::{
@@ -23,9 +24,17 @@ use crate::{
     event_handler::{CommandError, Handler},
     lexer::Token,
     transformers::Transformers,
+    utils::{
+        clamp_chars,
+        normalize::{describe_steps, parse_steps},
+        rule_cache::{RuleCooldown, RuleExemptions},
+        rule_condition::RuleCondition,
+    },
 };
 
-#[derive(Debug, Clone)]
+const RECORD_COLUMNS: &str = "id, guild_id, name, type, rule, is_regex, created_at, reason, punishment_type, duration, day_clear_amount, silent, exempt_roles, exempt_users, channels, channels_include_only, normalization, debounce, max_punishments, punishment_period, delete_during_cooldown, condition";
+
+#[derive(Debug, Clone, FromRow)]
 struct LogRecord {
     id: String,
     guild_id: i64,
@@ -39,6 +48,44 @@ struct LogRecord {
     duration: ::std::option::Option<i64>,
     day_clear_amount: ::std::option::Option<i16>,
     silent: ::std::option::Option<bool>,
+    exempt_roles: Vec<i64>,
+    exempt_users: Vec<i64>,
+    channels: Vec<i64>,
+    channels_include_only: bool,
+    normalization: Vec<String>,
+    debounce: i32,
+    max_punishments: i32,
+    punishment_period: i64,
+    delete_during_cooldown: bool,
+    condition: Option<serde_json::Value>,
+}
+
+impl LogRecord {
+    fn exemptions(&self) -> RuleExemptions {
+        let ids = |ids: &[i64]| ids.iter().map(|id| *id as u64).collect();
+
+        RuleExemptions {
+            roles: ids(&self.exempt_roles),
+            users: ids(&self.exempt_users),
+            channels: ids(&self.channels),
+            include_only: self.channels_include_only,
+        }
+    }
+
+    fn condition(&self) -> Option<RuleCondition> {
+        self.condition
+            .clone()
+            .and_then(|value| serde_json::from_value(value).ok())
+    }
+
+    fn cooldown(&self) -> RuleCooldown {
+        RuleCooldown {
+            debounce: Duration::from_secs(self.debounce as u64),
+            max_punishments: self.max_punishments as u32,
+            period: Duration::from_secs(self.punishment_period as u64),
+            delete_during_cooldown: self.delete_during_cooldown,
+        }
+    }
 }
 
 pub struct Rules;
@@ -49,15 +96,13 @@ impl Rules {
     }
 
     async fn get_one_response(&self, guild_id: i64, log: String) -> Result<String, CommandError> {
-        let res = sqlx::query_as!(
-            LogRecord,
-            r#"
-                SELECT id, guild_id, name, type, rule, is_regex, created_at, reason, punishment_type as "punishment_type!: ActionType", duration, day_clear_amount, silent FROM automod_rules WHERE guild_id = $1 AND id = $2;
-            "#,
-            guild_id,
-            log
-        )
-        .fetch_optional(&*SQL).await;
+        let res = sqlx::query_as::<_, LogRecord>(&format!(
+            "SELECT {RECORD_COLUMNS} FROM automod_rules WHERE guild_id = $1 AND id = $2"
+        ))
+        .bind(guild_id)
+        .bind(log)
+        .fetch_optional(&*SQL)
+        .await;
 
         let data = match res {
             Ok(d) => d,
@@ -110,7 +155,14 @@ impl Rules {
         chunk.iter().for_each(|data| {
             let record = data.clone();
 
-            let rule = {
+            let rule = if let Some(condition) = data.condition() {
+                let condition = condition.describe();
+                if compact {
+                    clamp_chars(condition, 100)
+                } else {
+                    condition
+                }
+            } else {
                 let r = if record.rule.len() > 100 && compact {
                     format!("{}...", &record.rule[..97])
                 } else {
@@ -159,6 +211,7 @@ impl Rules {
 
             let punishment = match record.punishment_type {
                 ActionType::Warn => format!(" | Punishment: Warn | Reason: {}", record.reason),
+                ActionType::Kick => format!(" | Punishment: Kick | Reason: {}", record.reason),
                 ActionType::Softban => {
                     format!(" | Punishment: Softban | Reason: {}", record.reason)
                 }
@@ -166,18 +219,40 @@ impl Rules {
                     " | Punishment: Ban | Reason: {} | Duration: {}",
                     record.reason, time_string
                 ),
+                ActionType::Mute => format!(
+                    " | Punishment: Mute | Reason: {} | Duration: {}",
+                    record.reason, time_string
+                ),
+                ActionType::Log => format!(" | Punishment: Log | Reason: {}", record.reason),
+                ActionType::Rename => {
+                    format!(" | Punishment: Rename | Reason: {}", record.reason)
+                }
                 _ => String::from("unexpected"),
             };
 
+            let exemptions = data.exemptions();
+            let exemptions = if exemptions.is_empty() {
+                String::new()
+            } else {
+                format!("\n-# {}", exemptions.describe())
+            };
+            let normalization = format!(
+                "\n-# Normalization: {}\n-# {}",
+                describe_steps(&parse_steps(&record.normalization)),
+                data.cooldown().describe()
+            );
+
             response.push_str(
                 format!(
-                    "**{0}**\n-# ID: `{1}` | Type: {2} | Created: <t:{3}:d> <t:{3}:T>{4}\n```\n{5}\n```\n\n",
+                    "**{0}**\n-# ID: `{1}` | Type: {2} | Created: <t:{3}:d> <t:{3}:T>{4}{6}{7}\n```\n{5}\n```\n\n",
                     record.name,
                     record.id,
                     record.r#type,
                     record.created_at.and_utc().timestamp(),
                     punishment,
-                    rule
+                    rule,
+                    exemptions,
+                    normalization
                 )
                 .as_str(),
             );
@@ -227,14 +302,12 @@ impl Command for Rules {
 
         trace.point("fetching_rules");
 
-        let res = sqlx::query_as!(
-            LogRecord,
-            r#"
-                SELECT id, guild_id, name, type, rule, is_regex, created_at, reason, punishment_type as "punishment_type!: ActionType", duration, day_clear_amount, silent FROM automod_rules WHERE guild_id = $1;
-            "#,
-            msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64
-        )
-        .fetch_all(&*SQL).await;
+        let res = sqlx::query_as::<_, LogRecord>(&format!(
+            "SELECT {RECORD_COLUMNS} FROM automod_rules WHERE guild_id = $1 ORDER BY created_at"
+        ))
+        .bind(msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64)
+        .fetch_all(&*SQL)
+        .await;
 
         let data = match res {
             Ok(d) => d,
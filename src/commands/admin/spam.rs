@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage, Message, Permissions,
+    },
+    async_trait,
+};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    transformers::Transformers,
+    utils::{clamp_chars, consume_pgsql_error, consume_serenity_error, spam_cache::SpamFilter},
+};
+use aegis_macros::command;
+
+pub struct Spam;
+
+impl Spam {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Spam {
+    fn get_name(&self) -> &'static str {
+        "spam"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures the servers spam filter"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures the servers spam filter. \
+        `spam set <channel> <server> <duplicates> <period>` triggers once a member sends `channel` messages in one channel, \
+        `server` messages across the server or the same message in `duplicates` different channels within `period`. \
+        Use 0 to turn a single check off, e.g. `spam set 6 10 3 5s`. \
+        The messages of the burst are deleted and you will be asked for the punishment to apply. \
+        `spam disable` turns the filter off, running the command without arguments shows the current configuration. \
+        Members with the manage messages permission are exempt."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("set/disable", false),
+            CommandSyntax::Number("channel", false),
+            CommandSyntax::Number("server", false),
+            CommandSyntax::Number("duplicates", false),
+            CommandSyntax::Duration("period", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        handler: &Handler,
+        #[transformers::string] subcommand: Option<String>,
+        #[transformers::i32] channel_limit: Option<i32>,
+        #[transformers::i32] guild_limit: Option<i32>,
+        #[transformers::i32] duplicate_channels: Option<i32>,
+        #[transformers::string] period: Option<String>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let description = match subcommand.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("show") => {
+                let filter = handler.spam_cache.lock().await.get(guild_id.get());
+                match filter {
+                    Some(f) => format!(
+                        "**SPAM FILTER**\n-# {} | Type: {} | Reason: {}",
+                        f.describe(),
+                        f.punishment.kind(),
+                        clamp_chars(f.punishment.reason().to_string(), 25)
+                    ),
+                    None => String::from("**SPAM FILTER**\n-# Disabled"),
+                }
+            }
+            Some("disable") => {
+                trace.point("deleting_filter");
+                if let Err(err) = sqlx::query("DELETE FROM spam_filters WHERE guild_id = $1")
+                    .bind(guild_id.get() as i64)
+                    .execute(&*SQL)
+                    .await
+                {
+                    consume_pgsql_error("SPAM DISABLE".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+
+                handler.spam_cache.lock().await.remove(guild_id.get());
+                String::from("**SPAM FILTER DISABLED**")
+            }
+            Some("set") => {
+                let limits = [
+                    (channel_limit, &_channel_limit_arg),
+                    (guild_limit, &_guild_limit_arg),
+                    (duplicate_channels, &_duplicate_channels_arg),
+                ];
+                for (limit, arg) in &limits {
+                    if !limit.is_some_and(|n| n >= 0) {
+                        return Err(CommandError {
+                            title: String::from("Expected a limit of 0 or more"),
+                            hint: Some(String::from("e.g. `spam set 6 10 3 5s`")),
+                            arg: (*arg).clone(),
+                        });
+                    }
+                }
+
+                let (Some(_), Some(period_token)) = (&period, _period_arg) else {
+                    return Err(CommandError::new("Missing period argument"));
+                };
+                let period = match Transformers::duration(
+                    &ctx,
+                    &msg,
+                    &mut vec![period_token].into_iter().peekable(),
+                )
+                .await
+                {
+                    Ok(Token {
+                        contents: Some(CommandArgument::Duration(d)),
+                        ..
+                    }) if d.num_seconds() > 0 && d.num_seconds() <= 3600 => d,
+                    Err(TransformerError::CommandError(err)) => return Err(err),
+                    _ => {
+                        return Err(CommandError::new(
+                            "Period must be between 1 second and 1 hour",
+                        ));
+                    }
+                };
+
+                let (channel_limit, guild_limit, duplicate_channels) = (
+                    channel_limit.unwrap_or(0),
+                    guild_limit.unwrap_or(0),
+                    duplicate_channels.unwrap_or(0),
+                );
+
+                if channel_limit == 0 && guild_limit == 0 && duplicate_channels == 0 {
+                    return Err(CommandError {
+                        title: String::from("At least one limit has to be enabled"),
+                        hint: Some(String::from("use `spam disable` to turn the filter off")),
+                        arg: None,
+                    });
+                }
+
+                let Some(selection) = super::punishment_select::select_punishment(
+                    &ctx,
+                    &msg,
+                    "SPAM FILTER",
+                    "the spam filter",
+                    trace,
+                )
+                .await?
+                else {
+                    return Ok(());
+                };
+
+                trace.point("updating_database");
+                if let Err(err) = sqlx::query(
+                    "INSERT INTO spam_filters \
+                     (guild_id, channel_limit, guild_limit, duplicate_channels, period, reason, \
+                      punishment_type, day_clear_amount, duration, silent, log_channel_id) \
+                     VALUES ($1, $2, $3, $4, $5, $6, CAST($7 AS action_type), $8, $9, $10, $11) \
+                     ON CONFLICT (guild_id) DO UPDATE SET \
+                     channel_limit = $2, guild_limit = $3, duplicate_channels = $4, period = $5, reason = $6, \
+                     punishment_type = CAST($7 AS action_type), day_clear_amount = $8, duration = $9, silent = $10, log_channel_id = $11",
+                )
+                .bind(guild_id.get() as i64)
+                .bind(channel_limit)
+                .bind(guild_limit)
+                .bind(duplicate_channels)
+                .bind(period.num_seconds() as i32)
+                .bind(selection.reason.clone())
+                .bind(selection.kind.clone())
+                .bind(selection.days as i16)
+                .bind(selection.duration)
+                .bind(selection.silent)
+                .bind(selection.channel_id)
+                .execute(&*SQL)
+                .await
+                {
+                    consume_pgsql_error("SPAM SET".into(), err);
+                    return Err(CommandError::new("Could not update the database"));
+                }
+
+                let filter = SpamFilter {
+                    channel_limit: channel_limit as u32,
+                    guild_limit: guild_limit as u32,
+                    duplicate_channels: duplicate_channels as u32,
+                    period: period.to_std().unwrap_or_default(),
+                    punishment: selection.punishment(),
+                };
+                let details = filter.describe();
+                handler.spam_cache.lock().await.set(guild_id.get(), filter);
+
+                let reply = CreateInteractionResponseMessage::new()
+                    .add_embed(
+                        CreateEmbed::new()
+                            .description(format!(
+                                "**SPAM FILTER UPDATED**\n-# {details} | Type: {} | Reason: {}",
+                                selection.kind,
+                                clamp_chars(selection.reason.clone(), 25)
+                            ))
+                            .color(BRAND_BLUE),
+                    )
+                    .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+                if let Err(err) = selection
+                    .interaction
+                    .create_response(&ctx, CreateInteractionResponse::Message(reply))
+                    .await
+                {
+                    consume_serenity_error("SPAM SET RESPONSE".into(), err);
+                }
+
+                return Ok(());
+            }
+            Some(_) => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("use set, disable or no subcommand")),
+                    arg: _subcommand_arg,
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        trace.point("sending_response");
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            consume_serenity_error("SPAM RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+            silence_typing: false,
+        }
+    }
+}
@@ -37,7 +37,7 @@ impl Command for OcrDbg {
     }
 
     fn get_full(&self) -> &'static str {
-        "Pass a message ID or reply to a message that had an image attachment. Shows the OCR text extracted from each attachment and whether it matched any automod OCR rule. Works even if the message has been deleted, as long as it was processed after the bot last started."
+        "Pass a message ID or reply to a message that had an image attachment. Shows the preprocessing steps applied to each image, the OCR text extracted from it and whether it matched any automod OCR rule. Works even if the message has been deleted, as long as it was processed after the bot last started."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -126,7 +126,21 @@ impl Command for OcrDbg {
                 format!("```\n{}\n```", entry.text)
             };
 
-            output.push_str(&format!("**OCR Text:**\n{text_display}\n"));
+            if !entry.preprocessing.is_empty() {
+                output.push_str(&format!(
+                    "**Preprocessing:** {}\n",
+                    entry.preprocessing.join(" → ")
+                ));
+            }
+
+            output.push_str(&format!(
+                "**OCR Text:**{}\n{text_display}\n",
+                if entry.cached_text { " *(cached)*" } else { "" }
+            ));
+
+            if let Some(normalized) = &entry.normalized {
+                output.push_str(&format!("**Normalized Text:**\n```\n{normalized}\n```\n"));
+            }
 
             match &entry.matched {
                 Some((rule_name, rule_id, pattern)) => {
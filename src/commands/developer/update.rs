@@ -108,7 +108,7 @@ impl Command for Update {
                         ),
                         None,
                     )
-                    .await
+                    .await;
                 }
             }));
         }
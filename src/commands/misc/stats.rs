@@ -8,7 +8,7 @@ use serenity::{
 use tracing::warn;
 
 use crate::{
-    START_TIME,
+    OCR_POOL, START_TIME,
     commands::{Command, CommandArgument, CommandCategory, CommandParameter, CommandSyntax},
     constants::BRAND_BLUE,
     event_handler::{CommandError, Handler},
@@ -35,7 +35,8 @@ impl Command for Stats {
     }
 
     fn get_full(&self) -> &'static str {
-        "Shows various statistics of the bot. Useful for nerds!"
+        "Shows various statistics of the bot, including the load of the OCR workers \
+        and the average time images spent queued and processing over the last 100 images. Useful for nerds!"
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -96,6 +97,9 @@ impl Command for Stats {
                 .unwrap_or(0.0)
         };
 
+        trace.point("fetching_ocr_stats");
+        let ocr = OCR_POOL.stats().await;
+
         let description = {
             let uptime = if uptime.0 != 0 {
                 format!("{}h {}m {}s", uptime.0, uptime.1, uptime.2)
@@ -105,7 +109,22 @@ impl Command for Stats {
                 format!("{}s", uptime.2)
             };
 
-            format!("**STATS**\nServers: {guild_count}\nUptime: {uptime}\nMemory: {memory:.2}MB")
+            format!(
+                "**STATS**\nServers: {guild_count}\nUptime: {uptime}\nMemory: {memory:.2}MB\n\
+                OCR Workers: {}/{} busy\nOCR Queue: {}/{} | {} done, {} rejected\n\
+                OCR Text Cache: {} texts, {} hits\n\
+                OCR Latency: {}ms queued, {}ms processing",
+                ocr.active,
+                ocr.workers,
+                ocr.queued,
+                ocr.queue_size,
+                ocr.completed,
+                ocr.rejected,
+                ocr.cached_texts,
+                ocr.cache_hits,
+                ocr.avg_wait.as_millis(),
+                ocr.avg_processing.as_millis()
+            )
         };
 
         trace.point("sending_response");
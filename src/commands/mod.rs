@@ -29,13 +29,16 @@ pub type TransformerFn = dyn for<'a> Fn(&'a Context, &'a Message, &'a mut Peekab
 /// Transformer function wrapped in an Arc
 pub type TransformerFnArc = Arc<TransformerFn>;
 
+/// Several users, named so the command macro can bind it like the other arguments
+pub type ManyUsers = Vec<User>;
+
 /// Defines the values which can be passed to a command
 #[derive(Debug, Clone)]
 #[allow(non_camel_case_types)]
 pub enum CommandArgument {
     String(String),
     User(User),
-    // ManyUsers(Vec<User>), // may be used in the future
+    ManyUsers(ManyUsers),
     Member(Member),
     Duration(chrono::Duration),
     None,
@@ -230,13 +233,30 @@ pub trait Command: Send + Sync {
 }
 
 mod admin;
+pub use admin::AccountAge;
 // pub use admin::Config;
+pub use admin::CreateAttachmentRule;
+pub use admin::CreateNameRule;
 pub use admin::CreateOcrRule;
+pub use admin::CreateTextRule;
 pub use admin::DefineLog;
 pub use admin::DeleteRule;
 pub use admin::Encrypt;
+pub use admin::Escalation;
+pub use admin::ExportRules;
+pub use admin::ImportRules;
+pub use admin::Mentions;
 pub use admin::OcrCheck;
+pub use admin::Raid;
+pub use admin::RaidLockdown;
+pub use admin::RuleCondition;
+pub use admin::RuleCooldown;
+pub use admin::RuleDryRun;
+pub use admin::RuleExempt;
+pub use admin::RuleNormalize;
+pub use admin::RuleStats;
 pub use admin::Rules;
+pub use admin::Spam;
 pub use admin::Sticky;
 
 mod developer;
@@ -268,6 +288,7 @@ pub use moderation::Log;
 pub use moderation::Mute;
 pub use moderation::Note;
 pub use moderation::Purge;
+pub use moderation::RaidCleanup;
 pub use moderation::Reason;
 pub use moderation::Ref;
 pub use moderation::Softban;
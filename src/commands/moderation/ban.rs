@@ -23,6 +23,8 @@ use crate::{
 };
 use aegis_macros::command;
 
+use super::bulk::{BulkAction, run_bulk};
+
 pub struct Ban;
 
 impl Ban {
@@ -47,7 +49,9 @@ impl Command for Ban {
         Use 0 for the duration to make the ban permanent. \
         If the duration cannot be resolved it will default to permanent. \
         Ban expiry is checked every 5 minutes. \
-        Clears one day of messages by default."
+        Clears one day of messages by default. \
+        Several users can be given at once as IDs or mentions, or by replying to a raid alert or your own message listing them. \
+        They are listed for confirmation first, share one reason and are linked by a batch ID in their logs."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -97,7 +101,7 @@ impl Command for Ban {
         &self,
         ctx: Context,
         msg: Message,
-        #[transformers::reply_user] user: User,
+        #[transformers::many_users] users: ManyUsers,
         #[transformers::maybe_duration] duration: Option<Duration>,
         #[transformers::reply_consume] reason: Option<String>,
         params: HashMap<&str, (bool, CommandArgument)>,
@@ -112,6 +116,31 @@ impl Command for Ban {
             });
         };
 
+        let days = {
+            if let Some(arg) = params.get("clear") {
+                if !arg.0 {
+                    0
+                } else if let CommandArgument::i32(days) = arg.1 {
+                    days.clamp(0, 7) as u8
+                } else {
+                    1
+                }
+            } else {
+                1
+            }
+        };
+
+        if users.len() > 1 {
+            let action = BulkAction::Ban {
+                duration: duration.unwrap_or(Duration::zero()),
+                days,
+            };
+            return run_bulk(&ctx, &msg, action, users, reason, &params, trace).await;
+        }
+        let Some(user) = users.into_iter().next() else {
+            return Err(CommandError::new("Unexpected error has occured."));
+        };
+
         trace.point("checking_discord_ban_cache");
 
         if ctx
@@ -169,7 +198,7 @@ impl Command for Ban {
             }
         }
 
-        let inferred = matches!(_user_arg.inferred, Some(InferType::Message));
+        let inferred = matches!(_users_arg.inferred, Some(InferType::Message));
         let duration = duration.unwrap_or(Duration::zero());
         let mut reason = reason
             .map(|s| {
@@ -185,20 +214,6 @@ impl Command for Ban {
             reason = String::from("No reason provided")
         }
 
-        let days = {
-            if let Some(arg) = params.get("clear") {
-                if !arg.0 {
-                    0
-                } else if let CommandArgument::i32(days) = arg.1 {
-                    days.clamp(0, 7) as u8
-                } else {
-                    1
-                }
-            } else {
-                1
-            }
-        };
-
         if reason.len() > 500 {
             reason.truncate(500);
             reason.push_str("...");
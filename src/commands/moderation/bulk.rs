@@ -0,0 +1,570 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::TimeDelta;
+use serenity::all::{
+    ButtonStyle, Context, CreateActionRow, CreateAllowedMentions, CreateAttachment, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+    EditAttachments, EditMessage, GuildId, Member, Message, Permissions, User,
+};
+
+use crate::{
+    SQL,
+    commands::CommandArgument,
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    moderation,
+    utils::{
+        can_target, consume_pgsql_error, consume_serenity_error, get_guild_info,
+        reference::{RefData, resolve_ref, save_ref},
+        time_string, tinyid,
+        trace::TraceContext,
+    },
+};
+
+/// Pause between two targets, so DMs and bans of large batches don't run into Discords rate limits
+const BULK_ACTION_INTERVAL: Duration = Duration::from_millis(750);
+/// Targets between two progress updates of the confirmation message
+const BULK_PROGRESS_STEP: usize = 5;
+
+/// A moderation command applied to several users at once
+pub(super) enum BulkAction {
+    Warn,
+    Kick,
+    Mute { duration: TimeDelta },
+    Ban { duration: TimeDelta, days: u8 },
+    Softban { days: u8 },
+}
+
+impl BulkAction {
+    fn name(&self) -> &'static str {
+        match self {
+            BulkAction::Warn => "warn",
+            BulkAction::Kick => "kick",
+            BulkAction::Mute { .. } => "mute",
+            BulkAction::Ban { .. } => "ban",
+            BulkAction::Softban { .. } => "softban",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            BulkAction::Warn => "WARNED",
+            BulkAction::Kick => "KICKED",
+            BulkAction::Mute { .. } => "TIMED OUT",
+            BulkAction::Ban { .. } => "BANNED",
+            BulkAction::Softban { .. } => "SOFTBANNED",
+        }
+    }
+
+    /// Only bans can target users who are not in the server
+    fn needs_member(&self) -> bool {
+        !matches!(self, BulkAction::Ban { .. })
+    }
+
+    /// e.g. ` | Duration: for 1 day | Cleared 1 days of messages`
+    fn details(&self) -> String {
+        let clear = |days: u8| {
+            if days == 0 {
+                String::new()
+            } else {
+                format!(" | Cleared {days} days of messages")
+            }
+        };
+
+        match self {
+            BulkAction::Warn | BulkAction::Kick => String::new(),
+            BulkAction::Mute { duration } => format!(" | Duration: {}", time_string(*duration)),
+            BulkAction::Ban { duration, days } => {
+                format!(" | Duration: {}{}", time_string(*duration), clear(*days))
+            }
+            BulkAction::Softban { days } => clear(*days),
+        }
+    }
+
+    /// The DM sent to every target, matching the one of the single target commands
+    fn dm_content(&self, guild_name: &str, reason: &str) -> String {
+        let title = match self {
+            BulkAction::Warn => "WARNED",
+            BulkAction::Kick | BulkAction::Softban { .. } => "KICKED",
+            BulkAction::Mute { .. } => "TIMEOUT",
+            BulkAction::Ban { .. } => "BANNED",
+        };
+        let duration = match self {
+            BulkAction::Mute { duration } | BulkAction::Ban { duration, .. } => {
+                format!(" | Duration: {}", time_string(*duration))
+            }
+            _ => String::new(),
+        };
+
+        format!("**{title}**\n-# Server: {guild_name}{duration}\n```\n{reason}\n```")
+    }
+}
+
+/// A target that can not be acted on, shown in the confirmation and the summary
+struct Skipped {
+    user: User,
+    reason: &'static str,
+}
+
+/// The member of the only target of a moderation command, for the commands that need members
+pub(super) async fn target_member(
+    ctx: &Context,
+    guild_id: GuildId,
+    user: &User,
+    arg: &Token,
+) -> Result<Member, CommandError> {
+    if let Ok(member) = guild_id.member(ctx, user.id).await {
+        return Ok(member);
+    }
+
+    if arg.inferred.is_some() {
+        Err(CommandError {
+            title: String::from("Replied member not in server"),
+            hint: Some(String::from(
+                "the member you replied to isn't in the server anymore. Urge them to join back!",
+            )),
+            arg: None,
+        })
+    } else {
+        Err(CommandError {
+            arg: Some(arg.clone()),
+            title: String::from("Could not find the <Discord Member>"),
+            hint: Some(String::from(
+                "make sure the ID or mention you provided is valid and that the member is in this server!",
+            )),
+        })
+    }
+}
+
+/// The `note` parameter shared by the moderation commands, at most 128 characters
+fn note_param(params: &HashMap<&str, (bool, CommandArgument)>) -> Option<String> {
+    let Some((true, CommandArgument::String(s))) = params.get("note") else {
+        return None;
+    };
+
+    let mut trimmed = s.trim().to_string();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed.len() > 128 {
+        trimmed.truncate(125);
+        trimmed.push_str("...");
+    }
+    Some(trimmed)
+}
+
+/// Applies a moderation command to several users with one shared reason.
+/// The targets are listed for confirmation first, then acted on one after another and summarized,
+/// every `actions` row of the run shares the same `batch_id`.
+pub(super) async fn run_bulk(
+    ctx: &Context,
+    msg: &Message,
+    action: BulkAction,
+    users: Vec<User>,
+    reason: Option<String>,
+    params: &HashMap<&str, (bool, CommandArgument)>,
+    trace: &mut TraceContext,
+) -> Result<(), CommandError> {
+    let Some(guild_id) = msg.guild_id else {
+        return Err(CommandError::new("Unexpected error has occured."));
+    };
+    let Ok(author_member) = msg.member(ctx).await else {
+        return Err(CommandError {
+            title: String::from("Unexpected error has occured."),
+            hint: Some(String::from("could not get author member")),
+            arg: None,
+        });
+    };
+
+    let mut reason = reason
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or(String::from("No reason provided"));
+    if reason.len() > 500 {
+        reason.truncate(500);
+        reason.push_str("...");
+    }
+    let note = note_param(params);
+    let silent = params.contains_key("silent");
+
+    trace.point("verifying_permissions");
+    let permission = match action {
+        BulkAction::Ban { .. } => Permissions::BAN_MEMBERS,
+        _ => Permissions::MODERATE_MEMBERS,
+    };
+    let mut targets: Vec<(User, Option<Member>)> = vec![];
+    let mut skipped: Vec<Skipped> = vec![];
+    for user in users {
+        if user.id == msg.author.id || user.id == ctx.cache.current_user().id {
+            skipped.push(Skipped {
+                user,
+                reason: "can not target yourself or the bot",
+            });
+            continue;
+        }
+
+        let member = guild_id.member(ctx, user.id).await.ok();
+        let skip = match &member {
+            None => action.needs_member().then_some("not in the server"),
+            Some(member) => (!can_target(ctx, &author_member, member, permission).await)
+                .then_some("you may not target this member"),
+        };
+
+        match skip {
+            Some(reason) => skipped.push(Skipped { user, reason }),
+            None => targets.push((user, member)),
+        }
+    }
+
+    if targets.is_empty() {
+        return Err(CommandError {
+            title: String::from("None of the users can be targeted"),
+            hint: skipped
+                .first()
+                .map(|s| format!("{}: {}", s.user.name, s.reason)),
+            arg: None,
+        });
+    }
+
+    let title = action.name().to_uppercase();
+    let target_list = targets
+        .iter()
+        .map(|(user, _)| format!("<@{}> `{}`", user.id, user.name.replace('`', "")))
+        .chain(
+            skipped
+                .iter()
+                .map(|s| format!("~~<@{}>~~ {}", s.user.id, s.reason)),
+        )
+        .collect::<Vec<_>>();
+    let header = format!(
+        "**BULK {title}**\n-# Targets: {} | Skipped: {}{}\n```\n{reason}\n```",
+        targets.len(),
+        skipped.len(),
+        action.details()
+    );
+
+    let buttons = |disabled: bool| {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new("confirm")
+                .label(format!("{} {} users", action.name(), targets.len()))
+                .style(ButtonStyle::Danger)
+                .disabled(disabled),
+            CreateButton::new("cancel")
+                .label("Cancel")
+                .style(ButtonStyle::Secondary)
+                .disabled(disabled),
+        ])]
+    };
+
+    let mut list = String::new();
+    for (i, line) in target_list.iter().enumerate() {
+        if header.len() + list.len() + line.len() > 3900 {
+            list.push_str(&format!("-# and {} more", target_list.len() - i));
+            break;
+        }
+        list.push_str(line);
+        list.push('\n');
+    }
+
+    let reply = CreateMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(format!("{header}\n{list}"))
+                .color(BRAND_BLUE),
+        )
+        .components(buttons(false))
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+    let mut prompt = match msg.channel_id.send_message(ctx, reply).await {
+        Ok(m) => m,
+        Err(err) => {
+            consume_serenity_error("BULK MODERATION RESPONSE".into(), err);
+            return Ok(());
+        }
+    };
+
+    trace.point("awaiting_user_interaction");
+    let interaction = loop {
+        let Some(interaction) = prompt
+            .await_component_interaction(&ctx.shard)
+            .timeout(Duration::from_secs(60))
+            .await
+        else {
+            let _ = prompt
+                .edit(ctx, EditMessage::new().components(buttons(true)))
+                .await;
+            return Ok(());
+        };
+
+        if interaction.user.id != msg.author.id {
+            if let Err(err) = interaction
+                .create_response(
+                    ctx,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("You are not the author of the original message!")
+                            .ephemeral(true),
+                    ),
+                )
+                .await
+            {
+                consume_serenity_error("BULK MODERATION INTERACTION RESPONSE".into(), err);
+            }
+
+            continue;
+        }
+
+        break interaction;
+    };
+
+    let total = targets.len();
+    let progress = |done: usize| {
+        CreateEmbed::new()
+            .description(format!(
+                "**BULK {title} IN PROGRESS**\n-# {done}/{total} targets done{}\n```\n{reason}\n```",
+                action.details()
+            ))
+            .color(BRAND_BLUE)
+    };
+
+    if interaction.data.custom_id.as_str() != "confirm" {
+        let _ = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(
+                            CreateEmbed::new()
+                                .description(format!("**BULK {title} CANCELLED**"))
+                                .color(BRAND_BLUE),
+                        )
+                        .components(vec![]),
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+
+    if let Err(err) = interaction
+        .create_response(
+            ctx,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(progress(0))
+                    .components(vec![]),
+            ),
+        )
+        .await
+    {
+        consume_serenity_error("BULK MODERATION INTERACTION RESPONSE".into(), err);
+    }
+
+    let guild_name = get_guild_info(ctx, Some(guild_id))
+        .await
+        .map(|g| g.name())
+        .unwrap_or_else(|| String::from("UNKNOWN_GUILD"));
+    let ref_data = match params.get("ref") {
+        Some((true, CommandArgument::String(url))) => {
+            resolve_ref(ctx, msg, "", Some(url.as_str())).await
+        }
+        _ => RefData::default(),
+    };
+    let reason_is_default = reason == "No reason provided";
+
+    trace.point("executing_sanctions");
+    let batch_id = tinyid().await;
+    // (user, Ok(action id) or why it failed)
+    let mut results: Vec<(User, Result<String, String>)> = vec![];
+
+    for (i, (user, member)) in targets.into_iter().enumerate() {
+        if i != 0 {
+            tokio::time::sleep(BULK_ACTION_INTERVAL).await;
+        }
+        if i != 0 && i % BULK_PROGRESS_STEP == 0 {
+            let _ = prompt
+                .edit(ctx, EditMessage::new().embed(progress(i)))
+                .await;
+        }
+
+        if !silent {
+            let dm = CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(action.dm_content(&guild_name, &reason))
+                    .color(BRAND_BLUE),
+            );
+            let _ = user.id.direct_message(ctx, dm).await;
+        }
+
+        let db_id = tinyid().await;
+        let author = author_member.clone();
+        let result = match (&action, member) {
+            (BulkAction::Ban { duration, days }, Some(member)) => {
+                moderation::ban_member(
+                    ctx,
+                    author,
+                    member,
+                    guild_id,
+                    db_id.clone(),
+                    reason.clone(),
+                    note.clone(),
+                    *days,
+                    *duration,
+                    ref_data.clone(),
+                )
+                .await
+            }
+            (BulkAction::Ban { duration, days }, None) => {
+                moderation::ban_user(
+                    ctx,
+                    author,
+                    user.clone(),
+                    guild_id,
+                    db_id.clone(),
+                    reason.clone(),
+                    note.clone(),
+                    *days,
+                    *duration,
+                    ref_data.clone(),
+                )
+                .await
+            }
+            (_, None) => Err(CommandError::new("not in the server")),
+            (BulkAction::Warn, Some(member)) => {
+                moderation::warn_member(
+                    ctx,
+                    author,
+                    member,
+                    guild_id,
+                    db_id.clone(),
+                    reason.clone(),
+                    note.clone(),
+                    ref_data.clone(),
+                )
+                .await
+            }
+            (BulkAction::Kick, Some(member)) => {
+                moderation::kick_member(
+                    ctx,
+                    author,
+                    member,
+                    guild_id,
+                    db_id.clone(),
+                    reason.clone(),
+                    note.clone(),
+                    ref_data.clone(),
+                )
+                .await
+            }
+            (BulkAction::Mute { duration }, Some(member)) => {
+                moderation::mute_member(
+                    ctx,
+                    author,
+                    member,
+                    guild_id,
+                    db_id.clone(),
+                    reason.clone(),
+                    note.clone(),
+                    *duration,
+                    ref_data.clone(),
+                )
+                .await
+            }
+            (BulkAction::Softban { days }, Some(member)) => {
+                moderation::softban(
+                    ctx,
+                    author,
+                    member,
+                    guild_id,
+                    db_id.clone(),
+                    reason.clone(),
+                    note.clone(),
+                    *days,
+                    ref_data.clone(),
+                )
+                .await
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                save_ref(&db_id, &ref_data, guild_id.get(), reason_is_default).await;
+                results.push((user, Ok(db_id)));
+            }
+            Err(err) => results.push((user, Err(err.title))),
+        }
+    }
+
+    trace.point("linking_batch");
+    let action_ids = results
+        .iter()
+        .filter_map(|(_, r)| r.as_ref().ok().cloned())
+        .collect::<Vec<_>>();
+    if !action_ids.is_empty()
+        && let Err(err) = sqlx::query("UPDATE actions SET batch_id = $1 WHERE id = ANY($2)")
+            .bind(&batch_id)
+            .bind(&action_ids)
+            .execute(&*SQL)
+            .await
+    {
+        consume_pgsql_error("BULK MODERATION BATCH".into(), err);
+    }
+
+    let lines = results
+        .iter()
+        .map(|(user, result)| match result {
+            Ok(db_id) => format!("<@{}> Log ID: `{db_id}`", user.id),
+            Err(err) => format!("<@{}> failed: {err}", user.id),
+        })
+        .chain(
+            skipped
+                .iter()
+                .map(|s| format!("<@{}> skipped: {}", s.user.id, s.reason)),
+        )
+        .collect::<Vec<_>>();
+    let summary = format!(
+        "**BULK {title} DONE**\n-# Batch ID: `{batch_id}` | {}: {} | Failed: {} | Skipped: {}{}\n```\n{reason}\n```",
+        action.past_tense().to_lowercase(),
+        action_ids.len(),
+        total - action_ids.len(),
+        skipped.len(),
+        action.details()
+    );
+
+    let mut edit = EditMessage::new();
+    if summary.len() + lines.iter().map(|l| l.len() + 1).sum::<usize>() > 4000 {
+        let file = results
+            .iter()
+            .map(|(user, result)| match result {
+                Ok(db_id) => format!("{} ({}) {db_id}", user.name, user.id),
+                Err(err) => format!("{} ({}) failed: {err}", user.name, user.id),
+            })
+            .chain(
+                skipped
+                    .iter()
+                    .map(|s| format!("{} ({}) skipped: {}", s.user.name, s.user.id, s.reason)),
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        edit = edit
+            .embed(CreateEmbed::new().description(summary).color(BRAND_BLUE))
+            .attachments(EditAttachments::new().add(CreateAttachment::bytes(
+                file.into_bytes(),
+                format!("batch-{batch_id}.txt"),
+            )));
+    } else {
+        edit = edit.embed(
+            CreateEmbed::new()
+                .description(format!("{summary}\n{}", lines.join("\n")))
+                .color(BRAND_BLUE),
+        );
+    }
+
+    trace.point("sending_response");
+    if let Err(err) = prompt.edit(ctx, edit).await {
+        consume_serenity_error("BULK MODERATION RESPONSE".into(), err);
+    }
+
+    Ok(())
+}
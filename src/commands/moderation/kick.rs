@@ -15,6 +15,8 @@ use crate::{
     },
 };
 use aegis_macros::command;
+
+use super::bulk::{BulkAction, run_bulk, target_member};
 use serenity::{
     all::{Context, Mentionable, Message, Permissions},
     async_trait,
@@ -39,7 +41,9 @@ impl Command for Kick {
     }
 
     fn get_full(&self) -> &'static str {
-        "Kicks a member from the server and leaves a note in the users log."
+        "Kicks a member from the server and leaves a note in the users log. \
+        Several members can be given at once as IDs or mentions, or by replying to a raid alert or your own message listing them. \
+        They are listed for confirmation first, share one reason and are linked by a batch ID in their logs."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -81,11 +85,21 @@ impl Command for Kick {
         &self,
         ctx: Context,
         msg: Message,
-        #[transformers::reply_member] member: Member,
+        #[transformers::many_users] users: ManyUsers,
         #[transformers::reply_consume] reason: Option<String>,
         params: std::collections::HashMap<&str, (bool, CommandArgument)>,
         trace: &mut TraceContext,
     ) -> Result<(), CommandError> {
+        if users.len() > 1 {
+            let action = BulkAction::Kick;
+            return run_bulk(&ctx, &msg, action, users, reason, &params, trace).await;
+        }
+        let Some(user) = users.first() else {
+            return Err(CommandError::new("Unexpected error has occured."));
+        };
+        let member =
+            target_member(&ctx, msg.guild_id.unwrap_or_default(), user, &_users_arg).await?;
+
         let guild = crate::utils::get_guild_info(&ctx, msg.guild_id).await;
         let Ok(author_member) = msg.member(&ctx).await else {
             return Err(CommandError {
@@ -107,7 +121,7 @@ impl Command for Kick {
             });
         }
 
-        let inferred = matches!(_member_arg.inferred, Some(InferType::Message));
+        let inferred = matches!(_users_arg.inferred, Some(InferType::Message));
         let mut reason = reason
             .map(|s| {
                 if s.is_empty() || s.chars().all(char::is_whitespace) {
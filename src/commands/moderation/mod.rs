@@ -1,6 +1,8 @@
 mod ban;
 pub use ban::Ban;
 
+mod bulk;
+
 mod duration;
 pub use duration::Duration;
 
@@ -19,6 +21,9 @@ pub use mute::Mute;
 mod purge;
 pub use purge::Purge;
 
+mod raid_cleanup;
+pub use raid_cleanup::RaidCleanup;
+
 mod reason;
 pub use reason::Reason;
 
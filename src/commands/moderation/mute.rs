@@ -22,6 +22,8 @@ use crate::{
 };
 use aegis_macros::command;
 
+use super::bulk::{BulkAction, run_bulk, target_member};
+
 pub struct Mute;
 
 impl Mute {
@@ -42,7 +44,9 @@ impl Command for Mute {
 
     fn get_full(&self) -> &'static str {
         "Uses the Discord timeout feature on a member and leaves a note in the users log. \
-        Has a max duration of 28 days. Duration (including the removal of the timeout) is managed by Discord"
+        Has a max duration of 28 days. Duration (including the removal of the timeout) is managed by Discord. \
+        Several members can be given at once as IDs or mentions, or by replying to a raid alert or your own message listing them. \
+        They are listed for confirmation first, share one reason and are linked by a batch ID in their logs."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -85,12 +89,24 @@ impl Command for Mute {
         &self,
         ctx: Context,
         msg: Message,
-        #[transformers::reply_member] member: Member,
+        #[transformers::many_users] users: ManyUsers,
         #[transformers::maybe_duration] duration: Option<Duration>,
         #[transformers::reply_consume] reason: Option<String>,
         params: HashMap<&str, (bool, CommandArgument)>,
         trace: &mut TraceContext,
     ) -> Result<(), CommandError> {
+        if users.len() > 1 {
+            let action = BulkAction::Mute {
+                duration: duration.unwrap_or(Duration::zero()),
+            };
+            return run_bulk(&ctx, &msg, action, users, reason, &params, trace).await;
+        }
+        let Some(user) = users.first() else {
+            return Err(CommandError::new("Unexpected error has occured."));
+        };
+        let member =
+            target_member(&ctx, msg.guild_id.unwrap_or_default(), user, &_users_arg).await?;
+
         let guild = crate::utils::get_guild_info(&ctx, msg.guild_id).await;
         let Ok(author_member) = msg.member(&ctx).await else {
             return Err(CommandError {
@@ -112,7 +128,7 @@ impl Command for Mute {
             });
         }
 
-        let inferred = matches!(_member_arg.inferred, Some(InferType::Message));
+        let inferred = matches!(_users_arg.inferred, Some(InferType::Message));
         let duration = duration.unwrap_or(Duration::zero());
         let mut reason = reason
             .map(|s| {
@@ -0,0 +1,657 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use regex::Regex;
+use serenity::{
+    all::{
+        ButtonStyle, Context, CreateActionRow, CreateAllowedMentions, CreateAttachment,
+        CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+        CreateMessage, EditMessage, GuildId, Member, Message, Permissions, UserId,
+    },
+    async_trait,
+};
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::{BRAND_BLUE, MAX_RAID_CLEANUP_TARGETS},
+    event_handler::{CommandError, Handler},
+    lexer::Token,
+    moderation::raid::{CleanupAction, cleanup_raid},
+    transformers::Transformers,
+    utils::{
+        can_target, consume_serenity_error, has_guild_permission, rule_regex::build_rule_regex,
+        time_string,
+    },
+};
+use aegis_macros::command;
+
+/// Members requested per page when listing the guild
+const MEMBER_PAGE_SIZE: u64 = 1000;
+
+/// Which of the members who joined in the window are selected
+struct CleanupFilter {
+    since: i64,
+    until: i64,
+    /// Accounts created less than this many seconds before joining
+    max_account_age: Option<i64>,
+    default_avatar: bool,
+    name: Option<Regex>,
+}
+
+impl CleanupFilter {
+    fn matches(&self, member: &Member) -> bool {
+        let Some(joined) = member.joined_at.map(|t| t.unix_timestamp()) else {
+            return false;
+        };
+        if joined < self.since || joined > self.until {
+            return false;
+        }
+
+        if let Some(age) = self.max_account_age
+            && joined - member.user.created_at().unix_timestamp() >= age
+        {
+            return false;
+        }
+
+        if self.default_avatar && member.user.avatar.is_some() {
+            return false;
+        }
+
+        if let Some(name) = &self.name {
+            let names = [
+                Some(member.user.name.as_str()),
+                member.user.global_name.as_deref(),
+                member.nick.as_deref(),
+            ];
+            if !names.into_iter().flatten().any(|n| name.is_match(n)) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = vec![format!(
+            "Joined <t:{}:f> to <t:{}:f>",
+            self.since, self.until
+        )];
+        if let Some(age) = self.max_account_age {
+            parts.push(format!(
+                "Account age below {}",
+                time_string(TimeDelta::seconds(age)).trim_start_matches("for ")
+            ));
+        }
+        if self.default_avatar {
+            parts.push(String::from("Default avatar"));
+        }
+        if let Some(name) = &self.name {
+            parts.push(format!(
+                "Name `/{}/`",
+                name.as_str().trim_start_matches("(?i)")
+            ));
+        }
+        parts.join(" | ")
+    }
+}
+
+pub struct RaidCleanup;
+
+impl RaidCleanup {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// A point in time given as a unix timestamp, a Discord timestamp (`<t:1700000000:f>`),
+    /// an ISO 8601 date in UTC or a duration meaning that long ago
+    async fn parse_time(ctx: &Context, msg: &Message, token: Token) -> Option<i64> {
+        let raw = token.raw.trim();
+        let raw = raw
+            .strip_prefix("<t:")
+            .and_then(|s| s.strip_suffix('>'))
+            .map(|s| s.split(':').next().unwrap_or_default())
+            .unwrap_or(raw);
+
+        if !raw.is_empty() && raw.chars().all(|c| c.is_ascii_digit()) {
+            return raw.parse::<i64>().ok();
+        }
+
+        if let Ok(date) = DateTime::parse_from_rfc3339(raw) {
+            return Some(date.timestamp());
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+            if let Ok(date) = NaiveDateTime::parse_from_str(raw, format) {
+                return Some(date.and_utc().timestamp());
+            }
+        }
+
+        match Transformers::duration(ctx, msg, &mut vec![token].into_iter().peekable()).await {
+            Ok(Token {
+                contents: Some(CommandArgument::Duration(d)),
+                ..
+            }) => Some((Utc::now() - d).timestamp()),
+            _ => None,
+        }
+    }
+
+    /// The start of the window, either a point in time or the join of a member given as ID or mention
+    async fn parse_since(
+        ctx: &Context,
+        msg: &Message,
+        guild_id: GuildId,
+        raw: &str,
+        token: Token,
+    ) -> Result<i64, CommandError> {
+        let id = match raw.strip_prefix("<@").and_then(|s| s.strip_suffix('>')) {
+            Some(id) => id.trim_start_matches('!').parse::<u64>().ok(),
+            None if raw.len() >= 15 => raw.parse::<u64>().ok(),
+            None => None,
+        };
+
+        if let Some(id) = id {
+            return match guild_id.member(ctx, UserId::new(id)).await {
+                Ok(Member {
+                    joined_at: Some(joined),
+                    ..
+                }) => Ok(joined.unix_timestamp()),
+                _ => Err(CommandError {
+                    title: String::from("Could not find the <Discord Member>"),
+                    hint: Some(String::from(
+                        "the window starts at the join of the given member, they have to still be in the server",
+                    )),
+                    arg: Some(token),
+                }),
+            };
+        }
+
+        let error = CommandError {
+            title: String::from("Could not turn input to a point in time"),
+            hint: Some(String::from(
+                "provide a member, a unix or Discord timestamp, a date like 2026-10-18T14:30 (UTC) or a duration like 30m",
+            )),
+            arg: Some(token.clone()),
+        };
+        Self::parse_time(ctx, msg, token).await.ok_or(error)
+    }
+
+    /// Every member of the guild, paginated as Discord returns at most 1000 at once
+    async fn fetch_members(ctx: &Context, guild_id: GuildId) -> Result<Vec<Member>, CommandError> {
+        let mut members = vec![];
+        let mut after = None;
+
+        loop {
+            let page = match guild_id.members(ctx, Some(MEMBER_PAGE_SIZE), after).await {
+                Ok(page) => page,
+                Err(err) => {
+                    consume_serenity_error("RAID CLEANUP MEMBERS".into(), err);
+                    return Err(CommandError {
+                        title: String::from("Could not list the members of this server"),
+                        hint: Some(String::from("please try again later")),
+                        arg: None,
+                    });
+                }
+            };
+
+            let full = page.len() as u64 == MEMBER_PAGE_SIZE;
+            after = page.last().map(|m| m.user.id);
+            members.extend(page);
+
+            if !full {
+                return Ok(members);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Command for RaidCleanup {
+    fn get_name(&self) -> &'static str {
+        "raid_cleanup"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Bans or kicks everyone who joined in a time window"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Bans or kicks every member who joined in a time window, e.g. to clean up after a raid. \
+        The window starts at `since`, which is either a member (everyone who joined after them, including them), \
+        a unix or Discord timestamp, a date like `2026-10-18T14:30` in UTC or a duration like `30m` for 30 minutes ago. \
+        It ends now unless `+until` is given in the same format. \
+        The selection can be narrowed with `+age 1d` (accounts younger than a day when joining), \
+        `+avatar` (default avatars only) and `+name <regex>` (matched case insensitive against user, display and nick names). \
+        e.g. `raid_cleanup ban 30m +age 7d +avatar Spam raid`. \
+        The selected members are listed for confirmation first and are not DMed. \
+        Every member gets their own log entry in the database linked by a batch ID, \
+        the cleanup itself is summarized in a single message in the moderation log. \
+        Bans clear one day of messages by default."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("ban/kick", true),
+            CommandSyntax::String("since", true),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![
+            &CommandParameter {
+                name: "until",
+                short: "u",
+                transformer: &Transformers::some_string,
+                desc: "End of the join window, defaults to now",
+            },
+            &CommandParameter {
+                name: "age",
+                short: "a",
+                transformer: &Transformers::duration,
+                desc: "Only accounts younger than this when joining",
+            },
+            &CommandParameter {
+                name: "avatar",
+                short: "av",
+                transformer: &Transformers::none,
+                desc: "Only accounts with the default avatar",
+            },
+            &CommandParameter {
+                name: "name",
+                short: "n",
+                transformer: &Transformers::some_string,
+                desc: "Only members whose name matches this regex",
+            },
+            &CommandParameter {
+                name: "clear",
+                short: "c",
+                transformer: &Transformers::i32,
+                desc: "Amount of messages to clear when banning (in days 0-7)",
+            },
+        ]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] action: String,
+        #[transformers::some_string] since: String,
+        #[transformers::string_consume] reason: Option<String>,
+        params: HashMap<&str, (bool, CommandArgument)>,
+        trace: &mut TraceContext,
+    ) -> Result<(), CommandError> {
+        let Some(guild_id) = msg.guild_id else {
+            return Err(CommandError::new("Unexpected error has occured."));
+        };
+        let Ok(author) = msg.member(&ctx).await else {
+            return Err(CommandError {
+                title: String::from("Unexpected error has occured."),
+                hint: Some(String::from("could not get author member")),
+                arg: None,
+            });
+        };
+
+        let action = match action.to_lowercase().as_str() {
+            "ban" => CleanupAction::Ban {
+                clear_days: match params.get("clear") {
+                    Some((false, _)) => 0,
+                    Some((true, CommandArgument::i32(days))) => (*days).clamp(0, 7) as u8,
+                    _ => 1,
+                },
+            },
+            "kick" => CleanupAction::Kick,
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown action"),
+                    hint: Some(String::from("use ban or kick")),
+                    arg: Some(_action_arg),
+                });
+            }
+        };
+
+        let permission = match action {
+            CleanupAction::Ban { .. } => Permissions::BAN_MEMBERS,
+            CleanupAction::Kick => Permissions::KICK_MEMBERS,
+        };
+        // the command can be run with either permission, the chosen action needs its own
+        if !has_guild_permission(&ctx, &author, permission).await {
+            return Err(CommandError {
+                title: format!(
+                    "You need the {} members permission to {} with a raid cleanup",
+                    action.name(),
+                    action.name()
+                ),
+                hint: None,
+                arg: Some(_action_arg),
+            });
+        }
+
+        let since = Self::parse_since(&ctx, &msg, guild_id, &since, _since_arg).await?;
+        let until = match params.get("until") {
+            Some((true, CommandArgument::String(raw))) => {
+                let token = Token {
+                    raw: raw.clone(),
+                    ..Default::default()
+                };
+                Self::parse_time(&ctx, &msg, token)
+                    .await
+                    .ok_or_else(|| CommandError {
+                        title: String::from("Could not turn the until parameter to a point in time"),
+                        hint: Some(String::from(
+                            "provide a unix or Discord timestamp, a date like 2026-10-18T14:30 (UTC) or a duration like 5m",
+                        )),
+                        arg: None,
+                    })?
+            }
+            Some(_) => return Err(CommandError::new("Missing value for the until parameter")),
+            None => Utc::now().timestamp(),
+        };
+
+        if since >= until {
+            return Err(CommandError {
+                title: String::from("The window has to start before it ends"),
+                hint: Some(format!(
+                    "it currently starts <t:{since}:R> and ends <t:{until}:R>"
+                )),
+                arg: None,
+            });
+        }
+
+        let max_account_age = match params.get("age") {
+            Some((true, CommandArgument::Duration(d))) if d.num_seconds() > 0 => {
+                Some(d.num_seconds())
+            }
+            Some(_) => return Err(CommandError::new("Invalid account age")),
+            None => None,
+        };
+
+        let name = match params.get("name") {
+            Some((true, CommandArgument::String(raw))) => {
+                let pattern = raw
+                    .strip_prefix('/')
+                    .and_then(|s| s.strip_suffix('/'))
+                    .unwrap_or(raw);
+                match build_rule_regex(&format!("(?i){pattern}")) {
+                    Ok(regex) => Some(regex),
+                    Err(err) => {
+                        return Err(CommandError {
+                            title: format!("Invalid name regex: {}", err.reason),
+                            hint: None,
+                            arg: None,
+                        });
+                    }
+                }
+            }
+            Some(_) => return Err(CommandError::new("Missing value for the name parameter")),
+            None => None,
+        };
+
+        let filter = CleanupFilter {
+            since,
+            until,
+            max_account_age,
+            default_avatar: params.contains_key("avatar"),
+            name,
+        };
+
+        let mut reason = reason
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or(String::from("Raid cleanup"));
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        trace.point("fetching_members");
+        let bot_id = ctx.cache.current_user().id;
+        let mut selected = Self::fetch_members(&ctx, guild_id)
+            .await?
+            .into_iter()
+            .filter(|m| m.user.id != msg.author.id && m.user.id != bot_id)
+            .filter(|m| filter.matches(m))
+            .collect::<Vec<_>>();
+        selected.sort_by_key(|m| m.joined_at.map(|t| t.unix_timestamp()));
+
+        if selected.is_empty() {
+            return Err(CommandError {
+                title: String::from("No members match the selection"),
+                hint: Some(filter.describe()),
+                arg: None,
+            });
+        }
+
+        if selected.len() > MAX_RAID_CLEANUP_TARGETS {
+            return Err(CommandError {
+                title: format!(
+                    "{} members match the selection, at most {MAX_RAID_CLEANUP_TARGETS} can be removed at once",
+                    selected.len()
+                ),
+                hint: Some(String::from(
+                    "narrow the window with +until or add filters like +age",
+                )),
+                arg: None,
+            });
+        }
+
+        trace.point("verifying_permissions");
+        let mut targets = vec![];
+        let mut skipped = vec![];
+        for member in selected {
+            if can_target(&ctx, &author, &member, permission).await {
+                targets.push(member);
+            } else {
+                skipped.push(member);
+            }
+        }
+
+        if targets.is_empty() {
+            return Err(CommandError {
+                title: format!(
+                    "You may not target any of the {} selected members",
+                    skipped.len()
+                ),
+                hint: None,
+                arg: None,
+            });
+        }
+
+        let title = action.name().to_uppercase();
+        let header = format!(
+            "**RAID CLEANUP: {title}**\n-# Targets: {} | Skipped: {}\n-# {}\n```\n{reason}\n```",
+            targets.len(),
+            skipped.len(),
+            filter.describe()
+        );
+        let line = |m: &Member| {
+            format!(
+                "<@{}> `{}` | Joined <t:{}:R> | Created <t:{}:R>",
+                m.user.id,
+                m.user.name.replace('`', ""),
+                m.joined_at.map(|t| t.unix_timestamp()).unwrap_or_default(),
+                m.user.created_at().unix_timestamp()
+            )
+        };
+
+        let mut list = String::new();
+        let mut truncated = false;
+        for (i, member) in targets.iter().enumerate() {
+            let entry = line(member);
+            if header.len() + list.len() + entry.len() > 3900 {
+                list.push_str(&format!("-# and {} more", targets.len() - i));
+                truncated = true;
+                break;
+            }
+            list.push_str(&entry);
+            list.push('\n');
+        }
+
+        let buttons = |disabled: bool| {
+            vec![CreateActionRow::Buttons(vec![
+                CreateButton::new("confirm")
+                    .label(format!("{} {} members", action.name(), targets.len()))
+                    .style(ButtonStyle::Danger)
+                    .disabled(disabled),
+                CreateButton::new("cancel")
+                    .label("Cancel")
+                    .style(ButtonStyle::Secondary)
+                    .disabled(disabled),
+            ])]
+        };
+
+        let mut reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!("{header}\n{list}"))
+                    .color(BRAND_BLUE),
+            )
+            .components(buttons(false))
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if truncated {
+            let file = targets
+                .iter()
+                .map(|m| format!("{} ({})", m.user.name, m.user.id))
+                .collect::<Vec<_>>()
+                .join("\n");
+            reply = reply.add_file(CreateAttachment::bytes(
+                file.into_bytes(),
+                "raid-cleanup.txt",
+            ));
+        }
+
+        let mut prompt = match msg.channel_id.send_message(&ctx, reply).await {
+            Ok(m) => m,
+            Err(err) => {
+                consume_serenity_error("RAID CLEANUP RESPONSE".into(), err);
+                return Ok(());
+            }
+        };
+
+        trace.point("awaiting_user_interaction");
+        let interaction = loop {
+            let Some(interaction) = prompt
+                .await_component_interaction(&ctx.shard)
+                .timeout(Duration::from_secs(60))
+                .await
+            else {
+                let _ = prompt
+                    .edit(&ctx, EditMessage::new().components(buttons(true)))
+                    .await;
+                return Ok(());
+            };
+
+            if interaction.user.id != msg.author.id {
+                if let Err(err) = interaction
+                    .create_response(
+                        &ctx,
+                        CreateInteractionResponse::Message(
+                            CreateInteractionResponseMessage::new()
+                                .content("You are not the author of the original message!")
+                                .ephemeral(true),
+                        ),
+                    )
+                    .await
+                {
+                    consume_serenity_error("RAID CLEANUP INTERACTION RESPONSE".into(), err);
+                }
+
+                continue;
+            }
+
+            break interaction;
+        };
+
+        let confirmed = interaction.data.custom_id.as_str() == "confirm";
+        let status = if confirmed {
+            format!(
+                "**RAID CLEANUP: {title} IN PROGRESS**\n-# Removing {} members, this can take a while",
+                targets.len()
+            )
+        } else {
+            format!("**RAID CLEANUP: {title} CANCELLED**")
+        };
+
+        if let Err(err) = interaction
+            .create_response(
+                &ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(CreateEmbed::new().description(status).color(BRAND_BLUE))
+                        .components(vec![]),
+                ),
+            )
+            .await
+        {
+            consume_serenity_error("RAID CLEANUP INTERACTION RESPONSE".into(), err);
+        }
+
+        if !confirmed {
+            return Ok(());
+        }
+
+        trace.point("executing_sanctions");
+        let result = cleanup_raid(
+            &ctx,
+            &author,
+            guild_id,
+            &targets,
+            action,
+            &reason,
+            &filter.describe(),
+        )
+        .await;
+
+        let mut summary = format!(
+            "**RAID CLEANUP: {title} DONE**\n-# Batch ID: `{}` | {}: {} | Failed: {} | Skipped: {}\n```\n{reason}\n```",
+            result.batch_id,
+            action.past_tense(),
+            result.done.len(),
+            result.failed.len(),
+            skipped.len()
+        );
+        for (user_id, err) in result.failed.iter().take(20) {
+            summary.push_str(&format!("\n<@{user_id}> failed: {err}"));
+        }
+        if result.failed.len() > 20 {
+            summary.push_str(&format!(
+                "\n-# and {} more failures",
+                result.failed.len() - 20
+            ));
+        }
+
+        trace.point("sending_response");
+        if let Err(err) = prompt
+            .edit(
+                &ctx,
+                EditMessage::new().embed(CreateEmbed::new().description(summary).color(BRAND_BLUE)),
+            )
+            .await
+        {
+            consume_serenity_error("RAID CLEANUP RESPONSE".into(), err);
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![],
+            one_of: vec![Permissions::KICK_MEMBERS, Permissions::BAN_MEMBERS],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                CommandPermissions::moderation().as_slice(),
+            ]
+            .concat(),
+            silence_typing: false,
+        }
+    }
+}
@@ -22,6 +22,8 @@ use crate::{
 };
 use aegis_macros::command;
 
+use super::bulk::{BulkAction, run_bulk, target_member};
+
 pub struct Softban;
 
 impl Softban {
@@ -43,7 +45,9 @@ impl Command for Softban {
     fn get_full(&self) -> &'static str {
         "Bans and immediately unbans a member from the server and leaves a note in the users log. \
         Useful for clearing out messages without permanent consequences. \
-        Clears 1 day of messages."
+        Clears 1 day of messages. \
+        Several members can be given at once as IDs or mentions, or by replying to a raid alert or your own message listing them. \
+        They are listed for confirmation first, share one reason and are linked by a batch ID in their logs."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -91,11 +95,35 @@ impl Command for Softban {
         &self,
         ctx: Context,
         msg: Message,
-        #[transformers::reply_member] member: Member,
+        #[transformers::many_users] users: ManyUsers,
         #[transformers::reply_consume] reason: Option<String>,
         params: std::collections::HashMap<&str, (bool, CommandArgument)>,
         trace: &mut TraceContext,
     ) -> Result<(), CommandError> {
+        let days = {
+            if let Some(arg) = params.get("clear") {
+                if !arg.0 {
+                    0
+                } else if let CommandArgument::i32(days) = arg.1 {
+                    days.clamp(0, 7) as u8
+                } else {
+                    1
+                }
+            } else {
+                1
+            }
+        };
+
+        if users.len() > 1 {
+            let action = BulkAction::Softban { days };
+            return run_bulk(&ctx, &msg, action, users, reason, &params, trace).await;
+        }
+        let Some(user) = users.first() else {
+            return Err(CommandError::new("Unexpected error has occured."));
+        };
+        let member =
+            target_member(&ctx, msg.guild_id.unwrap_or_default(), user, &_users_arg).await?;
+
         let guild = crate::utils::get_guild_info(&ctx, msg.guild_id).await;
         let Ok(author_member) = msg.member(&ctx).await else {
             return Err(CommandError {
@@ -116,7 +144,7 @@ impl Command for Softban {
             });
         }
 
-        let inferred = matches!(_member_arg.inferred, Some(InferType::Message));
+        let inferred = matches!(_users_arg.inferred, Some(InferType::Message));
         let mut reason = reason
             .map(|s| {
                 if s.is_empty() || s.chars().all(char::is_whitespace) {
@@ -127,20 +155,6 @@ impl Command for Softban {
             })
             .unwrap_or(String::from("No reason provided"));
 
-        let days = {
-            if let Some(arg) = params.get("clear") {
-                if !arg.0 {
-                    0
-                } else if let CommandArgument::i32(days) = arg.1 {
-                    days.clamp(0, 7) as u8
-                } else {
-                    1
-                }
-            } else {
-                1
-            }
-        };
-
         if inferred && let Some(reply) = msg.referenced_message.clone() {
             if reply.author.id != ctx.cache.current_user().id {
                 let _ = reply.delete(&ctx).await;
@@ -24,13 +24,30 @@ pub struct Environment {
     pub repository: Option<String>,
     pub github_token: Option<String>,
     pub webhook: Option<String>,
+    pub ocr_backend: Option<OcrBackend>,
     pub ocr_training_data: Option<String>,
     pub ocr_character_whitelist: Option<String>,
+    pub ocr_workers: Option<usize>,
+    pub ocr_queue_size: Option<usize>,
+    pub ocr_max_image_size: Option<u64>,
+    pub ocr_max_pixels: Option<u64>,
     pub web_port: Option<u16>,
     pub web_url: Option<String>,
     pub s3: S3,
 }
 
+/// The engine used for OCR, `paddle` if not set or `tesseract` in builds without the `paddle` feature
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OcrBackend {
+    #[cfg(feature = "paddle")]
+    #[default]
+    Paddle,
+    #[cfg_attr(not(feature = "paddle"), default)]
+    Tesseract,
+    None,
+}
+
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct S3 {
     pub endpoint: String,
@@ -5,3 +5,12 @@ pub const BRAND_BLUE: Color = Color::from_rgb(4, 217, 178);
 
 pub const SOFT_YELLOW: Color = Color::from_rgb(255, 243, 176);
 pub const SOFT_GREEN: Color = Color::from_rgb(168, 213, 186);
+
+/// The nickname given to members renamed by the automod
+pub const NAME_PLACEHOLDER: &str = "Moderated Nickname";
+
+/// Targets a single bulk moderation command may act on
+pub const MAX_BULK_TARGETS: usize = 50;
+
+/// Members a single raid cleanup may remove
+pub const MAX_RAID_CLEANUP_TARGETS: usize = 500;
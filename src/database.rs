@@ -9,6 +9,7 @@ pub enum ActionType {
     Unban,
     Unmute,
     Log,
+    Rename,
 }
 
 impl std::fmt::Display for ActionType {
@@ -22,6 +23,7 @@ impl std::fmt::Display for ActionType {
             ActionType::Unban => write!(f, "unban"),
             ActionType::Unmute => write!(f, "unmute"),
             ActionType::Log => write!(f, "log"),
+            ActionType::Rename => write!(f, "rename"),
         }
     }
 }
@@ -0,0 +1,131 @@
+use chrono::Utc;
+use serenity::all::{Context, CreateEmbed, CreateMessage, GuildId, Member, Mentionable, RoleId};
+use tracing::warn;
+
+use crate::{
+    constants::{BRAND_BLUE, BRAND_RED, SOFT_YELLOW},
+    event_handler::Handler,
+    moderation,
+    utils::{
+        LogType,
+        age_gate::{AgeGateAction, age_string},
+        guild_log,
+        logging::LogContext,
+        reference::RefData,
+        snowflake_to_timestamp, tinyid,
+    },
+};
+
+/// Checks the account age of a joining member against the guilds age gate.
+/// Returns true if the member was kicked.
+pub(super) async fn account_age_gate(
+    handler: &Handler,
+    ctx: &Context,
+    guild_id: GuildId,
+    member: &Member,
+) -> bool {
+    let Some(gate) = handler.age_gate_cache.lock().await.get(guild_id.get()) else {
+        return false;
+    };
+
+    let user = &member.user;
+    let created_at = snowflake_to_timestamp(user.id.get());
+    let age = Utc::now() - created_at;
+    if age >= gate.min_age {
+        return false;
+    }
+
+    let reason = format!(
+        "Account younger than {} (Account age: {})",
+        age_string(gate.min_age),
+        age_string(age)
+    );
+
+    if gate.action != AgeGateAction::Flag {
+        let guild_name = match guild_id.to_partial_guild(ctx).await {
+            Ok(p) => p.name,
+            Err(_) => String::from("UNKNOWN_GUILD"),
+        };
+
+        let dm = CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**ACCOUNT TOO NEW**\n-# Server: {guild_name}\n{}",
+                    gate.render_message(&guild_name, age)
+                ))
+                .color(BRAND_BLUE),
+        );
+        let _ = user.direct_message(ctx, dm).await;
+    }
+
+    let (outcome, kicked) = match gate.action {
+        AgeGateAction::Kick => {
+            let current_user_id = ctx.cache.current_user().id;
+            let kicked = match guild_id.member(ctx, current_user_id).await {
+                Ok(author) => moderation::kick_member(
+                    ctx,
+                    author,
+                    member.clone(),
+                    guild_id,
+                    tinyid().await,
+                    reason.clone(),
+                    Some(String::from("Account Age Gate")),
+                    RefData::default(),
+                )
+                .await
+                .is_ok(),
+                Err(_) => false,
+            };
+
+            if kicked {
+                ("Kicked", true)
+            } else {
+                ("Kick failed", false)
+            }
+        }
+        AgeGateAction::Quarantine(role_id) => {
+            match ctx
+                .http
+                .add_member_role(guild_id, user.id, RoleId::new(role_id), Some(&reason))
+                .await
+            {
+                Ok(_) => ("Quarantined", false),
+                Err(err) => {
+                    warn!("Could not quarantine member; guild = {guild_id}; err = {err:?}");
+                    ("Quarantine failed", false)
+                }
+            }
+        }
+        AgeGateAction::Flag => ("Flagged", false),
+    };
+
+    let created_ts = created_at.timestamp();
+    guild_log(
+        ctx,
+        LogType::MemberJoinLeave,
+        guild_id,
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**ACCOUNT AGE GATE**\n-# User: {} | ID: {} | Action: {outcome}\nAccount Age: <t:{created_ts}:R> (<t:{created_ts}:f>)\nMinimum Age: {}",
+                    user.mention(),
+                    user.id.get(),
+                    age_string(gate.min_age)
+                ))
+                .color(if gate.action == AgeGateAction::Flag {
+                    SOFT_YELLOW
+                } else {
+                    BRAND_RED
+                }),
+        ),
+        Some(LogContext {
+            target_id: user.id.get(),
+            moderator_id: 0,
+            db_id: None,
+            content: None,
+        }),
+    )
+    .await;
+
+    kicked
+}
@@ -3,10 +3,15 @@ use serenity::all::{Context, CreateEmbed, CreateMessage, Member, Mentionable};
 use crate::{
     constants::SOFT_GREEN,
     event_handler::Handler,
-    utils::{LogType, guild_log, logging::LogContext},
+    moderation::raid,
+    utils::{
+        LogType, guild_log,
+        logging::LogContext,
+        raid_cache::{RaidCheck, RecentJoin},
+    },
 };
 
-pub async fn guild_member_addition(_handler: &Handler, ctx: Context, new_member: Member) {
+pub async fn guild_member_addition(handler: &Handler, ctx: Context, new_member: Member) {
     if new_member.user.bot {
         return;
     }
@@ -52,4 +57,39 @@ pub async fn guild_member_addition(_handler: &Handler, ctx: Context, new_member:
         }),
     )
     .await;
+
+    let kicked =
+        super::account_age_gate::account_age_gate(handler, &ctx, guild_id, &new_member).await;
+
+    let user = &new_member.user;
+    let join = RecentJoin::new(
+        user.id.get(),
+        created_ts,
+        user.global_name.clone().unwrap_or(user.name.clone()),
+        user.avatar.map(|a| a.to_string()),
+    );
+    let check = handler.raid_cache.lock().await.track(guild_id.get(), join);
+    match check {
+        RaidCheck::Triggered {
+            filter,
+            trigger,
+            suspects,
+        } => raid::start_lockdown(&ctx, guild_id, &filter.lockdown, &trigger, &suspects).await,
+        RaidCheck::Ongoing => raid::add_raid_suspect(guild_id, user.id).await,
+        RaidCheck::Clear => {}
+    }
+
+    if kicked {
+        return;
+    }
+
+    super::name_rules::name_rules(
+        handler,
+        &ctx,
+        guild_id,
+        &new_member.user,
+        new_member.nick.as_deref(),
+        &new_member.roles,
+    )
+    .await;
 }
@@ -28,6 +28,23 @@ pub async fn guild_member_update(
         return;
     }
 
+    let names_changed = old_if_available.as_ref().is_none_or(|old| {
+        old.nick != event.nick
+            || old.user.name != event.user.name
+            || old.user.global_name != event.user.global_name
+    });
+    if names_changed {
+        super::name_rules::name_rules(
+            handler,
+            &ctx,
+            event.guild_id,
+            &event.user,
+            event.nick.as_deref(),
+            &event.roles,
+        )
+        .await;
+    }
+
     let (moderator_id, reason, role_changes_from_log) =
         fetch_audit_log_info(&ctx, event.guild_id, event.user.id.get()).await;
 
@@ -1,20 +1,26 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
 use serenity::{
-    all::{ChannelId, Context, Message, MessageId},
-    futures::{StreamExt, stream::FuturesUnordered},
+    all::{Attachment, ChannelId, Context, GuildId, Message, MessageId, UserId},
+    futures::{StreamExt, future::join_all, stream::FuturesUnordered},
 };
-use sha2::{Digest, Sha256};
 
 use crate::{
+    OCR_POOL,
     event_handler::Handler,
     moderation,
     utils::{
+        attachment_filter::{AttachmentFilter, sniff_content_type},
         command_processing::process,
-        ocr::extract_text_from_bytes,
-        reference::RefData,
-        rule_cache::{OcrDebugEntry, Punishment, db_check_image_hash, db_record_image_hash},
-        tinyid,
+        image_hash::{perceptual_hash, sha256_hex},
+        mention_cache::Mentions,
+        normalize::{NormalizeStep, normalize},
+        ocr::image_urls,
+        ocr_pool::OcrPriority,
+        rule_cache::{
+            OcrDebugEntry, Rule, RuleHitSource, RuleScope, db_check_image_hash,
+            db_check_perceptual_hash, db_record_image_hash, db_record_rule_hit,
+        },
     },
 };
 
@@ -83,28 +89,42 @@ pub async fn message(handler: &Handler, ctx: Context, msg: Message) {
         }
     }
 
-    ocr_attachments(&ctx, &msg, handler).await;
+    if spam_filter(&ctx, &msg, handler).await {
+        return;
+    }
 
-    if msg.content.starts_with(handler.prefix.as_str()) && msg.guild_id.is_some() {
-        process(handler, ctx.clone(), msg.clone()).await;
+    if mention_filter(&ctx, &msg, handler).await {
+        return;
+    }
+
+    if text_rules(&ctx, &msg, handler).await {
+        return;
+    }
+
+    if attachment_rules(&ctx, &msg, handler, &msg.attachments).await {
         return;
     }
-}
 
-fn sha256_hex(bytes: &[u8]) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(bytes);
+    let images = image_urls(&msg.attachments, &msg.embeds, &msg.sticker_items);
+    ocr_images(&ctx, &msg, handler, images, OcrPriority::Normal).await;
 
-    hasher
-        .finalize()
-        .iter()
-        .map(|b| format!("{:02x}", b))
-        .collect::<String>()
+    if msg.content.starts_with(handler.prefix.as_str()) && msg.guild_id.is_some() {
+        process(handler, ctx.clone(), msg.clone()).await;
+        return;
+    }
 }
 
-#[allow(deprecated)]
-async fn ocr_attachments(ctx: &Context, msg: &Message, handler: &Handler) {
-    if msg.attachments.is_empty() || msg.author.bot {
+/// Runs the given images of the message through OCR and the guilds OCR rules.
+/// Edits pass only the images that were added, so already checked images are not processed twice.
+/// Images that reach OCR are queued on the OCR pool with the given priority.
+pub(super) async fn ocr_images(
+    ctx: &Context,
+    msg: &Message,
+    handler: &Handler,
+    images: Vec<String>,
+    priority: OcrPriority,
+) {
+    if images.is_empty() || msg.author.bot {
         return;
     }
 
@@ -112,26 +132,32 @@ async fn ocr_attachments(ctx: &Context, msg: &Message, handler: &Handler) {
         return;
     };
 
-    if let Ok(member) = guild_id.member(ctx, msg.author.id).await {
-        if let Ok(perms) = member.permissions(ctx) {
-            if perms.contains(serenity::all::Permissions::MANAGE_MESSAGES)
-                || perms.contains(serenity::all::Permissions::ADMINISTRATOR)
-            {
-                return;
-            }
-        }
+    // nothing could match, so the images are not even downloaded
+    if !handler
+        .rule_cache
+        .lock()
+        .await
+        .has_ocr_rules(guild_id.get())
+    {
+        return;
+    }
+
+    if is_automod_exempt(ctx, guild_id, msg.author.id).await {
+        return;
     }
 
     let mut handles = vec![];
     let guild_id_u64 = guild_id.get();
     let msg_id = msg.id.get();
+    let scope = rule_scope(ctx, msg).await;
 
-    for attachment in msg.attachments.clone().into_iter() {
+    for url in images {
         let rule_cache = handler.rule_cache.clone();
         let ocr_result_cache = handler.ocr_result_cache.clone();
+        let scope = scope.clone();
 
         handles.push(tokio::spawn(async move {
-            let Ok(req) = reqwest::get(attachment.proxy_url.clone()).await else {
+            let Ok(req) = reqwest::get(url).await else {
                 return None;
             };
             let Ok(bytes) = req.bytes().await else {
@@ -142,17 +168,27 @@ async fn ocr_attachments(ctx: &Context, msg: &Message, handler: &Handler) {
 
             {
                 let cache = rule_cache.lock().await;
-                if let Some(cached) = cache.image_hash_cache.get(guild_id_u64, &image_hash) {
-                    let rule = match cached {
+                let cached = cache
+                    .image_hash_cache
+                    .get(guild_id_u64, &image_hash)
+                    .map(|cached| match cached {
                         Some(rule_id) => cache.get_by_id(rule_id).cloned(),
                         None => None,
-                    };
+                    });
+
+                // only the first matched rule is cached, if it is exempt here the others still have to be checked
+                if let Some(rule) = cached
+                    && !rule.as_ref().is_some_and(|r| r.exemptions.exempts(&scope))
+                {
                     {
                         let debug_entry = OcrDebugEntry {
                             text: String::from("*(matched via image hash cache)*"),
+                            normalized: None,
+                            preprocessing: vec![],
+                            cached_text: false,
                             matched: rule
                                 .as_ref()
-                                .map(|r| (r.name.clone(), r.id.clone(), r.pattern.clone())),
+                                .map(|r| (r.name.clone(), r.id.clone(), r.describe_pattern())),
                         };
 
                         let mut ocr_cache = ocr_result_cache.lock().await;
@@ -161,26 +197,35 @@ async fn ocr_attachments(ctx: &Context, msg: &Message, handler: &Handler) {
                         updated.push(debug_entry);
                         ocr_cache.insert(msg_id, updated);
                     }
-                    return rule;
+                    return rule.map(|rule| (rule, RuleHitSource::HashCache));
                 }
             }
 
-            if let Some(rule_id) = db_check_image_hash(guild_id_u64, &image_hash).await {
-                let rule = {
+            let db_hit = match db_check_image_hash(guild_id_u64, &image_hash).await {
+                Some(rule_id) => {
                     let mut cache = rule_cache.lock().await;
                     cache.image_hash_cache.insert(
                         guild_id_u64,
                         image_hash.clone(),
                         Some(rule_id.clone()),
                     );
-                    cache.get_by_id(&rule_id).cloned()
-                };
+                    Some(cache.get_by_id(&rule_id).cloned())
+                }
+                None => None,
+            };
+
+            if let Some(rule) = db_hit
+                && !rule.as_ref().is_some_and(|r| r.exemptions.exempts(&scope))
+            {
                 {
                     let debug_entry = OcrDebugEntry {
                         text: String::from("*(matched via database image hash)*"),
+                        normalized: None,
+                        preprocessing: vec![],
+                        cached_text: false,
                         matched: rule
                             .as_ref()
-                            .map(|r| (r.name.clone(), r.id.clone(), r.pattern.clone())),
+                            .map(|r| (r.name.clone(), r.id.clone(), r.describe_pattern())),
                     };
 
                     let mut ocr_cache = ocr_result_cache.lock().await;
@@ -189,25 +234,108 @@ async fn ocr_attachments(ctx: &Context, msg: &Message, handler: &Handler) {
                     updated.push(debug_entry);
                     ocr_cache.insert(msg_id, updated);
                 }
-                return rule;
+                return rule.map(|rule| (rule, RuleHitSource::DbHash));
+            }
+
+            let phash = {
+                let bytes = bytes.clone();
+                tokio::task::spawn_blocking(move || perceptual_hash(&bytes))
+                    .await
+                    .ok()
+                    .flatten()
+            };
+
+            if let Some(phash) = phash {
+                let cached = {
+                    let cache = rule_cache.lock().await;
+                    cache
+                        .image_hash_cache
+                        .get_similar(guild_id_u64, phash)
+                        .map(str::to_string)
+                };
+
+                let rule_id = match cached {
+                    Some(id) => Some(id),
+                    None => db_check_perceptual_hash(guild_id_u64, phash).await,
+                };
+
+                // rules deleted since the hash was stored fall through to OCR
+                let rule = match rule_id {
+                    Some(rule_id) => rule_cache.lock().await.get_by_id(&rule_id).cloned(),
+                    None => None,
+                };
+
+                if let Some(rule) = rule
+                    && !rule.exemptions.exempts(&scope)
+                {
+                    {
+                        let mut cache = rule_cache.lock().await;
+                        cache.image_hash_cache.insert(
+                            guild_id_u64,
+                            image_hash.clone(),
+                            Some(rule.id.clone()),
+                        );
+                        cache.image_hash_cache.insert_perceptual(
+                            guild_id_u64,
+                            phash,
+                            rule.id.clone(),
+                        );
+                    }
+                    {
+                        let debug_entry = OcrDebugEntry {
+                            text: String::from("*(matched via perceptual image hash)*"),
+                            normalized: None,
+                            preprocessing: vec![],
+                            cached_text: false,
+                            matched: Some((
+                                rule.name.clone(),
+                                rule.id.clone(),
+                                rule.describe_pattern(),
+                            )),
+                        };
+
+                        let mut ocr_cache = ocr_result_cache.lock().await;
+                        let existing = ocr_cache.get(msg_id).cloned().unwrap_or_default();
+                        let mut updated = existing;
+                        updated.push(debug_entry);
+                        ocr_cache.insert(msg_id, updated);
+                    }
+
+                    db_record_image_hash(guild_id_u64, &image_hash, Some(phash), &rule.id).await;
+                    return Some((rule, RuleHitSource::PerceptualHash));
+                }
             }
 
-            let image_str = match extract_text_from_bytes(&bytes).await {
-                Ok(d) => d,
+            let (image_str, preprocessing, cached_text) = match OCR_POOL
+                .extract(guild_id_u64, priority, bytes.to_vec())
+                .await
+            {
+                Ok(output) => (output.text, output.steps, output.cached),
                 Err(_) => return None,
             };
 
-            let result = {
+            let (result, exempt_rules) = {
                 let cache = rule_cache.lock().await;
-                cache.matches(guild_id_u64, image_str.clone())
+                (
+                    cache.matches(guild_id_u64, image_str.clone(), &scope),
+                    cache.has_exempt_ocr_rules(guild_id_u64, &scope),
+                )
             };
 
             {
+                let normalized = match &result {
+                    Some(rule) => rule.normalize(&image_str),
+                    None => normalize(&image_str, &NormalizeStep::DEFAULT),
+                };
+
                 let debug_entry = OcrDebugEntry {
                     text: image_str.clone(),
+                    normalized: (normalized != image_str).then_some(normalized),
+                    preprocessing,
+                    cached_text,
                     matched: result
                         .as_ref()
-                        .map(|rule| (rule.name.clone(), rule.id.clone(), rule.pattern.clone())),
+                        .map(|rule| (rule.name.clone(), rule.id.clone(), rule.describe_pattern())),
                 };
 
                 let mut ocr_cache = ocr_result_cache.lock().await;
@@ -217,256 +345,378 @@ async fn ocr_attachments(ctx: &Context, msg: &Message, handler: &Handler) {
                 ocr_cache.insert(msg_id, updated);
             }
 
-            {
+            // an image is only known as clean if no rule was skipped for being exempt here
+            if result.is_some() || !exempt_rules {
                 let mut cache = rule_cache.lock().await;
                 cache.image_hash_cache.insert(
                     guild_id_u64,
                     image_hash.clone(),
                     result.as_ref().map(|rule| rule.id.clone()),
                 );
+                if let (Some(rule), Some(phash)) = (&result, phash) {
+                    cache
+                        .image_hash_cache
+                        .insert_perceptual(guild_id_u64, phash, rule.id.clone());
+                }
             }
 
             if let Some(ref rule) = result {
-                db_record_image_hash(guild_id_u64, &image_hash, &rule.id).await;
+                db_record_image_hash(guild_id_u64, &image_hash, phash, &rule.id).await;
             }
 
-            result
+            result.map(|rule| (rule, RuleHitSource::Ocr))
         }));
     }
 
     let mut futures: FuturesUnordered<_> = handles.into_iter().collect();
 
     while let Some(res) = futures.next().await {
-        let Some(rule) = res.ok().flatten() else {
+        let Some((rule, source)) = res.ok().flatten() else {
             continue;
         };
 
-        let _ = msg.delete(ctx).await;
+        // hash matches skip the rule lookup, so exemptions are checked here as well
+        if rule.exemptions.exempts(&scope) {
+            continue;
+        }
 
-        let should_punish = {
-            let mut rule_cache = handler.rule_cache.lock().await;
-            rule_cache.check_debounce(rule.id.clone(), msg.author.id.get())
-        };
+        punish_rule_match(ctx, msg, handler, &rule, "OCR RULE", source, None).await;
+        break;
+    }
+}
 
-        if should_punish {
-            let current_user_id = ctx.cache.current_user().id;
-            let Some(guild_id) = msg.guild_id else {
-                break;
-            };
-            let Ok(author) = guild_id.member(ctx, current_user_id).await else {
-                break;
-            };
-            let Ok(member) = guild_id.member(ctx, msg.author.id).await else {
-                break;
-            };
-            let db_id = tinyid().await;
-
-            let raw_reason = match &rule.punishment {
-                Punishment::Warn { reason, .. }
-                | Punishment::Softban { reason, .. }
-                | Punishment::Kick { reason, .. }
-                | Punishment::Ban { reason, .. }
-                | Punishment::Mute { reason, .. }
-                | Punishment::Log { reason, .. } => reason.clone(),
-            };
+/// Checks the message content against the guilds text rules, returns true if a rule was triggered
+pub(super) async fn text_rules(ctx: &Context, msg: &Message, handler: &Handler) -> bool {
+    if msg.content.is_empty() || msg.author.bot {
+        return false;
+    }
 
-            let rule_note = format!("Rule `{}` Violation | {}", rule.id, rule.pattern);
-            let formatted_reason = raw_reason;
+    let Some(guild_id) = msg.guild_id else {
+        return false;
+    };
 
-            let guild_name = {
-                match guild_id.to_partial_guild(&ctx).await {
-                    Ok(p) => p.name.clone(),
-                    Err(_) => String::from("UNKNOWN_GUILD"),
-                }
-            };
+    if !handler
+        .rule_cache
+        .lock()
+        .await
+        .has_text_rules(guild_id.get())
+    {
+        return false;
+    }
 
-            let time_string = |duration_seconds: u64| -> String {
-                if duration_seconds == 0 {
-                    return String::from("permanent");
-                }
-                let duration =
-                    chrono::TimeDelta::try_seconds(duration_seconds as i64).unwrap_or_default();
-                let (time, mut unit) = match () {
-                    _ if (duration.num_days() as f64 / 365.0).fract() == 0.0
-                        && duration.num_days() >= 365 =>
-                    {
-                        (duration.num_days() / 365, String::from("year"))
-                    }
-                    _ if (duration.num_days() as f64 / 30.0).fract() == 0.0
-                        && duration.num_days() >= 30 =>
-                    {
-                        (duration.num_days() / 30, String::from("month"))
-                    }
-                    _ if duration.num_days() != 0 => (duration.num_days(), String::from("day")),
-                    _ if duration.num_hours() != 0 => (duration.num_hours(), String::from("hour")),
-                    _ if duration.num_minutes() != 0 => {
-                        (duration.num_minutes(), String::from("minute"))
-                    }
-                    _ if duration.num_seconds() != 0 => {
-                        (duration.num_seconds(), String::from("second"))
-                    }
-                    _ => (0, String::new()),
-                };
-                if time > 1 {
-                    unit.push('s');
-                }
-                format!("for {time} {unit}")
-            };
+    let scope = rule_scope(ctx, msg).await;
+    let rule = {
+        let cache = handler.rule_cache.lock().await;
+        cache.matches_text(guild_id.get(), &msg.content, &scope)
+    };
 
-            macro_rules! send_dm {
-                ($silent:expr, $title:expr) => {
-                    send_dm!($silent, $title, String::new())
-                };
-                ($silent:expr, $title:expr, $duration:expr) => {
-                    if !$silent {
-                        use serenity::all::{CreateEmbed, CreateMessage};
-                        let duration_text = if $duration.is_empty() {
-                            String::new()
-                        } else {
-                            format!(" | Duration: {}", $duration)
-                        };
-                        let desc = format!(
-                            "**{}**\n-# Server: {}{}\n```\n{}\n```",
-                            $title, guild_name, duration_text, formatted_reason
-                        );
-                        let dm = CreateMessage::new().add_embed(
-                            CreateEmbed::new()
-                                .description(desc)
-                                .color(crate::constants::BRAND_BLUE),
-                        );
-                        let _ = msg.author.direct_message(&ctx, dm).await;
-                    }
-                };
-            }
+    let Some(rule) = rule else {
+        return false;
+    };
 
-            match rule.punishment {
-                Punishment::Warn { reason: _, silent } => {
-                    send_dm!(silent, "WARNED");
-                    let _ = moderation::warn_member(
-                        &ctx,
-                        author,
-                        member,
-                        guild_id,
-                        db_id,
-                        formatted_reason.clone(),
-                        Some(rule_note.clone()),
-                        RefData::default(),
-                    )
-                    .await;
-                }
-                Punishment::Softban {
-                    reason: _,
-                    day_clear_amount,
-                    silent,
-                } => {
-                    send_dm!(silent, "SOFTBANNED");
-                    let _ = moderation::softban(
-                        ctx,
-                        author,
-                        member,
-                        guild_id,
-                        db_id,
-                        formatted_reason.clone(),
-                        Some(rule_note.clone()),
-                        day_clear_amount,
-                        RefData::default(),
-                    )
-                    .await;
-                }
-                Punishment::Kick { reason: _, silent } => {
-                    send_dm!(silent, "KICKED");
-                    let _ = moderation::kick_member(
-                        &ctx,
-                        author,
-                        member,
-                        guild_id,
-                        db_id,
-                        formatted_reason.clone(),
-                        Some(rule_note.clone()),
-                        RefData::default(),
-                    )
-                    .await;
-                }
-                Punishment::Ban {
-                    reason: _,
-                    day_clear_amount,
-                    duration,
-                    silent,
-                } => {
-                    send_dm!(silent, "BANNED", time_string(duration));
-                    let _ = moderation::ban_member(
-                        ctx,
-                        author,
-                        member,
-                        guild_id,
-                        db_id,
-                        formatted_reason.clone(),
-                        Some(rule_note.clone()),
-                        day_clear_amount,
-                        chrono::TimeDelta::try_seconds(duration as i64).unwrap_or_default(),
-                        RefData::default(),
-                    )
-                    .await;
-                }
-                Punishment::Mute {
-                    reason: _,
-                    duration,
-                    silent,
-                } => {
-                    send_dm!(silent, "MUTED", time_string(duration));
-                    let _ = moderation::mute_member(
-                        ctx,
-                        author,
-                        member,
-                        guild_id,
-                        db_id,
-                        formatted_reason.clone(),
-                        Some(rule_note.clone()),
-                        chrono::TimeDelta::try_seconds(duration as i64).unwrap_or_default(),
-                        RefData::default(),
-                    )
-                    .await;
-                }
-                Punishment::Log {
-                    reason: _,
-                    channel_id,
-                } => {
-                    use serenity::all::{
-                        ChannelId, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable,
-                    };
-                    let reply = CreateMessage::new()
-                        .add_embed(
-                            CreateEmbed::new()
-                                .description(format!(
-                                    "**OCR RULE TRIGGERED**\n-# Log ID: `{}` | Actor: {} | Target: {} | Rule: {}\n```\n{}\n```\n-# {}",
-                                    db_id,
-                                    author.mention(),
-                                    member.mention(),
-                                    rule.name.to_uppercase(),
-                                    formatted_reason,
-                                    rule_note
-                                ))
-                                .color(crate::constants::BRAND_BLUE),
-                        )
-                        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
-
-                    if let Ok(m) = ChannelId::new(channel_id).send_message(ctx, reply).await {
-                        let _ = sqlx::query!(
-                            "INSERT INTO log_messages_context (message_id, guild_id, target_id, moderator_id, db_id, content) VALUES ($1, $2, $3, $4, $5, $6)",
-                            m.id.get() as i64,
-                            guild_id.get() as i64,
-                            member.user.id.get() as i64,
-                            author.user.id.get() as i64,
-                            Some(db_id),
-                            None::<Vec<u8>>
-                        )
-                        .execute(&*crate::SQL)
-                        .await;
-                    }
-                }
-            }
+    if is_automod_exempt(ctx, guild_id, msg.author.id).await {
+        return false;
+    }
+
+    punish_rule_match(
+        ctx,
+        msg,
+        handler,
+        &rule,
+        "TEXT RULE",
+        RuleHitSource::Text,
+        None,
+    )
+    .await;
+    true
+}
+
+/// Checks the given attachments of the message against the guilds attachment rules, returns true if a rule was triggered.
+/// Content types are only downloaded if one of the rules filters by them.
+pub(super) async fn attachment_rules(
+    ctx: &Context,
+    msg: &Message,
+    handler: &Handler,
+    attachments: &[Attachment],
+) -> bool {
+    if attachments.is_empty() || msg.author.bot {
+        return false;
+    }
+
+    let Some(guild_id) = msg.guild_id else {
+        return false;
+    };
+
+    if !handler
+        .rule_cache
+        .lock()
+        .await
+        .has_attachment_rules(guild_id.get())
+    {
+        return false;
+    }
+
+    let scope = rule_scope(ctx, msg).await;
+    let rules = handler
+        .rule_cache
+        .lock()
+        .await
+        .attachment_rules(guild_id.get(), &scope);
+    if rules.is_empty() {
+        return false;
+    }
+
+    if is_automod_exempt(ctx, guild_id, msg.author.id).await {
+        return false;
+    }
+
+    let filters = rules
+        .into_iter()
+        .filter_map(|rule| Some((AttachmentFilter::parse(&rule.pattern).ok()?, rule)))
+        .collect::<Vec<_>>();
+
+    let content_types = if filters.iter().any(|(f, _)| f.needs_content_type()) {
+        join_all(attachments.iter().map(sniff_content_type)).await
+    } else {
+        vec![]
+    };
+
+    let Some((rule, matched)) = filters
+        .iter()
+        .find_map(|(filter, rule)| Some((rule, filter.check(attachments, &content_types)?)))
+    else {
+        return false;
+    };
+
+    punish_rule_match(
+        ctx,
+        msg,
+        handler,
+        rule,
+        "ATTACHMENT RULE",
+        RuleHitSource::Attachment,
+        Some(&matched),
+    )
+    .await;
+    true
+}
+
+/// Tracks the message for the guilds spam filter, returns true if the filter was triggered
+async fn spam_filter(ctx: &Context, msg: &Message, handler: &Handler) -> bool {
+    if msg.author.bot {
+        return false;
+    }
+
+    let Some(guild_id) = msg.guild_id else {
+        return false;
+    };
+
+    let triggered = {
+        let mut cache = handler.spam_cache.lock().await;
+        cache.track(
+            guild_id.get(),
+            msg.author.id.get(),
+            msg.channel_id.get(),
+            msg.id.get(),
+            &msg.content,
+        )
+    };
+
+    let Some((filter, trigger, messages)) = triggered else {
+        return false;
+    };
+
+    if is_automod_exempt(ctx, guild_id, msg.author.id).await {
+        return false;
+    }
+
+    let mut by_channel: HashMap<u64, Vec<MessageId>> = HashMap::new();
+    for (channel_id, message_id) in messages {
+        by_channel
+            .entry(channel_id)
+            .or_default()
+            .push(MessageId::new(message_id));
+    }
+
+    for (channel_id, message_ids) in by_channel {
+        let _ = ChannelId::new(channel_id)
+            .delete_messages(ctx, message_ids)
+            .await;
+    }
+
+    let note = format!(
+        "Spam Filter | Triggered by {trigger} in <#{}>",
+        msg.channel_id
+    );
+
+    moderation::apply_punishment(
+        ctx,
+        guild_id,
+        &msg.author,
+        &filter.punishment,
+        "SPAM FILTER",
+        "spam",
+        note,
+    )
+    .await;
+
+    true
+}
+
+/// Counts the unique mentions of the message for the guilds mention filter, returns true if the filter was triggered
+async fn mention_filter(ctx: &Context, msg: &Message, handler: &Handler) -> bool {
+    if msg.author.bot || msg.content.is_empty() {
+        return false;
+    }
+
+    let Some(guild_id) = msg.guild_id else {
+        return false;
+    };
+
+    if !handler
+        .mention_cache
+        .lock()
+        .await
+        .has_filter(guild_id.get())
+    {
+        return false;
+    }
+
+    let mentions = Mentions::parse(&msg.content, msg.author.id.get());
+
+    let triggered = {
+        let mut cache = handler.mention_cache.lock().await;
+        cache.track(guild_id.get(), msg.author.id.get(), mentions.count())
+    };
+
+    let Some((filter, total)) = triggered else {
+        return false;
+    };
+
+    if is_automod_exempt(ctx, guild_id, msg.author.id).await {
+        return false;
+    }
+
+    let _ = msg.delete(ctx).await;
+
+    let note = format!(
+        "Mention Filter | {total} mentions in <#{}>{}",
+        msg.channel_id,
+        if mentions.everyone {
+            " | attempted @everyone"
+        } else {
+            ""
         }
+    );
+
+    moderation::apply_punishment(
+        ctx,
+        guild_id,
+        &msg.author,
+        &filter.punishment,
+        "MENTION FILTER",
+        "mentions",
+        note,
+    )
+    .await;
+
+    true
+}
 
-        break;
+/// Builds the scope for rule exemption checks, fetching the member if the message does not carry it (e.g. edits)
+async fn rule_scope(ctx: &Context, msg: &Message) -> RuleScope {
+    let role_ids = match (&msg.member, msg.guild_id) {
+        (Some(member), _) => member.roles.iter().map(|r| r.get()).collect(),
+        (None, Some(guild_id)) => guild_id
+            .member(ctx, msg.author.id)
+            .await
+            .map(|m| m.roles.iter().map(|r| r.get()).collect())
+            .unwrap_or_default(),
+        (None, None) => vec![],
+    };
+
+    RuleScope {
+        channel_id: msg.channel_id.get(),
+        user_id: msg.author.id.get(),
+        role_ids,
+    }
+}
+
+#[allow(deprecated)]
+async fn is_automod_exempt(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+    if let Ok(member) = guild_id.member(ctx, user_id).await {
+        if let Ok(perms) = member.permissions(ctx) {
+            return perms.contains(serenity::all::Permissions::MANAGE_MESSAGES)
+                || perms.contains(serenity::all::Permissions::ADMINISTRATOR);
+        }
+    }
+
+    false
+}
+
+/// Deletes the offending message and applies the rules punishment, respecting the rules cooldown.
+/// Every match is recorded for the rule statistics, including the ones skipped by the cooldown.
+/// `detail` describes what matched if the pattern alone does not, e.g. the offending attachment.
+async fn punish_rule_match(
+    ctx: &Context,
+    msg: &Message,
+    handler: &Handler,
+    rule: &Rule,
+    source: &str,
+    hit_source: RuleHitSource,
+    detail: Option<&str>,
+) {
+    let should_punish = {
+        let mut rule_cache = handler.rule_cache.lock().await;
+        rule_cache.check_cooldown(rule, msg.author.id.get())
+    };
+
+    if should_punish || rule.cooldown.delete_during_cooldown {
+        let _ = msg.delete(ctx).await;
+    }
+
+    let Some(guild_id) = msg.guild_id else {
+        return;
+    };
+
+    if !should_punish {
+        db_record_rule_hit(
+            &rule.id,
+            guild_id.get(),
+            msg.author.id.get(),
+            Some(msg),
+            hit_source,
+            false,
+            None,
+        )
+        .await;
+        return;
     }
+
+    let mut rule_note = format!("Rule `{}` Violation | {}", rule.id, rule.describe_pattern());
+    if let Some(detail) = detail {
+        rule_note.push_str(&format!(" | Matched: {detail}"));
+    }
+
+    let action_id = moderation::apply_punishment(
+        ctx,
+        guild_id,
+        &msg.author,
+        &rule.punishment,
+        source,
+        &rule.name,
+        rule_note,
+    )
+    .await;
+
+    db_record_rule_hit(
+        &rule.id,
+        guild_id.get(),
+        msg.author.id.get(),
+        Some(msg),
+        hit_source,
+        action_id.is_some(),
+        action_id.as_deref(),
+    )
+    .await;
 }
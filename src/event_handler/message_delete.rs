@@ -1,15 +1,21 @@
 use chrono::Utc;
 use serenity::all::{
-    Channel, Context, CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateMessage,
+    Channel, Context, CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateMessage, GuildId,
     MessageAction, audit_log::Action,
 };
 
 use crate::{
     constants::BRAND_RED,
     event_handler::{Handler, MessageDeleteEvent},
-    utils::{LogType, cache::partials::PartialMessage, guild_log, snowflake_to_timestamp},
+    utils::{
+        LogType, cache::partials::PartialMessage, guild_log, mention_cache::Mentions,
+        snowflake_to_timestamp,
+    },
 };
 
+/// Messages with mentions deleted by their author within this many seconds are logged as ghost pings
+const GHOST_PING_WINDOW: i64 = 30;
+
 pub async fn message_delete(
     _handler: &Handler,
     ctx: Context,
@@ -91,6 +97,10 @@ pub async fn message_delete(
         }
     }
 
+    if actor_id.is_none() {
+        ghost_ping(&ctx, guild_id, &msg).await;
+    }
+
     if let Some(moderator) = actor_id {
         description.push_str(&format!("| Actor: <@{moderator}> "));
     };
@@ -125,3 +135,41 @@ pub async fn message_delete(
     )
     .await;
 }
+
+async fn ghost_ping(ctx: &Context, guild_id: GuildId, msg: &PartialMessage) {
+    let age = Utc::now() - snowflake_to_timestamp(msg.id);
+    if age.num_seconds() > GHOST_PING_WINDOW {
+        return;
+    }
+
+    let mentions = Mentions::parse(&msg.content, msg.author.id);
+    if mentions.is_empty() {
+        return;
+    }
+
+    guild_log(
+        ctx,
+        LogType::MessageUpdate,
+        guild_id,
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**GHOST PING**\n-# ID: {} | Target: <@{}> | Channel: <#{}> | Deleted after {}s\nMentioned: {}\n```\n{} \n```",
+                    msg.id,
+                    msg.author.id,
+                    msg.channel_id,
+                    age.num_seconds(),
+                    mentions.describe(),
+                    msg.content
+                ))
+                .color(BRAND_RED),
+        ),
+        Some(crate::utils::logging::LogContext {
+            target_id: msg.author.id,
+            moderator_id: msg.author.id,
+            db_id: None,
+            content: Some(msg.content.clone()),
+        }),
+    )
+    .await;
+}
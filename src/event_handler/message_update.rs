@@ -1,7 +1,7 @@
 use chrono::Utc;
 use serenity::all::{
-    Channel, Context, CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateMessage, Message,
-    MessageUpdateEvent,
+    Channel, Context, CreateAttachment, CreateEmbed, CreateEmbedAuthor, CreateMessage, Embed,
+    Message, MessageUpdateEvent,
 };
 
 use crate::{
@@ -11,16 +11,26 @@ use crate::{
         LogType,
         cache::{message_cache::MessageCache, partials::PartialMessage},
         create_diff, guild_log,
+        ocr::image_urls,
+        ocr_pool::OcrPriority,
     },
 };
 
 pub async fn message_update(
-    _handler: &Handler,
+    handler: &Handler,
     ctx: Context,
     old_if_available: Option<PartialMessage>,
     new: Option<Message>,
     event: MessageUpdateEvent,
 ) {
+    // Discord adds the embeds of posted links through an update without an edit timestamp
+    if event.edited_timestamp.is_none() {
+        if event.embeds.as_ref().is_some_and(|e| !e.is_empty()) {
+            embed_update(handler, &ctx, old_if_available.as_ref(), new, &event).await;
+        }
+        return;
+    }
+
     if event
         .edited_timestamp
         .is_none_or(|t| t.timestamp() < Utc::now().timestamp())
@@ -43,6 +53,42 @@ pub async fn message_update(
         },
     };
 
+    if new_msg.guild_id.is_none() {
+        new_msg.guild_id = event.guild_id;
+    }
+
+    // without the previous version every attachment is checked, known images hit the hash cache
+    let added = new_msg
+        .attachments
+        .iter()
+        .filter(|a| {
+            old_if_available.as_ref().is_none_or(|old| {
+                !old.attachment_urls
+                    .iter()
+                    .any(|o| o.url == a.url || o.attachment_id() == Some(a.id.get()))
+            })
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let embeds = added_embeds(&new_msg.embeds, old_if_available.as_ref());
+
+    // OCR can queue for a while, the edit is logged without waiting for it
+    {
+        let handler = handler.clone();
+        let ctx = ctx.clone();
+        let msg = new_msg.clone();
+        tokio::spawn(async move {
+            if super::message::text_rules(&ctx, &msg, &handler).await
+                || super::message::attachment_rules(&ctx, &msg, &handler, &added).await
+            {
+                return;
+            }
+
+            let images = image_urls(&added, &embeds, &[]);
+            super::message::ocr_images(&ctx, &msg, &handler, images, OcrPriority::Low).await;
+        });
+    }
+
     if new_msg.content.is_empty() {
         new_msg.content = String::from("(no content)");
     }
@@ -158,3 +204,71 @@ pub async fn message_update(
     )
     .await;
 }
+
+/// Runs the images of embeds Discord added to a message after it was sent through OCR
+async fn embed_update(
+    handler: &Handler,
+    ctx: &Context,
+    old: Option<&PartialMessage>,
+    new: Option<Message>,
+    event: &MessageUpdateEvent,
+) {
+    let Some(guild_id) = event.guild_id.or(new.as_ref().and_then(|m| m.guild_id)) else {
+        return;
+    };
+    if !handler
+        .rule_cache
+        .lock()
+        .await
+        .has_ocr_rules(guild_id.get())
+    {
+        return;
+    }
+
+    let mut msg = match new {
+        Some(m) => m,
+        None => match event.channel_id.message(ctx, event.id).await {
+            Ok(m) => m,
+            Err(_) => return,
+        },
+    };
+
+    if msg.author.bot {
+        return;
+    }
+
+    if msg.guild_id.is_none() {
+        msg.guild_id = event.guild_id;
+    }
+
+    let embeds = added_embeds(&msg.embeds, old);
+    let images = image_urls(&[], &embeds, &[]);
+
+    let handler = handler.clone();
+    let ctx = ctx.clone();
+    tokio::spawn(async move {
+        super::message::ocr_images(&ctx, &msg, &handler, images, OcrPriority::Low).await;
+    });
+}
+
+/// The embeds with images that are not part of the previous version of the message, all of them without one
+fn added_embeds(embeds: &[Embed], old: Option<&PartialMessage>) -> Vec<Embed> {
+    let Some(old) = old else {
+        return embeds.to_vec();
+    };
+    let old_embeds = serde_json::to_string(&old.embeds).unwrap_or_default();
+
+    embeds
+        .iter()
+        .filter(|e| {
+            [
+                e.image.as_ref().map(|i| i.url.as_str()),
+                e.thumbnail.as_ref().map(|t| t.url.as_str()),
+            ]
+            .into_iter()
+            .flatten()
+            .any(|url| !old_embeds.contains(url))
+        })
+        .cloned()
+        .collect()
+}
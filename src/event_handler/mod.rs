@@ -18,18 +18,25 @@ use tracing::{info, warn};
 use crate::{
     SQL,
     commands::{
-        About, Ban, Cache, CacheSize, ColonThree, Command, ContextCmd, CreateOcrRule, DefineLog,
-        DeleteRule, Duration as DurationCommand, EditRef, Edits, Encrypt, ExtractId, Jeprof, Kick,
-        Log, MsgDbg, Mute, Note, OcrCheck, OcrDbg, PermDbg, Ping, Purge, Reason, Ref, Restart,
-        Rules, Say, ScheduleDowntime, Softban, Stats, Sticky, Trace, Unban, Unmute, Update, Warn,
+        About, AccountAge, Ban, Cache, CacheSize, ColonThree, Command, ContextCmd,
+        CreateAttachmentRule, CreateNameRule, CreateOcrRule, CreateTextRule, DefineLog, DeleteRule,
+        Duration as DurationCommand, EditRef, Edits, Encrypt, Escalation, ExportRules, ExtractId,
+        ImportRules, Jeprof, Kick, Log, Mentions, MsgDbg, Mute, Note, OcrCheck, OcrDbg, PermDbg,
+        Ping, Purge, Raid, RaidCleanup, RaidLockdown, Reason, Ref, Restart, RuleCondition,
+        RuleCooldown, RuleDryRun, RuleExempt, RuleNormalize, RuleStats, Rules, Say,
+        ScheduleDowntime, Softban, Spam, Stats, Sticky, Trace, Unban, Unmute, Update, Warn,
     },
     constants::BRAND_RED,
     lexer::Token,
     utils::{
+        age_gate::AgeGateCache,
         cache::{message_cache::MessageCache, permission_cache::PermissionCache},
         consume_serenity_error,
+        mention_cache::MentionCache,
+        raid_cache::RaidCache,
         reference::{self, embeds_for_ref},
         rule_cache::{OcrResultCache, RuleCache},
+        spam_cache::SpamCache,
         sticky_cache::StickyCache,
     },
 };
@@ -94,6 +101,7 @@ struct MessageDeleteEvent {
 mod help_cmd;
 
 // events
+mod account_age_gate;
 mod guild_audit_log_entry_create;
 mod guild_create;
 mod guild_member_addition;
@@ -105,6 +113,7 @@ mod guild_update;
 mod message;
 mod message_delete;
 mod message_update;
+mod name_rules;
 mod shards_ready;
 mod voice_state_update;
 
@@ -117,6 +126,10 @@ pub struct Handler {
     pub rule_cache: Arc<Mutex<RuleCache>>,
     pub ocr_result_cache: Arc<Mutex<OcrResultCache>>,
     pub sticky_cache: Arc<Mutex<StickyCache>>,
+    pub spam_cache: Arc<Mutex<SpamCache>>,
+    pub mention_cache: Arc<Mutex<MentionCache>>,
+    pub raid_cache: Arc<Mutex<RaidCache>>,
+    pub age_gate_cache: Arc<Mutex<AgeGateCache>>,
 }
 
 impl Handler {
@@ -152,6 +165,9 @@ impl Handler {
             Arc::new(ScheduleDowntime::new()),
             Arc::new(OcrCheck::new()),
             Arc::new(CreateOcrRule::new()),
+            Arc::new(CreateTextRule::new()),
+            Arc::new(CreateNameRule::new()),
+            Arc::new(CreateAttachmentRule::new()),
             Arc::new(Rules::new()),
             Arc::new(DeleteRule::new()),
             Arc::new(Trace::new()),
@@ -162,6 +178,21 @@ impl Handler {
             Arc::new(Restart::new()),
             Arc::new(Encrypt::new()),
             Arc::new(Sticky::new()),
+            Arc::new(Escalation::new()),
+            Arc::new(Spam::new()),
+            Arc::new(Mentions::new()),
+            Arc::new(RuleExempt::new()),
+            Arc::new(RuleDryRun::new()),
+            Arc::new(ExportRules::new()),
+            Arc::new(ImportRules::new()),
+            Arc::new(RuleNormalize::new()),
+            Arc::new(RuleCooldown::new()),
+            Arc::new(RuleStats::new()),
+            Arc::new(Raid::new()),
+            Arc::new(RaidLockdown::new()),
+            Arc::new(AccountAge::new()),
+            Arc::new(RuleCondition::new()),
+            Arc::new(RaidCleanup::new()),
         ];
 
         let cache = Arc::new(Mutex::new(MessageCache::new()));
@@ -189,6 +220,55 @@ impl Handler {
             lock.populate_from_db().await;
         });
 
+        let spam_cache = Arc::new(Mutex::new(SpamCache::new()));
+        let populate_spam = spam_cache.clone();
+        tokio::spawn(async move {
+            {
+                let mut lock = populate_spam.lock().await;
+                lock.populate_from_db().await;
+            }
+
+            loop {
+                sleep(Duration::from_secs(600)).await;
+                populate_spam.lock().await.prune();
+            }
+        });
+
+        let mention_cache = Arc::new(Mutex::new(MentionCache::new()));
+        let populate_mention = mention_cache.clone();
+        tokio::spawn(async move {
+            {
+                let mut lock = populate_mention.lock().await;
+                lock.populate_from_db().await;
+            }
+
+            loop {
+                sleep(Duration::from_secs(600)).await;
+                populate_mention.lock().await.prune();
+            }
+        });
+
+        let raid_cache = Arc::new(Mutex::new(RaidCache::new()));
+        let populate_raid = raid_cache.clone();
+        tokio::spawn(async move {
+            {
+                let mut lock = populate_raid.lock().await;
+                lock.populate_from_db().await;
+            }
+
+            loop {
+                sleep(Duration::from_secs(600)).await;
+                populate_raid.lock().await.prune();
+            }
+        });
+
+        let age_gate_cache = Arc::new(Mutex::new(AgeGateCache::new()));
+        let populate_age_gate = age_gate_cache.clone();
+        tokio::spawn(async move {
+            let mut lock = populate_age_gate.lock().await;
+            lock.populate_from_db().await;
+        });
+
         Self {
             prefix,
             commands,
@@ -197,6 +277,10 @@ impl Handler {
             rule_cache: rule_cache,
             ocr_result_cache: Arc::new(Mutex::new(OcrResultCache::new())),
             sticky_cache,
+            spam_cache,
+            mention_cache,
+            raid_cache,
+            age_gate_cache,
         }
     }
 }
@@ -511,6 +595,10 @@ impl EventHandler for Handler {
                         .create_response(&ctx, CreateInteractionResponse::Message(msg))
                         .await;
                 }
+            } else if component.data.custom_id == "raid_ban"
+                || component.data.custom_id == "raid_dismiss"
+            {
+                crate::moderation::raid::handle_raid_button(self, &ctx, &component).await;
             } else if component.data.custom_id == "disable_encryption" {
                 if let Some(member) = &component.member
                     && let Ok(permissions) = member.permissions(&ctx)
@@ -0,0 +1,102 @@
+use serenity::all::{Context, GuildId, RoleId, User};
+
+use crate::{
+    constants::NAME_PLACEHOLDER,
+    event_handler::Handler,
+    moderation,
+    utils::rule_cache::{RuleHitSource, RuleScope, db_record_rule_hit},
+};
+
+/// Checks the username, global name and nickname of a member against the guilds name rules
+/// and applies the punishment of the first matching rule.
+/// Members already renamed to [`NAME_PLACEHOLDER`] are skipped, the nickname hides their other names.
+pub(super) async fn name_rules(
+    handler: &Handler,
+    ctx: &Context,
+    guild_id: GuildId,
+    user: &User,
+    nick: Option<&str>,
+    roles: &[RoleId],
+) {
+    if user.bot || nick == Some(NAME_PLACEHOLDER) {
+        return;
+    }
+
+    if !handler
+        .rule_cache
+        .lock()
+        .await
+        .has_name_rules(guild_id.get())
+    {
+        return;
+    }
+
+    if is_name_exempt(ctx, guild_id, user).await {
+        return;
+    }
+
+    let mut names = vec![user.name.clone()];
+    names.extend(user.global_name.clone());
+    names.extend(nick.map(str::to_string));
+    names.dedup();
+
+    let scope = RuleScope {
+        channel_id: 0,
+        user_id: user.id.get(),
+        role_ids: roles.iter().map(|r| r.get()).collect(),
+    };
+
+    let (rule, matched_name, should_punish) = {
+        let mut cache = handler.rule_cache.lock().await;
+        let Some((rule, matched_name)) = cache.matches_name(guild_id.get(), &names, &scope) else {
+            return;
+        };
+        let should_punish = cache.check_cooldown(&rule, user.id.get());
+        (rule, matched_name, should_punish)
+    };
+
+    let action_id = if should_punish {
+        let rule_note = format!(
+            "Rule `{}` Violation | {} | Name: {}",
+            rule.id,
+            rule.describe_pattern(),
+            matched_name
+        );
+
+        moderation::apply_punishment(
+            ctx,
+            guild_id,
+            user,
+            &rule.punishment,
+            "NAME RULE",
+            &rule.name,
+            rule_note,
+        )
+        .await
+    } else {
+        None
+    };
+
+    db_record_rule_hit(
+        &rule.id,
+        guild_id.get(),
+        user.id.get(),
+        None,
+        RuleHitSource::Name,
+        action_id.is_some(),
+        action_id.as_deref(),
+    )
+    .await;
+}
+
+#[allow(deprecated)]
+async fn is_name_exempt(ctx: &Context, guild_id: GuildId, user: &User) -> bool {
+    if let Ok(member) = guild_id.member(ctx, user.id).await
+        && let Ok(perms) = member.permissions(ctx)
+    {
+        return perms.contains(serenity::all::Permissions::MANAGE_NICKNAMES)
+            || perms.contains(serenity::all::Permissions::ADMINISTRATOR);
+    }
+
+    false
+}
@@ -20,7 +20,12 @@ use crate::{
     auto_once::AutoOnceLock,
     config::{Config, Environment},
     event_handler::Handler,
-    utils::{GuildSettings, consume_pgsql_error, send_error},
+    utils::{
+        GuildSettings, consume_pgsql_error, ocr,
+        ocr_pool::{OcrLimits, OcrPool},
+        ocr_text_cache::db_prune_ocr_texts,
+        send_error,
+    },
 };
 use std::process::Command as SystemCommand;
 
@@ -64,6 +69,7 @@ pub static SQL: AutoOnceLock<PgPool> = AutoOnceLock::new();
 pub static GUILD_SETTINGS: AutoOnceLock<Mutex<GuildSettings>> = AutoOnceLock::new();
 pub static BOT_CONFIG: AutoOnceLock<Environment> = AutoOnceLock::new();
 pub static ENCRYPTION_KEYS: AutoOnceLock<Mutex<HashMap<u64, [u8; 32]>>> = AutoOnceLock::new();
+pub static OCR_POOL: AutoOnceLock<OcrPool> = AutoOnceLock::new();
 
 #[tokio::main]
 async fn main() {
@@ -106,7 +112,7 @@ async fn main() {
     }
 
     let config: Config = toml::from_str(contents.as_str())
-        .unwrap_or_else(|_| panic!("Could not parse Config.toml."));
+        .unwrap_or_else(|err| panic!("Could not parse Config.toml: {err}"));
 
     let active_env = match config.bot.env.as_str() {
         "release" => &config.release,
@@ -130,6 +136,12 @@ async fn main() {
 
     BOT_CONFIG.set(active_env.clone()).unwrap();
     ENCRYPTION_KEYS.set(Mutex::new(HashMap::new())).unwrap();
+    OCR_POOL
+        .set(OcrPool::new(
+            OcrLimits::from_config(active_env),
+            ocr::engine_from_config(active_env),
+        ))
+        .unwrap();
 
     if let Err(err) = sqlx::migrate!().run(&*SQL).await {
         let dbg = format!("{err:?}");
@@ -178,6 +190,7 @@ async fn main() {
             sleep(Duration::from_secs(60 * 5)).await;
             tasks::check_expiring_bans(&http).await;
             tasks::check_expiring_timeouts(&http).await;
+            db_prune_ocr_texts().await;
         }
     });
 
@@ -0,0 +1,174 @@
+use std::{future::Future, pin::Pin};
+
+use chrono::{TimeDelta, Utc};
+use serenity::all::{Context, CreateEmbed, CreateMessage, GuildId, Mentionable, User};
+use sqlx::Row;
+use tracing::warn;
+
+use crate::{
+    SQL,
+    constants::BRAND_BLUE,
+    moderation,
+    utils::{LogType, guild_log, logging::LogContext, rule_cache::Punishment, time_string},
+};
+
+/// Punishments an escalation step may apply. Warns are excluded so a step can never trigger another step
+pub const ESCALATION_PUNISHMENTS: [&str; 4] = ["mute", "kick", "softban", "ban"];
+
+#[derive(Debug, Clone)]
+pub struct EscalationStep {
+    pub id: String,
+    pub warn_count: i32,
+    pub period: TimeDelta,
+    pub punishment_type: String,
+    pub duration: TimeDelta,
+}
+
+impl EscalationStep {
+    pub fn punishment(&self, reason: String) -> Punishment {
+        Punishment::from_parts(
+            &self.punishment_type,
+            reason,
+            0,
+            self.duration.num_seconds() as u64,
+            false,
+            0,
+        )
+    }
+
+    /// e.g. `3 warns within 30 days → mute for 1 day`
+    pub fn describe(&self) -> String {
+        let action = match self.punishment_type.as_str() {
+            "mute" | "ban" => format!("{} {}", self.punishment_type, time_string(self.duration)),
+            other => other.to_string(),
+        };
+
+        format!(
+            "{} warns within {} → {action}",
+            self.warn_count,
+            time_string(self.period).trim_start_matches("for ")
+        )
+    }
+}
+
+pub async fn get_escalation_steps(guild_id: GuildId) -> Result<Vec<EscalationStep>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT id, warn_count, period, punishment_type::text AS punishment_type, duration FROM escalation_steps WHERE guild_id = $1 ORDER BY warn_count ASC",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_all(&*SQL)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| EscalationStep {
+            id: row.get("id"),
+            warn_count: row.get("warn_count"),
+            period: TimeDelta::try_seconds(row.get::<i64, _>("period")).unwrap_or_default(),
+            punishment_type: row.get("punishment_type"),
+            duration: TimeDelta::try_seconds(row.get::<i64, _>("duration")).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Checks the guilds escalation ladder after a new warn was recorded and applies the step the user just reached.
+/// Boxed since escalation applies punishments which in turn lead back into the moderation functions.
+pub fn check_escalation<'a>(
+    ctx: &'a Context,
+    guild_id: GuildId,
+    user: &'a User,
+    trigger_id: &'a str,
+) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+    Box::pin(async move {
+        let steps = match get_escalation_steps(guild_id).await {
+            Ok(s) => s,
+            Err(err) => {
+                warn!("Could not fetch escalation steps; err = {err:?}");
+                return;
+            }
+        };
+
+        let Some(longest_period) = steps.iter().map(|s| s.period).max() else {
+            return;
+        };
+
+        let warns = sqlx::query(
+            "SELECT created_at FROM actions WHERE guild_id = $1 AND user_id = $2 AND type = 'warn' AND active = true AND created_at >= $3",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(user.id.get() as i64)
+        .bind((Utc::now() - longest_period).naive_utc())
+        .fetch_all(&*SQL)
+        .await;
+
+        let warns = match warns {
+            Ok(rows) => rows
+                .into_iter()
+                .map(|row| row.get::<chrono::NaiveDateTime, _>("created_at"))
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                warn!("Could not fetch warns for escalation; err = {err:?}");
+                return;
+            }
+        };
+
+        let now = Utc::now().naive_utc();
+
+        // only the step whose threshold was reached exactly by this warn fires, highest first
+        let Some(step) = steps.iter().rev().find(|step| {
+            let count = warns
+                .iter()
+                .filter(|created_at| now - **created_at <= step.period)
+                .count();
+            count as i32 == step.warn_count
+        }) else {
+            return;
+        };
+
+        let reason = format!(
+            "Automatic escalation: {} warns within {}",
+            step.warn_count,
+            time_string(step.period).trim_start_matches("for ")
+        );
+        let note = format!("Escalation `{}` | Triggered by `{trigger_id}`", step.id);
+
+        let Some(db_id) = moderation::apply_punishment(
+            ctx,
+            guild_id,
+            user,
+            &step.punishment(reason),
+            "ESCALATION",
+            "escalation",
+            note,
+        )
+        .await
+        else {
+            return;
+        };
+
+        let bot_id = ctx.cache.current_user().id;
+
+        guild_log(
+            ctx,
+            LogType::MemberModeration,
+            guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**ESCALATION TRIGGERED**\n-# Log ID: `{db_id}` | Target: {} | Trigger: `{trigger_id}` | Step: `{}`\n```\n{}\n```",
+                        user.mention(),
+                        step.id,
+                        step.describe()
+                    ))
+                    .color(BRAND_BLUE),
+            ),
+            Some(LogContext {
+                target_id: user.id.get(),
+                moderator_id: bot_id.get(),
+                db_id: Some(db_id),
+                content: None,
+            }),
+        )
+        .await;
+    })
+}
@@ -19,3 +19,14 @@ pub use unban::unban_user;
 
 mod warn;
 pub use warn::warn_member;
+
+mod rename;
+pub use rename::rename_member;
+
+pub mod raid;
+
+mod punishment;
+pub use punishment::apply_punishment;
+
+pub mod escalation;
+pub use escalation::check_escalation;
@@ -0,0 +1,208 @@
+use chrono::TimeDelta;
+use serenity::all::{
+    ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildId, Mentionable,
+    User,
+};
+
+use crate::{
+    moderation,
+    utils::{reference::RefData, rule_cache::Punishment, time_string, tinyid},
+};
+
+/// Applies an automod punishment to a user, with the bot as the acting member.
+/// `source` is the uppercase label used for log only punishments (e.g. `OCR RULE`).
+/// Returns the log ID of the action if one was created.
+pub async fn apply_punishment(
+    ctx: &Context,
+    guild_id: GuildId,
+    user: &User,
+    punishment: &Punishment,
+    source: &str,
+    rule_name: &str,
+    rule_note: String,
+) -> Option<String> {
+    let current_user_id = ctx.cache.current_user().id;
+    let author = guild_id.member(ctx, current_user_id).await.ok()?;
+    let member = guild_id.member(ctx, user.id).await.ok()?;
+    let db_id = tinyid().await;
+
+    let formatted_reason = punishment.reason().to_string();
+
+    let guild_name = {
+        match guild_id.to_partial_guild(&ctx).await {
+            Ok(p) => p.name.clone(),
+            Err(_) => String::from("UNKNOWN_GUILD"),
+        }
+    };
+
+    macro_rules! send_dm {
+        ($silent:expr, $title:expr) => {
+            send_dm!($silent, $title, String::new())
+        };
+        ($silent:expr, $title:expr, $duration:expr) => {
+            if !$silent {
+                let duration_text = if $duration.is_empty() {
+                    String::new()
+                } else {
+                    format!(" | Duration: {}", $duration)
+                };
+                let desc = format!(
+                    "**{}**\n-# Server: {}{}\n```\n{}\n```",
+                    $title, guild_name, duration_text, formatted_reason
+                );
+                let dm = CreateMessage::new().add_embed(
+                    CreateEmbed::new()
+                        .description(desc)
+                        .color(crate::constants::BRAND_BLUE),
+                );
+                let _ = user.direct_message(&ctx, dm).await;
+            }
+        };
+    }
+
+    let result = match *punishment {
+        Punishment::Warn { silent, .. } => {
+            send_dm!(silent, "WARNED");
+            moderation::warn_member(
+                ctx,
+                author,
+                member,
+                guild_id,
+                db_id.clone(),
+                formatted_reason.clone(),
+                Some(rule_note),
+                RefData::default(),
+            )
+            .await
+        }
+        Punishment::Softban {
+            day_clear_amount,
+            silent,
+            ..
+        } => {
+            send_dm!(silent, "SOFTBANNED");
+            moderation::softban(
+                ctx,
+                author,
+                member,
+                guild_id,
+                db_id.clone(),
+                formatted_reason.clone(),
+                Some(rule_note),
+                day_clear_amount,
+                RefData::default(),
+            )
+            .await
+        }
+        Punishment::Kick { silent, .. } => {
+            send_dm!(silent, "KICKED");
+            moderation::kick_member(
+                ctx,
+                author,
+                member,
+                guild_id,
+                db_id.clone(),
+                formatted_reason.clone(),
+                Some(rule_note),
+                RefData::default(),
+            )
+            .await
+        }
+        Punishment::Ban {
+            day_clear_amount,
+            duration,
+            silent,
+            ..
+        } => {
+            send_dm!(
+                silent,
+                "BANNED",
+                time_string(TimeDelta::try_seconds(duration as i64).unwrap_or_default())
+            );
+            moderation::ban_member(
+                ctx,
+                author,
+                member,
+                guild_id,
+                db_id.clone(),
+                formatted_reason.clone(),
+                Some(rule_note),
+                day_clear_amount,
+                TimeDelta::try_seconds(duration as i64).unwrap_or_default(),
+                RefData::default(),
+            )
+            .await
+        }
+        Punishment::Mute {
+            duration, silent, ..
+        } => {
+            send_dm!(
+                silent,
+                "MUTED",
+                time_string(TimeDelta::try_seconds(duration as i64).unwrap_or_default())
+            );
+            moderation::mute_member(
+                ctx,
+                author,
+                member,
+                guild_id,
+                db_id.clone(),
+                formatted_reason.clone(),
+                Some(rule_note),
+                TimeDelta::try_seconds(duration as i64).unwrap_or_default(),
+                RefData::default(),
+            )
+            .await
+        }
+        Punishment::Rename { silent, .. } => {
+            send_dm!(silent, "RENAMED");
+            moderation::rename_member(
+                ctx,
+                author,
+                member,
+                guild_id,
+                db_id.clone(),
+                formatted_reason.clone(),
+                Some(rule_note),
+                RefData::default(),
+            )
+            .await
+        }
+        Punishment::Log { channel_id, .. } => {
+            let reply = CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**{} TRIGGERED**\n-# Log ID: `{}` | Actor: {} | Target: {} | Rule: {}\n```\n{}\n```\n-# {}",
+                            source,
+                            db_id,
+                            author.mention(),
+                            member.mention(),
+                            rule_name.to_uppercase(),
+                            formatted_reason,
+                            rule_note
+                        ))
+                        .color(crate::constants::BRAND_BLUE),
+                )
+                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+            if let Ok(m) = ChannelId::new(channel_id).send_message(ctx, reply).await {
+                let _ = sqlx::query!(
+                    "INSERT INTO log_messages_context (message_id, guild_id, target_id, moderator_id, db_id, content) VALUES ($1, $2, $3, $4, $5, $6)",
+                    m.id.get() as i64,
+                    guild_id.get() as i64,
+                    member.user.id.get() as i64,
+                    author.user.id.get() as i64,
+                    Some(db_id.clone()),
+                    None::<Vec<u8>>
+                )
+                .execute(&*crate::SQL)
+                .await;
+            }
+
+            Ok(())
+        }
+    };
+
+    result.ok().map(|_| db_id)
+}
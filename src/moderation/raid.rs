@@ -0,0 +1,620 @@
+use chrono::TimeDelta;
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, Context, CreateActionRow, CreateAttachment,
+    CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, EditGuild, EditInteractionResponse, GuildId, Member, Mentionable,
+    PermissionOverwrite, PermissionOverwriteType, Permissions, RoleId, UserId, VerificationLevel,
+};
+use sqlx::Row;
+use tracing::{error, warn};
+
+use crate::{
+    SQL,
+    constants::{BRAND_BLUE, BRAND_RED},
+    event_handler::Handler,
+    moderation,
+    utils::{
+        LogType, can_target, consume_pgsql_error, guild_log, has_guild_permission,
+        raid_cache::{RaidLockdown, RaidSuspect},
+        reference::RefData,
+        tinyid,
+    },
+};
+
+const INVITES_DISABLED: &str = "INVITES_DISABLED";
+const LOCKED_PERMISSIONS: Permissions =
+    Permissions::SEND_MESSAGES.union(Permissions::SEND_MESSAGES_IN_THREADS);
+
+/// Applies the lockdown of a guild entering raid mode and posts the raid alert.
+/// What was changed is stored in `raid_lockdowns`, so it can be reverted through [`end_lockdown`] after a restart as well.
+pub async fn start_lockdown(
+    ctx: &Context,
+    guild_id: GuildId,
+    lockdown: &RaidLockdown,
+    trigger: &str,
+    suspects: &[RaidSuspect],
+) {
+    if let Err(err) = sqlx::query(
+        "INSERT INTO raid_lockdowns (guild_id, suspects) VALUES ($1, $2) \
+         ON CONFLICT (guild_id) DO UPDATE SET suspects = $2, previous_verification = NULL, invites_paused = false, locked_channels = '{}', created_at = now()",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(
+        suspects
+            .iter()
+            .map(|s| s.user_id as i64)
+            .collect::<Vec<_>>(),
+    )
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("RAID LOCKDOWN INSERT".into(), err);
+    }
+
+    let reason = format!("Raid detected: {trigger}");
+    let mut previous_verification = None;
+    let mut invites_paused = false;
+    let mut locked_channels = vec![];
+
+    if (lockdown.raise_verification || lockdown.pause_invites)
+        && let Ok(guild) = guild_id.to_partial_guild(ctx).await
+    {
+        let mut edit = EditGuild::new().audit_log_reason(&reason);
+
+        if lockdown.raise_verification
+            && u8::from(guild.verification_level) < u8::from(VerificationLevel::High)
+        {
+            previous_verification = Some(guild.verification_level);
+            edit = edit.verification_level(VerificationLevel::High);
+        }
+
+        if lockdown.pause_invites && !guild.features.iter().any(|f| f == INVITES_DISABLED) {
+            let mut features = guild.features.clone();
+            features.push(String::from(INVITES_DISABLED));
+            edit = edit.features(features);
+            invites_paused = true;
+        }
+
+        if (previous_verification.is_some() || invites_paused)
+            && let Err(err) = guild_id.edit(ctx, edit).await
+        {
+            warn!("Could not apply raid lockdown; guild = {guild_id}; err = {err:?}");
+            previous_verification = None;
+            invites_paused = false;
+        }
+    }
+
+    let everyone = RoleId::new(guild_id.get());
+    for channel_id in &lockdown.lock_channels {
+        let Some(channel) = ChannelId::new(*channel_id)
+            .to_channel(ctx)
+            .await
+            .ok()
+            .and_then(|c| c.guild())
+        else {
+            continue;
+        };
+
+        let existing = channel
+            .permission_overwrites
+            .iter()
+            .find(|o| matches!(o.kind, PermissionOverwriteType::Role(id) if id == everyone));
+
+        // channels which are locked already are left alone, so ending the raid does not unlock them
+        if existing.is_some_and(|o| o.deny.contains(LOCKED_PERMISSIONS)) {
+            continue;
+        }
+
+        let overwrite = PermissionOverwrite {
+            allow: existing.map_or(Permissions::empty(), |o| o.allow) - LOCKED_PERMISSIONS,
+            deny: existing.map_or(Permissions::empty(), |o| o.deny) | LOCKED_PERMISSIONS,
+            kind: PermissionOverwriteType::Role(everyone),
+        };
+
+        if channel.id.create_permission(ctx, overwrite).await.is_ok() {
+            locked_channels.push(*channel_id);
+        }
+    }
+
+    if let Err(err) = sqlx::query(
+        "UPDATE raid_lockdowns SET previous_verification = $2, invites_paused = $3, locked_channels = $4 WHERE guild_id = $1",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(previous_verification.map(|level| u8::from(level) as i16))
+    .bind(invites_paused)
+    .bind(
+        locked_channels
+            .iter()
+            .map(|id| *id as i64)
+            .collect::<Vec<_>>(),
+    )
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("RAID LOCKDOWN UPDATE".into(), err);
+    }
+
+    let applied = RaidLockdown {
+        raise_verification: previous_verification.is_some(),
+        pause_invites: invites_paused,
+        lock_channels: locked_channels,
+    };
+
+    let mut suspect_list = suspects
+        .iter()
+        .take(20)
+        .map(|s| {
+            let reasons = if s.reasons.is_empty() {
+                String::new()
+            } else {
+                format!(" | {}", s.reasons.join(", "))
+            };
+            format!(
+                "<@{}> `{}` | Created <t:{}:R>{reasons}",
+                s.user_id,
+                s.name.replace('`', ""),
+                s.created_at
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    if suspects.len() > 20 {
+        suspect_list.push_str(&format!("\n-# and {} more", suspects.len() - 20));
+    }
+
+    let alert = CreateMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**RAID DETECTED**\n-# Trigger: {trigger} | Lockdown: {}\n-# Members joining until the raid is dismissed are added to the suspects\n\n**SUSPECTS ({})**\n{suspect_list}",
+                    applied.describe(),
+                    suspects.len()
+                ))
+                .color(BRAND_RED),
+        )
+        .components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new("raid_ban")
+                .label("Ban suspects")
+                .style(ButtonStyle::Danger),
+            CreateButton::new("raid_dismiss")
+                .label("Dismiss")
+                .style(ButtonStyle::Secondary),
+        ])]);
+
+    let Some(message_id) = guild_log(ctx, LogType::MemberJoinLeave, guild_id, alert, None).await
+    else {
+        return;
+    };
+
+    // replying to the alert with a moderation command targets every suspect
+    if let Err(err) =
+        sqlx::query("INSERT INTO raid_alerts (message_id, guild_id, suspects) VALUES ($1, $2, $3)")
+            .bind(message_id.get() as i64)
+            .bind(guild_id.get() as i64)
+            .bind(
+                suspects
+                    .iter()
+                    .map(|s| s.user_id as i64)
+                    .collect::<Vec<_>>(),
+            )
+            .execute(&*SQL)
+            .await
+    {
+        consume_pgsql_error("RAID ALERT INSERT".into(), err);
+    }
+}
+
+/// Adds a member who joined during raid mode to the suspects of the raid and its latest alert
+pub async fn add_raid_suspect(guild_id: GuildId, user_id: UserId) {
+    if let Err(err) = sqlx::query(
+        "UPDATE raid_lockdowns SET suspects = array_append(suspects, $2) WHERE guild_id = $1",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(user_id.get() as i64)
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("RAID SUSPECT UPDATE".into(), err);
+    }
+
+    if let Err(err) = sqlx::query(
+        "UPDATE raid_alerts SET suspects = array_append(suspects, $2) \
+         WHERE message_id = (SELECT max(message_id) FROM raid_alerts WHERE guild_id = $1)",
+    )
+    .bind(guild_id.get() as i64)
+    .bind(user_id.get() as i64)
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("RAID ALERT SUSPECT UPDATE".into(), err);
+    }
+}
+
+/// Reverts the lockdown of a guild, returns the suspects or None if the guild was not in raid mode
+pub async fn end_lockdown(ctx: &Context, guild_id: GuildId) -> Option<Vec<u64>> {
+    let row = match sqlx::query(
+        "DELETE FROM raid_lockdowns WHERE guild_id = $1 RETURNING suspects, previous_verification, invites_paused, locked_channels",
+    )
+    .bind(guild_id.get() as i64)
+    .fetch_optional(&*SQL)
+    .await
+    {
+        Ok(row) => row?,
+        Err(err) => {
+            consume_pgsql_error("RAID LOCKDOWN DELETE".into(), err);
+            return None;
+        }
+    };
+
+    let previous_verification = row
+        .get::<Option<i16>, _>("previous_verification")
+        .map(|level| VerificationLevel::from(level as u8));
+    let invites_paused: bool = row.get("invites_paused");
+
+    if (previous_verification.is_some() || invites_paused)
+        && let Ok(guild) = guild_id.to_partial_guild(ctx).await
+    {
+        let mut edit = EditGuild::new().audit_log_reason("Raid dismissed");
+        if let Some(level) = previous_verification {
+            edit = edit.verification_level(level);
+        }
+        if invites_paused {
+            edit = edit.features(
+                guild
+                    .features
+                    .iter()
+                    .filter(|f| *f != INVITES_DISABLED)
+                    .cloned()
+                    .collect(),
+            );
+        }
+
+        if let Err(err) = guild_id.edit(ctx, edit).await {
+            warn!("Could not revert raid lockdown; guild = {guild_id}; err = {err:?}");
+        }
+    }
+
+    let everyone = RoleId::new(guild_id.get());
+    for channel_id in row.get::<Vec<i64>, _>("locked_channels") {
+        let Some(channel) = ChannelId::new(channel_id as u64)
+            .to_channel(ctx)
+            .await
+            .ok()
+            .and_then(|c| c.guild())
+        else {
+            continue;
+        };
+
+        let Some(existing) = channel
+            .permission_overwrites
+            .iter()
+            .find(|o| matches!(o.kind, PermissionOverwriteType::Role(id) if id == everyone))
+        else {
+            continue;
+        };
+
+        let overwrite = PermissionOverwrite {
+            allow: existing.allow,
+            deny: existing.deny - LOCKED_PERMISSIONS,
+            kind: existing.kind,
+        };
+        let _ = channel.id.create_permission(ctx, overwrite).await;
+    }
+
+    Some(
+        row.get::<Vec<i64>, _>("suspects")
+            .into_iter()
+            .map(|id| id as u64)
+            .collect(),
+    )
+}
+
+/// Handles the ban and dismiss buttons of a raid alert, both end raid mode
+pub async fn handle_raid_button(
+    handler: &Handler,
+    ctx: &Context,
+    component: &ComponentInteraction,
+) {
+    let Some(guild_id) = component.guild_id else {
+        return;
+    };
+    let ban = component.data.custom_id == "raid_ban";
+
+    let permission = if ban {
+        Permissions::BAN_MEMBERS
+    } else {
+        Permissions::MANAGE_GUILD
+    };
+    let permitted = match component.member.as_ref() {
+        Some(member) => has_guild_permission(ctx, member, permission).await,
+        None => false,
+    };
+
+    if !permitted {
+        let msg = CreateInteractionResponseMessage::new()
+            .content(if ban {
+                "You need the ban members permission to ban the suspects."
+            } else {
+                "You need the manage server permission to dismiss a raid."
+            })
+            .ephemeral(true);
+        let _ = component
+            .create_response(ctx, CreateInteractionResponse::Message(msg))
+            .await;
+        return;
+    }
+
+    let _ = component
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await;
+
+    handler.raid_cache.lock().await.end(guild_id.get());
+    let Some(suspects) = end_lockdown(ctx, guild_id).await else {
+        let _ = component
+            .edit_response(ctx, EditInteractionResponse::new().components(vec![]))
+            .await;
+        return;
+    };
+
+    let moderator = component.user.id;
+    let resolution = if ban {
+        let (banned, skipped) = ban_suspects(ctx, guild_id, moderator, &suspects).await;
+        let skipped = if skipped > 0 {
+            format!(" | Skipped {skipped} the moderator may not target")
+        } else {
+            String::new()
+        };
+        format!(
+            "Banned {banned}/{} suspects by <@{moderator}>{skipped}",
+            suspects.len()
+        )
+    } else {
+        format!("Dismissed by <@{moderator}>")
+    };
+
+    let description = component
+        .message
+        .embeds
+        .first()
+        .and_then(|e| e.description.clone())
+        .unwrap_or_default();
+
+    let _ = component
+        .edit_response(
+            ctx,
+            EditInteractionResponse::new()
+                .embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "{description}\n\n**RAID ENDED**\n-# {resolution} | Lockdown lifted"
+                        ))
+                        .color(BRAND_RED),
+                )
+                .components(vec![]),
+        )
+        .await;
+}
+
+/// Bans the suspects with the moderator as the actor, members above the moderator are skipped.
+/// Returns the amount of successful bans and skipped members.
+async fn ban_suspects(
+    ctx: &Context,
+    guild_id: GuildId,
+    moderator: UserId,
+    suspects: &[u64],
+) -> (usize, usize) {
+    let Ok(author) = guild_id.member(ctx, moderator).await else {
+        return (0, 0);
+    };
+
+    let mut banned = 0;
+    let mut skipped = 0;
+    for user_id in suspects {
+        let user_id = UserId::new(*user_id);
+
+        let mut target = ctx
+            .cache
+            .guild(guild_id)
+            .and_then(|g| g.members.get(&user_id).cloned());
+        if target.is_none() {
+            target = guild_id.member(ctx, user_id).await.ok();
+        }
+
+        // suspects who left already can not outrank the moderator
+        if let Some(target) = target
+            && !can_target(ctx, &author, &target, Permissions::BAN_MEMBERS).await
+        {
+            skipped += 1;
+            continue;
+        }
+
+        let Ok(user) = user_id.to_user(ctx).await else {
+            continue;
+        };
+
+        if moderation::ban_user(
+            ctx,
+            author.clone(),
+            user,
+            guild_id,
+            tinyid().await,
+            String::from("Raid"),
+            Some(String::from("Raid Detection | Banned from the raid alert")),
+            1,
+            TimeDelta::zero(),
+            RefData::default(),
+        )
+        .await
+        .is_ok()
+        {
+            banned += 1;
+        }
+    }
+
+    (banned, skipped)
+}
+
+/// How [`cleanup_raid`] removes the selected members
+#[derive(Clone, Copy)]
+pub enum CleanupAction {
+    Ban { clear_days: u8 },
+    Kick,
+}
+
+impl CleanupAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CleanupAction::Ban { .. } => "ban",
+            CleanupAction::Kick => "kick",
+        }
+    }
+
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            CleanupAction::Ban { .. } => "Banned",
+            CleanupAction::Kick => "Kicked",
+        }
+    }
+}
+
+/// The outcome of a raid cleanup, every recorded action shares the batch ID
+pub struct CleanupResult {
+    pub batch_id: String,
+    /// (target, log ID)
+    pub done: Vec<(UserId, String)>,
+    /// (target, why it failed)
+    pub failed: Vec<(UserId, &'static str)>,
+}
+
+/// Bans or kicks the selected members of a raid one after another.
+/// Every member gets their own `actions` row linked by a shared `batch_id`,
+/// but the run is summarized in a single member moderation log instead of one log per member.
+pub async fn cleanup_raid(
+    ctx: &Context,
+    author: &Member,
+    guild_id: GuildId,
+    targets: &[Member],
+    action: CleanupAction,
+    reason: &str,
+    selection: &str,
+) -> CleanupResult {
+    let batch_id = tinyid().await;
+    let mut result = CleanupResult {
+        batch_id: batch_id.clone(),
+        done: vec![],
+        failed: vec![],
+    };
+
+    for member in targets {
+        let user_id = member.user.id;
+        let db_id = tinyid().await;
+
+        if matches!(action, CleanupAction::Ban { .. })
+            && let Err(err) = sqlx::query(
+                "UPDATE actions SET active = false WHERE guild_id = $1 AND user_id = $2 AND type = 'ban'",
+            )
+            .bind(guild_id.get() as i64)
+            .bind(user_id.get() as i64)
+            .execute(&*SQL)
+            .await
+        {
+            consume_pgsql_error("RAID CLEANUP DISABLE PAST".into(), err);
+        }
+
+        if let Err(err) = sqlx::query(
+            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, note, batch_id) VALUES ($1, CAST($2 AS action_type), $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(&db_id)
+        .bind(action.name())
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .bind(author.user.id.get() as i64)
+        .bind(reason)
+        .bind("Raid cleanup")
+        .bind(&batch_id)
+        .execute(&*SQL)
+        .await
+        {
+            consume_pgsql_error("RAID CLEANUP INSERT".into(), err);
+            result.failed.push((user_id, "could not record the action"));
+            continue;
+        }
+
+        let res = match action {
+            CleanupAction::Ban { clear_days } => {
+                guild_id
+                    .ban_with_reason(ctx, user_id, clear_days, reason)
+                    .await
+            }
+            CleanupAction::Kick => guild_id.kick_with_reason(ctx, user_id, reason).await,
+        };
+
+        if let Err(err) = res {
+            warn!(
+                "Got error during raid cleanup; guild = {guild_id}; user = {user_id}; err = {err:?}"
+            );
+
+            if let Err(err) = sqlx::query("DELETE FROM actions WHERE id = $1")
+                .bind(&db_id)
+                .execute(&*SQL)
+                .await
+            {
+                error!(
+                    "Got an error during raid cleanup and an error with the database! Stray action entry in DB & manual action required; id = {db_id}; err = {err:?}"
+                );
+            }
+
+            result.failed.push((user_id, "Discord rejected the action"));
+            continue;
+        }
+
+        result.done.push((user_id, db_id));
+    }
+
+    let header = format!(
+        "**RAID CLEANUP**\n-# Batch ID: `{batch_id}` | Actor: {} | {}: {} | Failed: {}\n-# {selection}\n```\n{reason}\n```",
+        author.mention(),
+        action.past_tense(),
+        result.done.len(),
+        result.failed.len()
+    );
+    let mentions = result
+        .done
+        .iter()
+        .map(|(id, _)| format!("<@{id}>"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let mut log = CreateMessage::new();
+    if header.len() + mentions.len() > 4000 {
+        let file = result
+            .done
+            .iter()
+            .map(|(id, db_id)| format!("{id} {db_id}"))
+            .chain(
+                result
+                    .failed
+                    .iter()
+                    .map(|(id, err)| format!("{id} failed: {err}")),
+            )
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        log = log
+            .add_embed(CreateEmbed::new().description(header).color(BRAND_BLUE))
+            .add_file(CreateAttachment::bytes(
+                file.into_bytes(),
+                format!("batch-{batch_id}.txt"),
+            ));
+    } else {
+        log = log.add_embed(
+            CreateEmbed::new()
+                .description(format!("{header}\n{mentions}"))
+                .color(BRAND_BLUE),
+        );
+    }
+
+    guild_log(ctx, LogType::MemberModeration, guild_id, log, None).await;
+
+    result
+}
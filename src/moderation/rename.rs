@@ -0,0 +1,129 @@
+use serenity::all::{
+    Context, CreateEmbed, CreateMessage, EditMember, GuildId, Member, Mentionable, Permissions,
+};
+use tracing::{error, warn};
+
+use crate::{
+    SQL,
+    constants::{BRAND_BLUE, NAME_PLACEHOLDER},
+    event_handler::CommandError,
+    utils::{
+        LogType, can_target, guild_log,
+        logging::LogContext,
+        reference::{RefData, apply_ref_button},
+    },
+};
+
+/// Replaces the nickname of the member with [`NAME_PLACEHOLDER`]
+pub async fn rename_member(
+    ctx: &Context,
+    author: Member,
+    member: Member,
+    guild_id: GuildId,
+    db_id: String,
+    mut reason: String,
+    note: Option<String>,
+    ref_data: RefData,
+) -> Result<(), CommandError> {
+    let res = can_target(ctx, &author, &member, Permissions::MANAGE_NICKNAMES).await;
+
+    if !res {
+        return Err(CommandError {
+            title: String::from("You may not target this member."),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    if reason.len() > 500 {
+        reason.truncate(500);
+        reason.push_str("...");
+    }
+
+    let res = sqlx::query(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, note) VALUES ($1, 'rename', $2, $3, $4, $5, $6)",
+    )
+    .bind(db_id.as_str())
+    .bind(guild_id.get() as i64)
+    .bind(member.user.id.get() as i64)
+    .bind(author.user.id.get() as i64)
+    .bind(reason.as_str())
+    .bind(note.as_deref())
+    .execute(&*SQL)
+    .await;
+
+    if let Err(err) = res {
+        warn!("Got error while renaming; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not rename member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    let previous_name = member.display_name().to_string();
+
+    if let Err(err) = guild_id
+        .edit_member(
+            &ctx,
+            member.user.id,
+            EditMember::new()
+                .nickname(NAME_PLACEHOLDER)
+                .audit_log_reason(&reason),
+        )
+        .await
+    {
+        warn!("Got error while renaming; err = {err:?}");
+
+        if sqlx::query("DELETE FROM actions WHERE id = $1")
+            .bind(db_id.as_str())
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while renaming and an error with the database! Stray rename entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not rename member"),
+            hint: Some(String::from(
+                "check if the bot has the manage nicknames permission or try again later",
+            )),
+            arg: None,
+        });
+    }
+
+    let note_suffix = note
+        .as_deref()
+        .map(|n| format!("\n-# {n}"))
+        .unwrap_or_default();
+
+    let embed = CreateEmbed::new()
+        .description(format!(
+            "**MEMBER RENAMED**\n-# Log ID: `{db_id}` | Actor: {} | Target: {} | Previous name: {}\n```\n{reason}\n```{note_suffix}",
+            author.mention(),
+            member.mention(),
+            previous_name.replace('`', "")
+        ))
+        .color(BRAND_BLUE);
+
+    let msg = apply_ref_button(CreateMessage::new().add_embed(embed), &db_id, &ref_data);
+
+    guild_log(
+        &ctx,
+        LogType::MemberModeration,
+        guild_id,
+        msg,
+        Some(LogContext {
+            target_id: member.user.id.get(),
+            moderator_id: author.user.id.get(),
+            db_id: Some(db_id.clone()),
+            content: None,
+        }),
+    )
+    .await;
+
+    Ok(())
+}
@@ -88,5 +88,7 @@ pub async fn warn_member(
     )
     .await;
 
+    super::check_escalation(ctx, guild_id, &member.user, &db_id).await;
+
     Ok(())
 }
@@ -1,77 +1,173 @@
-// use std::{iter::Peekable, vec::IntoIter};
-
-// use serenity::all::{Context, Message, User};
-
-// use crate::{
-//     commands::{CommandArgument, TransformerReturn},
-//     lexer::Token,
-//     transformers::Transformers,
-// };
-
-// impl Transformers {
-//     pub fn many_users<'a>(
-//         ctx: &'a Context,
-//         _msg: &'a Message,
-//         args: &'a mut Peekable<IntoIter<Token>>,
-//     ) -> TransformerReturn<'a> {
-//         Box::pin(async move {
-//             let mut users: Vec<User> = vec![];
-
-//             let mut out = Token {
-//                 contents: None,
-//                 raw: String::new(),
-//                 position: 0,
-//                 length: 0,
-//                 iteration: 0,
-//                 quoted: false,
-//                 inferred: None,
-//             };
-
-//             while let Some(input) = args.peek() {
-//                 let id = if let Ok(id) = input.raw.parse::<u64>() {
-//                     id
-//                 } else if input.raw.starts_with("<@") && input.raw.ends_with(">") {
-//                     let new_input = input
-//                         .raw
-//                         .strip_prefix("<@")
-//                         .unwrap()
-//                         .strip_suffix(">")
-//                         .unwrap();
-
-//                     if let Ok(id) = new_input.parse::<u64>() {
-//                         id
-//                     } else {
-//                         break;
-//                     }
-//                 } else {
-//                     let user_cache = ctx.cache.users();
-//                     let opt_user = user_cache.iter().find(|u| u.name == input.raw);
-
-//                     if let Some(user) = opt_user {
-//                         users.push(user.clone());
-//                         args.next();
-//                         continue;
-//                     }
-
-//                     continue;
-//                 };
-
-//                 let user = {
-//                     if let Some(user) = ctx.cache.user(id) {
-//                         user.clone()
-//                     } else if let Ok(user) = ctx.http.get_user(id.into()).await {
-//                         user.clone()
-//                     } else {
-//                         continue;
-//                     }
-//                 };
-
-//                 args.next();
-//                 users.push(user);
-//             }
-
-//             out.contents = Some(CommandArgument::ManyUsers(users));
-//             Ok(out)
-//         })
-//     }
-// }
+use std::{iter::Peekable, vec::IntoIter};
+
+use serenity::all::{Context, Message, User};
+use sqlx::Row;
+
+use crate::{
+    commands::{CommandArgument, TransformerError, TransformerReturn},
+    constants::MAX_BULK_TARGETS,
+    event_handler::CommandError,
+    lexer::{InferType, Token},
+    transformers::Transformers,
+    utils::consume_pgsql_error,
+};
+
+/// The ID of a user given as a snowflake or mention, short numbers are left for the following arguments
+fn parse_user_id(raw: &str) -> Option<u64> {
+    match raw.strip_prefix("<@").and_then(|s| s.strip_suffix('>')) {
+        Some(id) => id.trim_start_matches('!').parse::<u64>().ok(),
+        None if raw.len() >= 15 => raw.parse::<u64>().ok(),
+        None => None,
+    }
+}
+
+/// The users listed in a replied to message: the suspects of a raid alert recorded in `raid_alerts`,
+/// or every ID and mention of a message the moderator wrote themselves consisting of nothing else.
+/// Any other message (e.g. mass mention spam) lists nobody, the reply then targets its author.
+async fn listed_user_ids(ctx: &Context, msg: &Message, reply: &Message) -> Vec<u64> {
+    let bot_id = ctx.cache.current_user().id.get();
+    let mut ids: Vec<u64> = vec![];
+
+    if reply.author.id.get() == bot_id {
+        match sqlx::query("SELECT suspects FROM raid_alerts WHERE message_id = $1")
+            .bind(reply.id.get() as i64)
+            .fetch_optional(&*crate::SQL)
+            .await
+        {
+            Ok(Some(row)) => ids.extend(
+                row.get::<Vec<i64>, _>("suspects")
+                    .into_iter()
+                    .map(|id| id as u64),
+            ),
+            Ok(None) => {}
+            Err(err) => consume_pgsql_error("RAID ALERT SELECT".into(), err),
+        }
+    } else if reply.author.id == msg.author.id {
+        let words = reply
+            .content
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<_>>();
+        if words.iter().all(|w| parse_user_id(w).is_some()) {
+            ids.extend(words.iter().filter_map(|w| parse_user_id(w)));
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    ids.retain(|id| *id != bot_id && *id != msg.author.id.get() && seen.insert(*id));
+    ids
+}
+
+async fn fetch_user(ctx: &Context, id: u64) -> Option<User> {
+    if let Some(user) = ctx.cache.user(id) {
+        return Some(user.clone());
+    }
+    ctx.http.get_user(id.into()).await.ok()
+}
+
+impl Transformers {
+    /// One or more users given as IDs or mentions, e.g. `123 <@456> 789`.
+    /// Replying to a raid alert or to an own message listing several users targets all of them,
+    /// any other reply targets its author the same way [`Transformers::reply_user`] does.
+    pub fn many_users<'a>(
+        ctx: &'a Context,
+        msg: &'a Message,
+        args: &'a mut Peekable<IntoIter<Token>>,
+    ) -> TransformerReturn<'a> {
+        Box::pin(async move {
+            if msg.guild_id.is_none() {
+                return Err(TransformerError::CommandError(CommandError {
+                    title: String::from("Server only command"),
+                    hint: Some(String::from("stop trying to run this in dms!")),
+                    arg: None,
+                }));
+            }
+
+            if let Some(reply) = msg.referenced_message.as_deref() {
+                let ids = listed_user_ids(ctx, msg, reply).await;
+                if ids.len() > 1 {
+                    if ids.len() > MAX_BULK_TARGETS {
+                        return Err(TransformerError::CommandError(CommandError {
+                            title: format!(
+                                "The replied to message lists {} users, at most {MAX_BULK_TARGETS} can be targeted at once",
+                                ids.len()
+                            ),
+                            hint: None,
+                            arg: None,
+                        }));
+                    }
+
+                    let mut users = vec![];
+                    for id in ids {
+                        if let Some(user) = fetch_user(ctx, id).await {
+                            users.push(user);
+                        }
+                    }
+
+                    if users.is_empty() {
+                        return Err(TransformerError::CommandError(CommandError {
+                            title: String::from("Could not find any of the listed users"),
+                            hint: None,
+                            arg: None,
+                        }));
+                    }
+
+                    let is_bot_reply = reply.author.id == ctx.cache.current_user().id;
+                    return Ok(Token {
+                        contents: Some(CommandArgument::ManyUsers(users)),
+                        inferred: is_bot_reply.then_some(InferType::Bot),
+                        ..Default::default()
+                    });
+                }
+
+                let mut token = Transformers::reply_user(ctx, msg, args).await?;
+                if let Some(CommandArgument::User(user)) = token.contents.take() {
+                    token.contents = Some(CommandArgument::ManyUsers(vec![user]));
+                }
+                return Ok(token);
+            }
+
+            let mut users: Vec<User> = vec![];
+            let mut first: Option<Token> = None;
+
+            while let Some(id) = args.peek().and_then(|input| parse_user_id(&input.raw)) {
+                let input = args.next().unwrap();
+
+                if users.len() >= MAX_BULK_TARGETS {
+                    return Err(TransformerError::CommandError(CommandError {
+                        title: format!("At most {MAX_BULK_TARGETS} users can be targeted at once"),
+                        hint: None,
+                        arg: Some(input),
+                    }));
+                }
+
+                let Some(user) = fetch_user(ctx, id).await else {
+                    return Err(TransformerError::CommandError(CommandError {
+                        arg: Some(input),
+                        title: String::from("Could not find the <Discord User>"),
+                        hint: Some(String::from(
+                            "make sure the ID or mention you provided is valid and that its associated user exists!",
+                        )),
+                    }));
+                };
+
+                if !users.iter().any(|u| u.id == user.id) {
+                    users.push(user);
+                }
+                first.get_or_insert(input);
+            }
+
+            // names and invalid input get the errors of the single user transformer
+            let Some(mut token) = first else {
+                let mut token = Transformers::user(ctx, msg, args).await?;
+                if let Some(CommandArgument::User(user)) = token.contents.take() {
+                    token.contents = Some(CommandArgument::ManyUsers(vec![user]));
+                }
+                return Ok(token);
+            };
+
+            token.contents = Some(CommandArgument::ManyUsers(users));
+            Ok(token)
+        })
+    }
+}
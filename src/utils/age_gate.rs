@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use chrono::TimeDelta;
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    utils::{consume_pgsql_error, time_string},
+};
+
+pub const DEFAULT_AGE_GATE_MESSAGE: &str = "Your account is too new to join **{server}**. \
+    Accounts have to be at least {required} old, yours is {age} old.";
+
+/// What happens to members whose account is younger than the gate allows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgeGateAction {
+    Kick,
+    /// Gives the member the quarantine role
+    Quarantine(u64),
+    /// Only logs the join
+    Flag,
+}
+
+impl AgeGateAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AgeGateAction::Kick => "kick",
+            AgeGateAction::Quarantine(_) => "quarantine",
+            AgeGateAction::Flag => "flag",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AgeGate {
+    pub min_age: TimeDelta,
+    pub action: AgeGateAction,
+    /// Custom DM, None for [`DEFAULT_AGE_GATE_MESSAGE`]
+    pub message: Option<String>,
+}
+
+impl AgeGate {
+    /// e.g. `Minimum age: 7 days | Action: quarantine <@&1>`
+    pub fn describe(&self) -> String {
+        let action = match self.action {
+            AgeGateAction::Quarantine(role_id) => format!("quarantine <@&{role_id}>"),
+            action => action.name().to_string(),
+        };
+
+        format!(
+            "Minimum age: {} | Action: {action}\n-# DM: {}",
+            age_string(self.min_age),
+            if self.message.is_some() {
+                "custom"
+            } else {
+                "default"
+            }
+        )
+    }
+
+    /// The DM sent to gated members, with `{server}`, `{age}` and `{required}` filled in
+    pub fn render_message(&self, server: &str, age: TimeDelta) -> String {
+        self.message
+            .as_deref()
+            .unwrap_or(DEFAULT_AGE_GATE_MESSAGE)
+            .replace("{server}", server)
+            .replace("{age}", &age_string(age))
+            .replace("{required}", &age_string(self.min_age))
+    }
+}
+
+/// The age gates of every guild, kept in memory as they are checked on every join
+#[derive(Default)]
+pub struct AgeGateCache {
+    gates: HashMap<u64, AgeGate>,
+}
+
+impl AgeGateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn populate_from_db(&mut self) {
+        let res = match sqlx::query(
+            "SELECT guild_id, min_age, action, quarantine_role_id, message FROM account_age_gates",
+        )
+        .fetch_all(&*SQL)
+        .await
+        {
+            Ok(d) => d,
+            Err(err) => {
+                consume_pgsql_error("POPULATE AGE GATE CACHE".into(), err);
+                return;
+            }
+        };
+
+        for record in res {
+            let action = match record.get::<String, _>("action").as_str() {
+                "kick" => AgeGateAction::Kick,
+                "quarantine" => AgeGateAction::Quarantine(
+                    record
+                        .get::<Option<i64>, _>("quarantine_role_id")
+                        .unwrap_or(0) as u64,
+                ),
+                _ => AgeGateAction::Flag,
+            };
+
+            self.gates.insert(
+                record.get::<i64, _>("guild_id") as u64,
+                AgeGate {
+                    min_age: TimeDelta::seconds(record.get("min_age")),
+                    action,
+                    message: record.get("message"),
+                },
+            );
+        }
+    }
+
+    pub fn get(&self, guild_id: u64) -> Option<AgeGate> {
+        self.gates.get(&guild_id).cloned()
+    }
+
+    pub fn set(&mut self, guild_id: u64, gate: AgeGate) {
+        self.gates.insert(guild_id, gate);
+    }
+
+    pub fn remove(&mut self, guild_id: u64) -> Option<AgeGate> {
+        self.gates.remove(&guild_id)
+    }
+}
+
+/// [`time_string`] without the leading `for`, e.g. `7 days`
+pub fn age_string(age: TimeDelta) -> String {
+    if age < TimeDelta::seconds(1) {
+        return String::from("0 seconds");
+    }
+
+    time_string(age).trim_start_matches("for ").to_string()
+}
@@ -0,0 +1,364 @@
+use serenity::all::Attachment;
+
+use crate::utils::s3::detect_content_type;
+
+/// Bytes downloaded from an attachment to detect its content type
+const SNIFF_BYTES: usize = 512;
+
+/// Shorthands usable in `mime:` conditions
+const MIME_GROUPS: [(&str, &[&str]); 2] = [
+    (
+        "executable",
+        &[
+            "application/x-msdownload",
+            "application/x-executable",
+            "application/x-mach-binary",
+            "text/x-shellscript",
+        ],
+    ),
+    (
+        "archive",
+        &[
+            "application/zip",
+            "application/vnd.rar",
+            "application/x-7z-compressed",
+            "application/gzip",
+            "application/x-bzip2",
+            "application/x-xz",
+        ],
+    ),
+];
+
+/// The conditions of an attachment rule, stored as its pattern, e.g. `ext:exe,scr mime:archive size:8mb count:5`.
+/// A message matches if any of its attachments matches one of the conditions or it has more than `count` attachments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttachmentFilter {
+    /// Lowercase file extensions without the dot
+    pub extensions: Vec<String>,
+    /// Detected content types, `image/*` matches every subtype
+    pub mime_types: Vec<String>,
+    /// Attachments larger than this many bytes match
+    pub max_size: Option<u64>,
+    /// Messages with more attachments than this match
+    pub max_count: Option<usize>,
+}
+
+impl AttachmentFilter {
+    /// Parses the conditions of an attachment rule, returns why the pattern is invalid otherwise
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let mut filter = Self::default();
+
+        for condition in pattern.split_whitespace() {
+            let Some((key, value)) = condition.split_once(':') else {
+                return Err(format!(
+                    "`{condition}` is not a condition, expected `key:value`"
+                ));
+            };
+            let values = value
+                .split(',')
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty());
+
+            match key.to_lowercase().as_str() {
+                "ext" | "extension" => filter
+                    .extensions
+                    .extend(values.map(|v| v.trim_start_matches('.').to_string())),
+                "mime" | "type" => {
+                    for mime in values {
+                        if let Some((_, group)) = MIME_GROUPS.iter().find(|(name, _)| *name == mime)
+                        {
+                            filter
+                                .mime_types
+                                .extend(group.iter().map(|m| m.to_string()));
+                        } else if mime.contains('/') {
+                            filter.mime_types.push(mime);
+                        } else {
+                            return Err(format!(
+                                "`{mime}` is not a content type, expected e.g. `video/mp4`, `image/*`, `executable` or `archive`"
+                            ));
+                        }
+                    }
+                }
+                "size" => {
+                    filter.max_size = Some(parse_size(value).ok_or_else(|| {
+                        format!("`{value}` is not a size, expected e.g. `500kb` or `8mb`")
+                    })?);
+                }
+                "count" => {
+                    filter.max_count = Some(value.parse().map_err(|_| {
+                        format!("`{value}` is not an attachment count, expected e.g. `5`")
+                    })?);
+                }
+                other => {
+                    return Err(format!(
+                        "unknown condition `{other}`, expected ext, mime, size or count"
+                    ));
+                }
+            }
+        }
+
+        filter.extensions.sort();
+        filter.extensions.dedup();
+        filter.mime_types.sort();
+        filter.mime_types.dedup();
+
+        if filter.extensions.is_empty()
+            && filter.mime_types.is_empty()
+            && filter.max_size.is_none()
+            && filter.max_count.is_none()
+        {
+            return Err(String::from("at least one condition is required"));
+        }
+
+        Ok(filter)
+    }
+
+    /// Whether the content types of the attachments have to be downloaded for [`AttachmentFilter::check`]
+    pub fn needs_content_type(&self) -> bool {
+        !self.mime_types.is_empty()
+    }
+
+    /// Checks the attachments against the conditions, returns what matched, e.g. `extension .exe (setup.exe)`.
+    /// `content_types` holds the detected content type of every attachment, it may be empty if no filter needs it.
+    pub fn check(&self, attachments: &[Attachment], content_types: &[String]) -> Option<String> {
+        if let Some(max) = self.max_count
+            && attachments.len() > max
+        {
+            return Some(format!("{} attachments (max {max})", attachments.len()));
+        }
+
+        for (i, attachment) in attachments.iter().enumerate() {
+            let name = attachment.filename.replace('`', "");
+
+            if let Some((_, ext)) = attachment.filename.rsplit_once('.')
+                && self.extensions.contains(&ext.to_lowercase())
+            {
+                return Some(format!("extension .{} ({name})", ext.to_lowercase()));
+            }
+
+            if let Some(content_type) = content_types.get(i)
+                && let Some(mime) = self
+                    .mime_types
+                    .iter()
+                    .find(|m| mime_matches(m, content_type))
+            {
+                return Some(format!("type {mime} ({name})"));
+            }
+
+            if let Some(max) = self.max_size
+                && attachment.size as u64 > max
+            {
+                return Some(format!(
+                    "size {} (max {}, {name})",
+                    format_size(attachment.size as u64),
+                    format_size(max)
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+fn mime_matches(pattern: &str, content_type: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => content_type
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind == prefix),
+        None => pattern == content_type,
+    }
+}
+
+/// Detects the content type of an attachment from its first bytes.
+/// Falls back to the content type reported by Discord if the file type is unknown or the download failed.
+pub async fn sniff_content_type(attachment: &Attachment) -> String {
+    let declared = || {
+        attachment
+            .content_type
+            .clone()
+            .map(|c| {
+                c.split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_lowercase()
+            })
+            .unwrap_or(String::from("application/octet-stream"))
+    };
+
+    let Ok(mut res) = reqwest::get(attachment.proxy_url.clone()).await else {
+        return declared();
+    };
+
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    while head.len() < SNIFF_BYTES {
+        match res.chunk().await {
+            Ok(Some(chunk)) => head.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+
+    match detect_content_type(&head) {
+        "application/octet-stream" => declared(),
+        detected => detected.to_string(),
+    }
+}
+
+/// Parses sizes like `500kb`, `8mb`, `1gb` or plain bytes
+fn parse_size(input: &str) -> Option<u64> {
+    let input = input.to_lowercase();
+    let digits_end = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(digits_end);
+    let number = number.parse::<f64>().ok()?;
+
+    let multiplier = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+
+    (number >= 0.0).then_some((number * multiplier as f64) as u64)
+}
+
+/// e.g. `8 MB`, `512 KB`
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if size.fract() == 0.0 {
+        format!("{size} {}", UNITS[unit])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(filename: &str, size: u32) -> Attachment {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "filename": filename,
+            "size": size,
+            "url": "https://cdn.discordapp.com/attachments/1/1/file",
+            "proxy_url": "https://media.discordapp.net/attachments/1/1/file",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_conditions() {
+        let filter =
+            AttachmentFilter::parse("ext:.EXE,scr,exe mime:image/*,video/mp4 size:8mb count:5")
+                .unwrap();
+        assert_eq!(filter.extensions, vec!["exe", "scr"]);
+        assert_eq!(filter.mime_types, vec!["image/*", "video/mp4"]);
+        assert_eq!(filter.max_size, Some(8 * 1024 * 1024));
+        assert_eq!(filter.max_count, Some(5));
+        assert!(filter.needs_content_type());
+    }
+
+    #[test]
+    fn expands_mime_groups() {
+        let filter = AttachmentFilter::parse("type:archive").unwrap();
+        assert!(filter.mime_types.contains(&String::from("application/zip")));
+        assert!(
+            filter
+                .mime_types
+                .contains(&String::from("application/x-7z-compressed"))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_conditions() {
+        assert!(AttachmentFilter::parse("").is_err());
+        assert!(AttachmentFilter::parse("exe").is_err());
+        assert!(AttachmentFilter::parse("ext:").is_err());
+        assert!(AttachmentFilter::parse("mime:video").is_err());
+        assert!(AttachmentFilter::parse("size:big").is_err());
+        assert!(AttachmentFilter::parse("size:5tb").is_err());
+        assert!(AttachmentFilter::parse("count:many").is_err());
+        assert!(AttachmentFilter::parse("name:setup").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("100"), Some(100));
+        assert_eq!(parse_size("500KB"), Some(500 * 1024));
+        assert_eq!(parse_size("1.5m"), Some(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_size("1gb"), Some(1024 * 1024 * 1024));
+        assert_eq!(parse_size("mb"), None);
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(8 * 1024 * 1024), "8 MB");
+        assert_eq!(format_size(1536), "1.5 KB");
+    }
+
+    #[test]
+    fn matches_mime_wildcards() {
+        assert!(mime_matches("image/*", "image/png"));
+        assert!(!mime_matches("image/*", "video/mp4"));
+        assert!(mime_matches("video/mp4", "video/mp4"));
+        assert!(!mime_matches("video/mp4", "video/webm"));
+    }
+
+    #[test]
+    fn checks_extensions_and_sizes() {
+        let filter = AttachmentFilter::parse("ext:exe size:1kb").unwrap();
+
+        assert_eq!(
+            filter.check(
+                &[attachment("cat.png", 100), attachment("Setup.EXE", 100)],
+                &[]
+            ),
+            Some(String::from("extension .exe (Setup.EXE)"))
+        );
+        assert_eq!(
+            filter.check(&[attachment("cat.png", 2048)], &[]),
+            Some(String::from("size 2 KB (max 1 KB, cat.png)"))
+        );
+        assert_eq!(filter.check(&[attachment("exe", 100)], &[]), None);
+    }
+
+    #[test]
+    fn checks_content_types_and_count() {
+        let filter = AttachmentFilter::parse("mime:executable count:2").unwrap();
+        let attachments = [attachment("cat.png", 100), attachment("cat.jpg", 100)];
+
+        assert_eq!(
+            filter.check(
+                &attachments,
+                &[
+                    String::from("image/png"),
+                    String::from("application/x-msdownload")
+                ]
+            ),
+            Some(String::from("type application/x-msdownload (cat.jpg)"))
+        );
+        assert_eq!(filter.check(&attachments, &[]), None);
+        assert_eq!(
+            filter.check(
+                &[
+                    attachment("a.png", 1),
+                    attachment("b.png", 1),
+                    attachment("c.png", 1)
+                ],
+                &[]
+            ),
+            Some(String::from("3 attachments (max 2)"))
+        );
+    }
+}
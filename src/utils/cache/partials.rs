@@ -25,6 +25,9 @@ impl PartialUser {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PartialAttachment {
+    /// 0 for attachments stored before the ID was kept
+    #[serde(default)]
+    pub id: u64,
     #[serde(alias = "filename")]
     pub name: String,
     pub url: String,
@@ -34,6 +37,18 @@ impl PartialAttachment {
     pub fn byte_footprint(&self) -> usize {
         std::mem::size_of::<Self>() + self.name.capacity() + self.url.capacity()
     }
+
+    /// The attachment ID, read from the CDN URL (`/attachments/<channel>/<attachment>/<name>`) if it was not stored
+    pub fn attachment_id(&self) -> Option<u64> {
+        if self.id != 0 {
+            return Some(self.id);
+        }
+
+        let path = self.url.split('?').next()?;
+        let mut segments = path.rsplit('/');
+        segments.next()?;
+        segments.next()?.parse().ok()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -88,6 +103,7 @@ impl From<Message> for PartialMessage {
                 .attachments
                 .into_iter()
                 .map(|a| PartialAttachment {
+                    id: a.id.get(),
                     name: a.filename,
                     url: a.url,
                 })
@@ -96,3 +96,30 @@ pub fn create_diff(a: String, b: String) -> String {
 
     final_string
 }
+
+/// Formats a duration the way moderation logs display it, e.g. `for 3 days` or `permanent`
+pub fn time_string(duration: chrono::TimeDelta) -> String {
+    if duration.is_zero() {
+        return String::from("permanent");
+    }
+
+    let (time, mut unit) = match () {
+        _ if (duration.num_days() as f64 / 365.0).fract() == 0.0 && duration.num_days() >= 365 => {
+            (duration.num_days() / 365, String::from("year"))
+        }
+        _ if (duration.num_days() as f64 / 30.0).fract() == 0.0 && duration.num_days() >= 30 => {
+            (duration.num_days() / 30, String::from("month"))
+        }
+        _ if duration.num_days() != 0 => (duration.num_days(), String::from("day")),
+        _ if duration.num_hours() != 0 => (duration.num_hours(), String::from("hour")),
+        _ if duration.num_minutes() != 0 => (duration.num_minutes(), String::from("minute")),
+        _ if duration.num_seconds() != 0 => (duration.num_seconds(), String::from("second")),
+        _ => (0, String::new()),
+    };
+
+    if time > 1 {
+        unit += "s";
+    }
+
+    format!("for {time} {unit}")
+}
@@ -0,0 +1,115 @@
+use image::{DynamicImage, imageops::FilterType};
+use sha2::{Digest, Sha256};
+
+/// Max differing bits between two perceptual hashes for the images to count as the same
+pub const PERCEPTUAL_HASH_MAX_DISTANCE: u32 = 6;
+
+/// Computes a 64 bit difference hash (dHash) of the image.
+/// The image is shrunk to 9x8 grayscale and every bit marks whether a pixel is brighter than its right neighbour,
+/// so re-encoded, resized or slightly altered copies end up with (nearly) the same hash.
+pub fn perceptual_hash(bytes: &[u8]) -> Option<u64> {
+    let image = image::load_from_memory(bytes).ok()?;
+    Some(dhash(&image))
+}
+
+/// [`perceptual_hash`] of an already decoded image
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y).0[0];
+            let right = small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+
+    hash
+}
+
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Hex encoded SHA-256 of the raw image bytes, the exact match key of `ocr_image_hashes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageFormat, RgbImage};
+
+    use super::*;
+
+    /// A diagonal gradient with a bright square, large enough that resizing keeps its shape
+    fn sample(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+            let (fx, fy) = (x * 256 / width, y * 256 / height);
+            let value = if (64..128).contains(&fx) && (96..192).contains(&fy) {
+                255
+            } else {
+                ((fx + fy) / 2) as u8
+            };
+            image::Rgb([value, value / 2, 255 - value])
+        }))
+    }
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn hamming_distance_counts_differing_bits() {
+        assert_eq!(hamming_distance(0, 0), 0);
+        assert_eq!(hamming_distance(0b1011, 0b0001), 2);
+        assert_eq!(hamming_distance(0, u64::MAX), 64);
+    }
+
+    #[test]
+    fn resized_copies_hash_nearly_the_same() {
+        let original = dhash(&sample(320, 240));
+        let resized = dhash(&sample(160, 120));
+        assert!(hamming_distance(original, resized) <= PERCEPTUAL_HASH_MAX_DISTANCE);
+    }
+
+    #[test]
+    fn reencoded_copies_hash_nearly_the_same() {
+        let image = sample(320, 240);
+        let png = perceptual_hash(&encode(&image, ImageFormat::Png)).unwrap();
+        let jpeg = perceptual_hash(&encode(&image, ImageFormat::Jpeg)).unwrap();
+        assert!(hamming_distance(png, jpeg) <= PERCEPTUAL_HASH_MAX_DISTANCE);
+    }
+
+    #[test]
+    fn different_images_hash_apart() {
+        let original = dhash(&sample(320, 240));
+        let mirrored = dhash(&sample(320, 240).fliph());
+        assert!(hamming_distance(original, mirrored) > PERCEPTUAL_HASH_MAX_DISTANCE);
+    }
+
+    #[test]
+    fn undecodable_bytes_have_no_hash() {
+        assert_eq!(perceptual_hash(b"not an image"), None);
+    }
+
+    #[test]
+    fn sha256_is_hex_encoded() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -94,16 +94,15 @@ impl LogType {
     }
 }
 
+/// Sends a log message of the given type, returns its ID if it was sent
 pub async fn guild_log(
     http: impl CacheHttp,
     log_type: LogType,
     guild: GuildId,
     msg: CreateMessage,
     context: Option<LogContext>,
-) {
-    let Some(channel) = log_type.channel_id(guild).await else {
-        return;
-    };
+) -> Option<MessageId> {
+    let channel = log_type.channel_id(guild).await?;
 
     match channel.send_message(http, msg).await {
         Ok(message) => {
@@ -144,9 +143,12 @@ pub async fn guild_log(
                     warn!("Cannot save log message context into log_messages_context; err = {err}");
                 }
             }
+
+            Some(message.id)
         }
         Err(err) => {
             warn!("Cannot not send log message; err = {err}");
+            None
         }
     }
 }
@@ -229,6 +231,7 @@ pub async fn update_guild_log(ctx: &Context, guild_id: GuildId, db_id: &str) {
         ActionType::Unban => "**MEMBER UNBANNED**",
         ActionType::Unmute => "**MEMBER UNMUTED**",
         ActionType::Log => "**MEMBER LOGGED**",
+        ActionType::Rename => "**MEMBER RENAMED**",
     };
 
     let mut header = format!(
@@ -0,0 +1,302 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    database::ActionType,
+    utils::{consume_pgsql_error, rule_cache::Punishment},
+};
+
+static MENTION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<@(!|&)?(\d+)>|@(everyone|here)").unwrap());
+
+/// The unique mentions found in a message, self mentions are not counted
+#[derive(Debug, Default, Clone)]
+pub struct Mentions {
+    pub users: HashSet<u64>,
+    pub roles: HashSet<u64>,
+    /// Whether the message attempted an `@everyone` or `@here` ping, regardless of permissions
+    pub everyone: bool,
+}
+
+impl Mentions {
+    pub fn parse(content: &str, author_id: u64) -> Self {
+        let mut mentions = Self::default();
+
+        for cap in MENTION_REGEX.captures_iter(content) {
+            if cap.get(3).is_some() {
+                mentions.everyone = true;
+                continue;
+            }
+
+            let Some(Ok(id)) = cap.get(2).map(|m| m.as_str().parse::<u64>()) else {
+                continue;
+            };
+
+            match cap.get(1).map(|m| m.as_str()) {
+                Some("&") => {
+                    mentions.roles.insert(id);
+                }
+                _ if id != author_id => {
+                    mentions.users.insert(id);
+                }
+                _ => {}
+            }
+        }
+
+        mentions
+    }
+
+    /// Unique users + roles, an everyone attempt counts as one mention
+    pub fn count(&self) -> u32 {
+        (self.users.len() + self.roles.len() + self.everyone as usize) as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count() == 0
+    }
+
+    /// Formats the mentions the way Discord renders them, e.g. `<@1>, <@&2>, @everyone`
+    pub fn describe(&self) -> String {
+        self.users
+            .iter()
+            .map(|id| format!("<@{id}>"))
+            .chain(self.roles.iter().map(|id| format!("<@&{id}>")))
+            .chain(self.everyone.then(|| String::from("@everyone")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Per guild mention thresholds, a limit of 0 disables that check
+#[derive(Debug, Clone)]
+pub struct MentionFilter {
+    /// Unique mentions in a single message that trigger the filter
+    pub message_limit: u32,
+    /// Mentions by a user across all their messages within the period that trigger the filter
+    pub window_limit: u32,
+    pub period: Duration,
+    pub punishment: Punishment,
+}
+
+impl MentionFilter {
+    pub fn describe(&self) -> String {
+        let limit = |n: u32| match n {
+            0 => String::from("off"),
+            n => n.to_string(),
+        };
+
+        format!(
+            "Per message: {} | Per period: {} | Period: {}s",
+            limit(self.message_limit),
+            limit(self.window_limit),
+            self.period.as_secs()
+        )
+    }
+}
+
+/// Tracks the recent mention counts per (guild, user) and checks them against the guilds mention filter
+#[derive(Default)]
+pub struct MentionCache {
+    filters: HashMap<u64, MentionFilter>,
+    recent: HashMap<(u64, u64), VecDeque<(Instant, u32)>>,
+}
+
+impl MentionCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn populate_from_db(&mut self) {
+        let res = match sqlx::query(
+            "SELECT guild_id, message_limit, window_limit, period, reason, punishment_type, duration, day_clear_amount, silent, log_channel_id FROM mention_filters",
+        )
+        .fetch_all(&*SQL)
+        .await
+        {
+            Ok(d) => d,
+            Err(err) => {
+                consume_pgsql_error("POPULATE MENTION CACHE".into(), err);
+                return;
+            }
+        };
+
+        for record in res {
+            let punishment_type: ActionType = record.get("punishment_type");
+            let punishment = Punishment::from_parts(
+                &punishment_type.to_string(),
+                record
+                    .get::<Option<String>, _>("reason")
+                    .unwrap_or(String::from("No reason provided")),
+                record
+                    .get::<Option<i16>, _>("day_clear_amount")
+                    .unwrap_or(0) as u8,
+                record.get::<Option<i64>, _>("duration").unwrap_or(0) as u64,
+                record.get::<Option<bool>, _>("silent").unwrap_or(false),
+                record.get::<Option<i64>, _>("log_channel_id").unwrap_or(0) as u64,
+            );
+
+            self.filters.insert(
+                record.get::<i64, _>("guild_id") as u64,
+                MentionFilter {
+                    message_limit: record.get::<i32, _>("message_limit") as u32,
+                    window_limit: record.get::<i32, _>("window_limit") as u32,
+                    period: Duration::from_secs(record.get::<i32, _>("period") as u64),
+                    punishment,
+                },
+            );
+        }
+    }
+
+    pub fn get(&self, guild_id: u64) -> Option<MentionFilter> {
+        self.filters.get(&guild_id).cloned()
+    }
+
+    pub fn has_filter(&self, guild_id: u64) -> bool {
+        self.filters.contains_key(&guild_id)
+    }
+
+    pub fn set(&mut self, guild_id: u64, filter: MentionFilter) {
+        self.filters.insert(guild_id, filter);
+    }
+
+    pub fn remove(&mut self, guild_id: u64) -> Option<MentionFilter> {
+        self.recent.retain(|(g, _), _| *g != guild_id);
+        self.filters.remove(&guild_id)
+    }
+
+    /// Records the mentions of a message, returns the filter and the total that triggered it.
+    /// The users history is cleared on a trigger.
+    pub fn track(
+        &mut self,
+        guild_id: u64,
+        user_id: u64,
+        count: u32,
+    ) -> Option<(MentionFilter, u32)> {
+        let filter = self.filters.get(&guild_id)?;
+        let now = Instant::now();
+
+        if filter.message_limit != 0 && count >= filter.message_limit {
+            self.recent.remove(&(guild_id, user_id));
+            return Some((filter.clone(), count));
+        }
+
+        if filter.window_limit == 0 || count == 0 {
+            return None;
+        }
+
+        let history = self.recent.entry((guild_id, user_id)).or_default();
+        while history
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > filter.period)
+        {
+            history.pop_front();
+        }
+        history.push_back((now, count));
+
+        let total = history.iter().map(|(_, c)| c).sum::<u32>();
+        if total < filter.window_limit {
+            return None;
+        }
+
+        let filter = filter.clone();
+        self.recent.remove(&(guild_id, user_id));
+
+        Some((filter, total))
+    }
+
+    /// Drops histories that have not seen a mention within their guilds period
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let filters = &self.filters;
+
+        self.recent.retain(|(guild_id, _), history| {
+            let Some(filter) = filters.get(guild_id) else {
+                return false;
+            };
+
+            history
+                .back()
+                .is_some_and(|(at, _)| now.duration_since(*at) <= filter.period)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(message_limit: u32, window_limit: u32, period: Duration) -> MentionCache {
+        let mut cache = MentionCache::new();
+        cache.set(
+            1,
+            MentionFilter {
+                message_limit,
+                window_limit,
+                period,
+                punishment: Punishment::Log {
+                    reason: String::new(),
+                    channel_id: 0,
+                },
+            },
+        );
+        cache
+    }
+
+    #[test]
+    fn parses_unique_mentions() {
+        let mentions = Mentions::parse("<@1> <@!1> <@2> <@&3> <@&3> <@99> @here @everyone", 99);
+        assert_eq!(mentions.users, HashSet::from([1, 2]));
+        assert_eq!(mentions.roles, HashSet::from([3]));
+        assert!(mentions.everyone);
+        assert_eq!(mentions.count(), 4);
+    }
+
+    #[test]
+    fn ignores_self_mentions_and_plain_text() {
+        let mentions = Mentions::parse("<@99> @someone <#5> <@abc>", 99);
+        assert!(mentions.is_empty());
+        assert_eq!(mentions.describe(), "");
+    }
+
+    #[test]
+    fn message_limit_triggers_immediately() {
+        let mut cache = cache(5, 0, Duration::from_secs(60));
+
+        assert!(cache.track(1, 10, 4).is_none());
+        let (_, total) = cache.track(1, 10, 5).unwrap();
+        assert_eq!(total, 5);
+        assert!(cache.track(2, 10, 50).is_none());
+    }
+
+    #[test]
+    fn window_limit_sums_messages() {
+        let mut cache = cache(0, 6, Duration::from_secs(60));
+
+        assert!(cache.track(1, 10, 3).is_none());
+        assert!(cache.track(1, 20, 3).is_none());
+        assert!(cache.track(1, 10, 0).is_none());
+        let (_, total) = cache.track(1, 10, 4).unwrap();
+        assert_eq!(total, 7);
+        assert!(cache.track(1, 10, 3).is_none());
+    }
+
+    #[test]
+    fn old_mentions_leave_the_period() {
+        let mut cache = cache(0, 4, Duration::from_millis(20));
+
+        assert!(cache.track(1, 10, 3).is_none());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.track(1, 10, 3).is_none());
+
+        std::thread::sleep(Duration::from_millis(30));
+        cache.prune();
+        assert!(cache.recent.is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 mod permissions;
 pub use permissions::can_target;
+pub use permissions::has_guild_permission;
 pub use permissions::is_developer;
 pub use permissions::permissions_for_channel;
 
@@ -28,6 +29,7 @@ pub mod command_processing;
 
 mod formatting;
 pub use formatting::create_diff;
+pub use formatting::time_string;
 
 mod guilds;
 pub use guilds::*;
@@ -40,8 +42,20 @@ pub use webhook::consume_pgsql_error;
 pub use webhook::consume_serenity_error;
 pub use webhook::send_error;
 
+pub mod age_gate;
+pub mod attachment_filter;
+pub mod image_hash;
+pub mod mention_cache;
+pub mod normalize;
 pub mod ocr;
+pub mod ocr_pool;
+pub mod ocr_preprocess;
+pub mod ocr_text_cache;
+pub mod raid_cache;
 pub mod rule_cache;
+pub mod rule_condition;
+pub mod rule_regex;
+pub mod spam_cache;
 pub mod sticky_cache;
 
 mod other;
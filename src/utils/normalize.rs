@@ -0,0 +1,418 @@
+use std::{fmt, str::FromStr};
+
+/// A single step of the text normalization applied before rule matching.
+/// Steps always run in the order of the variants, regardless of how they are configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NormalizeStep {
+    /// Removes zero-width and other invisible characters
+    Invisible,
+    /// Maps fullwidth, mathematical and enclosed letters and digits to ASCII
+    Width,
+    /// Strips combining marks (zalgo) and accents from latin letters
+    Diacritics,
+    /// Maps cyrillic and greek look-alikes to their latin counterpart
+    Confusables,
+    /// Maps common leetspeak digits and symbols to letters, e.g. `h3ll0` to `hello`
+    Leetspeak,
+}
+
+impl NormalizeStep {
+    pub const ALL: [NormalizeStep; 5] = [
+        NormalizeStep::Invisible,
+        NormalizeStep::Width,
+        NormalizeStep::Diacritics,
+        NormalizeStep::Confusables,
+        NormalizeStep::Leetspeak,
+    ];
+
+    /// Applied to new rules, leetspeak is left out since it changes every digit
+    pub const DEFAULT: [NormalizeStep; 4] = [
+        NormalizeStep::Invisible,
+        NormalizeStep::Width,
+        NormalizeStep::Diacritics,
+        NormalizeStep::Confusables,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NormalizeStep::Invisible => "invisible",
+            NormalizeStep::Width => "width",
+            NormalizeStep::Diacritics => "diacritics",
+            NormalizeStep::Confusables => "confusables",
+            NormalizeStep::Leetspeak => "leetspeak",
+        }
+    }
+
+    fn apply(&self, c: char) -> Option<char> {
+        match self {
+            NormalizeStep::Invisible => (!is_invisible(c)).then_some(c),
+            NormalizeStep::Width => Some(narrow(c)),
+            NormalizeStep::Diacritics => (!is_combining_mark(c)).then(|| strip_accent(c)),
+            NormalizeStep::Confusables => Some(confusable(c)),
+            NormalizeStep::Leetspeak => Some(leet(c)),
+        }
+    }
+}
+
+impl fmt::Display for NormalizeStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for NormalizeStep {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NormalizeStep::ALL
+            .into_iter()
+            .find(|step| step.name().eq_ignore_ascii_case(s))
+            .ok_or(())
+    }
+}
+
+/// Parses stored step names, unknown names are skipped
+pub fn parse_steps(names: &[String]) -> Vec<NormalizeStep> {
+    let mut steps = names
+        .iter()
+        .filter_map(|name| name.parse().ok())
+        .collect::<Vec<NormalizeStep>>();
+    steps.sort();
+    steps.dedup();
+    steps
+}
+
+/// e.g. `invisible, width`, or `none`
+pub fn describe_steps(steps: &[NormalizeStep]) -> String {
+    if steps.is_empty() {
+        return String::from("none");
+    }
+
+    steps
+        .iter()
+        .map(NormalizeStep::name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Runs the input through the given normalization steps
+pub fn normalize(input: &str, steps: &[NormalizeStep]) -> String {
+    if steps.is_empty() {
+        return input.to_string();
+    }
+
+    let mut steps = steps.to_vec();
+    steps.sort();
+    steps.dedup();
+
+    input
+        .chars()
+        .filter_map(|c| steps.iter().try_fold(c, |c, step| step.apply(c)))
+        .collect()
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{034F}'
+            | '\u{061C}'
+            | '\u{115F}'
+            | '\u{1160}'
+            | '\u{17B4}'
+            | '\u{17B5}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{206F}'
+            | '\u{3164}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FEFF}'
+            | '\u{FFA0}'
+            | '\u{E0000}'..='\u{E007F}'
+    )
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+fn narrow(c: char) -> char {
+    let cp = c as u32;
+
+    let mapped = match cp {
+        // fullwidth ASCII
+        0xFF01..=0xFF5E => cp - 0xFEE0,
+        0x3000 => ' ' as u32,
+        // mathematical alphanumeric letters, 52 letter blocks of A-Z a-z per style
+        0x1D400..=0x1D6A3 => {
+            let offset = (cp - 0x1D400) % 52;
+            if offset < 26 {
+                'A' as u32 + offset
+            } else {
+                'a' as u32 + offset - 26
+            }
+        }
+        // mathematical digits, 10 digit blocks per style
+        0x1D7CE..=0x1D7FF => '0' as u32 + (cp - 0x1D7CE) % 10,
+        // circled letters
+        0x24B6..=0x24CF => 'A' as u32 + cp - 0x24B6,
+        0x24D0..=0x24E9 => 'a' as u32 + cp - 0x24D0,
+        // parenthesized letters
+        0x249C..=0x24B5 => 'a' as u32 + cp - 0x249C,
+        // squared, negative circled and negative squared capital letters
+        0x1F130..=0x1F149 => 'A' as u32 + cp - 0x1F130,
+        0x1F150..=0x1F169 => 'A' as u32 + cp - 0x1F150,
+        0x1F170..=0x1F189 => 'A' as u32 + cp - 0x1F170,
+        // regional indicators
+        0x1F1E6..=0x1F1FF => 'A' as u32 + cp - 0x1F1E6,
+        _ => cp,
+    };
+
+    char::from_u32(mapped).unwrap_or(c)
+}
+
+fn strip_accent(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => 'A',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => 'C',
+        'ď' | 'đ' => 'd',
+        'Ď' | 'Đ' => 'D',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => 'E',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => 'G',
+        'ĥ' | 'ħ' => 'h',
+        'Ĥ' | 'Ħ' => 'H',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => 'I',
+        'ĵ' => 'j',
+        'Ĵ' => 'J',
+        'ķ' => 'k',
+        'Ķ' => 'K',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => 'L',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => 'N',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => 'O',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'Ŕ' | 'Ŗ' | 'Ř' => 'R',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' => 'S',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'Ţ' | 'Ť' | 'Ŧ' => 'T',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => 'U',
+        'ŵ' => 'w',
+        'Ŵ' => 'W',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'Ý' | 'Ÿ' | 'Ŷ' => 'Y',
+        'ź' | 'ż' | 'ž' => 'z',
+        'Ź' | 'Ż' | 'Ž' => 'Z',
+        other => other,
+    }
+}
+
+fn confusable(c: char) -> char {
+    match c {
+        // cyrillic
+        'а' => 'a',
+        'в' => 'b',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' | 'ё' => 'e',
+        'һ' => 'h',
+        'і' | 'ї' => 'i',
+        'ј' => 'j',
+        'к' => 'k',
+        'ӏ' => 'l',
+        'м' => 'm',
+        'п' => 'n',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'г' => 'r',
+        'ѕ' => 's',
+        'т' => 't',
+        'ц' => 'u',
+        'ѵ' => 'v',
+        'ԝ' | 'ш' => 'w',
+        'х' => 'x',
+        'у' => 'y',
+        'А' => 'A',
+        'В' => 'B',
+        'С' => 'C',
+        'Е' | 'Ё' => 'E',
+        'Н' => 'H',
+        'І' | 'Ї' | 'Ӏ' => 'I',
+        'Ј' => 'J',
+        'К' => 'K',
+        'М' => 'M',
+        'О' => 'O',
+        'Р' => 'P',
+        'Ѕ' => 'S',
+        'Т' => 'T',
+        'Х' => 'X',
+        'У' | 'Ү' => 'Y',
+        // greek
+        'α' => 'a',
+        'β' => 'b',
+        'ε' => 'e',
+        'η' => 'n',
+        'ι' => 'i',
+        'κ' => 'k',
+        'ν' => 'v',
+        'ο' => 'o',
+        'ρ' => 'p',
+        'τ' => 't',
+        'υ' => 'u',
+        'χ' => 'x',
+        'γ' => 'y',
+        'Α' => 'A',
+        'Β' => 'B',
+        'Ε' => 'E',
+        'Ζ' => 'Z',
+        'Η' => 'H',
+        'Ι' => 'I',
+        'Κ' => 'K',
+        'Μ' => 'M',
+        'Ν' => 'N',
+        'Ο' => 'O',
+        'Ρ' => 'P',
+        'Τ' => 'T',
+        'Υ' => 'Y',
+        'Χ' => 'X',
+        // latin look-alikes
+        'ɑ' => 'a',
+        'ɡ' => 'g',
+        'ɩ' => 'i',
+        'ℓ' => 'l',
+        'ʀ' => 'r',
+        'ꜱ' => 's',
+        'ᴠ' => 'v',
+        'ᴡ' => 'w',
+        'ʏ' => 'y',
+        'ᴢ' => 'z',
+        other => other,
+    }
+}
+
+fn leet(c: char) -> char {
+    match c {
+        '0' => 'o',
+        '1' | '!' | '|' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' | '+' => 't',
+        '8' => 'b',
+        '9' => 'g',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_steps_keeps_input() {
+        assert_eq!(normalize("h\u{200B}éllo", &[]), "h\u{200B}éllo");
+    }
+
+    #[test]
+    fn invisible_characters_are_removed() {
+        assert_eq!(
+            normalize("f\u{200B}r\u{FEFF}e\u{00AD}e", &[NormalizeStep::Invisible]),
+            "free"
+        );
+    }
+
+    #[test]
+    fn width_maps_to_ascii() {
+        assert_eq!(normalize("ｆｒｅｅ", &[NormalizeStep::Width]), "free");
+        assert_eq!(normalize("𝐍𝐢𝐭𝐫𝐨", &[NormalizeStep::Width]), "Nitro");
+        assert_eq!(normalize("ⓝⓘⓣⓡⓞ", &[NormalizeStep::Width]), "nitro");
+        assert_eq!(normalize("𝟙𝟘𝟘", &[NormalizeStep::Width]), "100");
+    }
+
+    #[test]
+    fn diacritics_are_stripped() {
+        assert_eq!(
+            normalize("frée nïtrø", &[NormalizeStep::Diacritics]),
+            "free nitro"
+        );
+        assert_eq!(
+            normalize("n\u{0301}\u{0337}itro", &[NormalizeStep::Diacritics]),
+            "nitro"
+        );
+    }
+
+    #[test]
+    fn confusables_map_to_latin() {
+        // cyrillic and greek look-alikes
+        assert_eq!(
+            normalize(
+                "fr\u{0435}\u{0435} nitr\u{043E}",
+                &[NormalizeStep::Confusables]
+            ),
+            "free nitro"
+        );
+        assert_eq!(
+            normalize(
+                "\u{039D}\u{0399}\u{03A4}\u{03A1}\u{039F}",
+                &[NormalizeStep::Confusables]
+            ),
+            "NITPO"
+        );
+    }
+
+    #[test]
+    fn leetspeak_maps_digits() {
+        assert_eq!(
+            normalize("fr33 n1tr0", &[NormalizeStep::Leetspeak]),
+            "free nitro"
+        );
+        assert_eq!(normalize("fr33", &NormalizeStep::DEFAULT), "fr33");
+    }
+
+    #[test]
+    fn steps_run_in_variant_order() {
+        // a fullwidth zero only becomes a digit leetspeak knows if width runs first
+        let steps = [NormalizeStep::Leetspeak, NormalizeStep::Width];
+        assert_eq!(normalize("０", &steps), "o");
+        assert_eq!(
+            normalize("\u{200B}ｆｒé\u{0301}\u{0435}", &NormalizeStep::ALL),
+            "free"
+        );
+    }
+
+    #[test]
+    fn parse_steps_skips_unknown_and_sorts() {
+        let names = ["Leetspeak", "width", "unknown", "width"].map(String::from);
+        assert_eq!(
+            parse_steps(&names),
+            vec![NormalizeStep::Width, NormalizeStep::Leetspeak]
+        );
+    }
+
+    #[test]
+    fn describe_steps_lists_names() {
+        assert_eq!(describe_steps(&[]), "none");
+        assert_eq!(
+            describe_steps(&[NormalizeStep::Invisible, NormalizeStep::Width]),
+            "invisible, width"
+        );
+    }
+}
@@ -1,31 +1,252 @@
-use kreuzberg::{OcrConfig, PaddleOcrBackend, plugins::OcrBackend};
-use std::sync::LazyLock;
+#[cfg(feature = "paddle")]
+use std::{collections::HashSet, sync::OnceLock};
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+#[cfg(feature = "paddle")]
+use kreuzberg::{OcrConfig, PaddleOcrBackend, plugins::OcrBackend as _};
+use serenity::{
+    all::{Attachment, Embed, StickerItem},
+    async_trait,
+};
 use thiserror::Error;
+use tracing::info;
+
+use crate::config::{Environment, OcrBackend};
 
 #[derive(Error, Debug)]
 pub enum OcrError {
     #[error("Extraction failed: {0}")]
     ExtractionError(String),
+    #[error("The OCR queue is full")]
+    QueueFull,
+    #[error("The image is too large")]
+    ImageTooLarge,
+}
+
+/// Turns image bytes into text, picked with `ocr_backend` in `Config.toml`
+#[async_trait]
+pub trait OcrEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn extract_text(&self, bytes: &[u8]) -> Result<String, OcrError>;
+}
+
+/// The images of a message to run through OCR: attachments, embed images and thumbnails (e.g. posted image links)
+/// and stickers. Animated Lottie stickers have no image and are skipped.
+pub fn image_urls(
+    attachments: &[Attachment],
+    embeds: &[Embed],
+    stickers: &[StickerItem],
+) -> Vec<String> {
+    let mut urls = attachments
+        .iter()
+        .map(|a| a.proxy_url.clone())
+        .collect::<Vec<_>>();
+
+    for embed in embeds {
+        let images = [
+            embed
+                .image
+                .as_ref()
+                .map(|i| i.proxy_url.clone().unwrap_or(i.url.clone())),
+            embed
+                .thumbnail
+                .as_ref()
+                .map(|t| t.proxy_url.clone().unwrap_or(t.url.clone())),
+        ];
+        urls.extend(images.into_iter().flatten());
+    }
+
+    urls.extend(stickers.iter().filter_map(StickerItem::image_url));
+    urls.dedup();
+    urls
+}
+
+/// Creates the engine configured with `ocr_backend`: `paddle` (default, needs the `paddle` feature), `tesseract` or `none`.
+/// `ocr_training_data` is either a language (e.g. `eng+deu` for Tesseract, `en` for PaddleOCR)
+/// or the path of a Tesseract `.traineddata` file, `ocr_character_whitelist` limits the recognized characters.
+pub fn engine_from_config(env: &Environment) -> Box<dyn OcrEngine> {
+    let whitelist = env
+        .ocr_character_whitelist
+        .clone()
+        .filter(|w| !w.is_empty());
+
+    let engine: Box<dyn OcrEngine> = match env.ocr_backend.unwrap_or_default() {
+        #[cfg(feature = "paddle")]
+        OcrBackend::Paddle => Box::new(PaddleEngine::new(env.ocr_training_data.clone(), whitelist)),
+        OcrBackend::Tesseract => Box::new(TesseractEngine::new(
+            env.ocr_training_data.as_deref(),
+            whitelist,
+        )),
+        OcrBackend::None => Box::new(NoopEngine),
+    };
+
+    info!("Using the {} OCR backend", engine.name());
+    engine
+}
+
+/// PaddleOCR through kreuzberg, the models are loaded on the first image
+#[cfg(feature = "paddle")]
+pub struct PaddleEngine {
+    backend: OnceLock<Result<PaddleOcrBackend, String>>,
+    config: OcrConfig,
+    /// PaddleOCR has no whitelist option, other characters are removed from its output
+    whitelist: Option<HashSet<char>>,
+}
+
+#[cfg(feature = "paddle")]
+impl PaddleEngine {
+    pub fn new(language: Option<String>, whitelist: Option<String>) -> Self {
+        let mut config = OcrConfig::default();
+        config.auto_rotate = true;
+        if let Some(language) = language {
+            config.language = language;
+        }
+
+        Self {
+            backend: OnceLock::new(),
+            config,
+            whitelist: whitelist.map(|w| w.chars().collect()),
+        }
+    }
+}
+
+#[cfg(feature = "paddle")]
+#[async_trait]
+impl OcrEngine for PaddleEngine {
+    fn name(&self) -> &'static str {
+        "paddle"
+    }
+
+    async fn extract_text(&self, bytes: &[u8]) -> Result<String, OcrError> {
+        let backend = self
+            .backend
+            .get_or_init(|| PaddleOcrBackend::new().map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| OcrError::ExtractionError(e.clone()))?;
+
+        let result = backend
+            .process_image(bytes, &self.config)
+            .await
+            .map_err(|e| OcrError::ExtractionError(e.to_string()))?;
+
+        let text = match &self.whitelist {
+            Some(whitelist) => result
+                .content
+                .chars()
+                .filter(|c| c.is_whitespace() || whitelist.contains(c))
+                .collect(),
+            None => result.content,
+        };
+
+        Ok(clean_text(&text))
+    }
 }
 
-static BACKEND: LazyLock<Result<PaddleOcrBackend, String>> =
-    LazyLock::new(|| PaddleOcrBackend::new().map_err(|e| e.to_string()));
+/// Runs the `tesseract` binary, which has to be installed and on the PATH
+pub struct TesseractEngine {
+    language: String,
+    /// Directory holding the `.traineddata` file, the Tesseract default if None
+    data_dir: Option<String>,
+    whitelist: Option<String>,
+}
+
+impl TesseractEngine {
+    pub fn new(training_data: Option<&str>, whitelist: Option<String>) -> Self {
+        let (language, data_dir) = match training_data {
+            Some(data) if data.ends_with(".traineddata") => {
+                let path = Path::new(data);
+                (
+                    path.file_stem()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or(String::from("eng")),
+                    path.parent()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .filter(|p| !p.is_empty()),
+                )
+            }
+            Some(language) => (language.to_string(), None),
+            None => (String::from("eng"), None),
+        };
+
+        Self {
+            language,
+            data_dir,
+            whitelist,
+        }
+    }
+
+    fn run(&self, bytes: &[u8]) -> Result<String, OcrError> {
+        let mut command = Command::new("tesseract");
+        command.args(["stdin", "stdout", "-l", &self.language]);
+        if let Some(dir) = &self.data_dir {
+            command.arg("--tessdata-dir").arg(dir);
+        }
+        if let Some(whitelist) = &self.whitelist {
+            command
+                .arg("-c")
+                .arg(format!("tessedit_char_whitelist={whitelist}"));
+        }
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| OcrError::ExtractionError(format!("Could not run tesseract: {e}")))?;
 
-static CONFIG: LazyLock<OcrConfig> = LazyLock::new(|| {
-    let mut config = OcrConfig::default();
-    config.auto_rotate = true;
-    config
-});
+        // tesseract reads the whole image before writing anything, so stdout can't fill up here
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(bytes)
+                .map_err(|e| OcrError::ExtractionError(e.to_string()))?;
+        }
 
-pub async fn extract_text_from_bytes(bytes: &[u8]) -> Result<String, OcrError> {
-    let backend = BACKEND
-        .as_ref()
-        .map_err(|e| OcrError::ExtractionError(e.clone()))?;
+        let output = child
+            .wait_with_output()
+            .map_err(|e| OcrError::ExtractionError(e.to_string()))?;
 
-    let result = backend
-        .process_image(bytes, &CONFIG)
-        .await
-        .map_err(|e| OcrError::ExtractionError(e.to_string()))?;
+        if !output.status.success() {
+            return Err(OcrError::ExtractionError(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        Ok(clean_text(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+#[async_trait]
+impl OcrEngine for TesseractEngine {
+    fn name(&self) -> &'static str {
+        "tesseract"
+    }
+
+    async fn extract_text(&self, bytes: &[u8]) -> Result<String, OcrError> {
+        // waiting on the process blocks, so the worker thread is handed off for its duration
+        tokio::task::block_in_place(|| self.run(bytes))
+    }
+}
+
+/// Disables OCR, images are still matched by their hashes
+pub struct NoopEngine;
+
+#[async_trait]
+impl OcrEngine for NoopEngine {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    async fn extract_text(&self, _bytes: &[u8]) -> Result<String, OcrError> {
+        Ok(String::new())
+    }
+}
 
-    Ok(result.content.replace("\n\n", " "))
+/// Joins the paragraphs of the recognized text into one line
+fn clean_text(text: &str) -> String {
+    text.trim().replace("\n\n", " ")
 }
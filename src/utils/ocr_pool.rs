@@ -0,0 +1,414 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{Mutex, Notify, oneshot};
+use tracing::warn;
+
+use crate::{
+    config::Environment,
+    utils::{
+        image_hash::sha256_hex,
+        ocr::{OcrEngine, OcrError},
+        ocr_preprocess::{Preprocessed, preprocess},
+        ocr_text_cache::{CachedText, OcrTextCache, db_get_ocr_text, db_store_ocr_text},
+    },
+};
+
+pub const DEFAULT_OCR_QUEUE_SIZE: usize = 64;
+pub const DEFAULT_OCR_MAX_IMAGE_SIZE: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_OCR_MAX_PIXELS: u64 = 12_000_000;
+
+/// Jobs whose wait and processing times make up the latency shown in `stats`
+const LATENCY_SAMPLES: usize = 100;
+
+/// Queued jobs of a higher priority are always processed first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OcrPriority {
+    /// Bulk work like rule dry runs and edited messages
+    Low = 0,
+    /// Images of new messages
+    Normal = 1,
+    /// Commands a moderator is waiting on
+    High = 2,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OcrLimits {
+    /// Images processed at the same time
+    pub workers: usize,
+    /// Images waiting for a worker, further images are rejected
+    pub queue_size: usize,
+    /// Larger images are rejected
+    pub max_image_size: u64,
+    /// Larger images (or parts of split images) are downscaled to this many pixels before OCR
+    pub max_pixels: u64,
+}
+
+impl OcrLimits {
+    /// Reads the limits from the config, one worker per two cores by default
+    pub fn from_config(env: &Environment) -> Self {
+        let cores = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(2);
+
+        Self {
+            workers: env.ocr_workers.unwrap_or(cores / 2).max(1),
+            queue_size: env.ocr_queue_size.unwrap_or(DEFAULT_OCR_QUEUE_SIZE).max(1),
+            max_image_size: env.ocr_max_image_size.unwrap_or(DEFAULT_OCR_MAX_IMAGE_SIZE),
+            max_pixels: env.ocr_max_pixels.unwrap_or(DEFAULT_OCR_MAX_PIXELS),
+        }
+    }
+}
+
+struct OcrJob {
+    guild_id: u64,
+    bytes: Vec<u8>,
+    queued_at: Instant,
+    result: oneshot::Sender<Result<OcrOutput, OcrError>>,
+}
+
+/// The text of an image and the preprocessing steps applied before OCR
+#[derive(Debug, Clone)]
+pub struct OcrOutput {
+    pub text: String,
+    pub steps: Vec<String>,
+    /// Whether the text was extracted earlier, possibly in another guild
+    pub cached: bool,
+}
+
+/// The pending jobs of one priority, served round-robin per guild so a single guild can't starve the others
+#[derive(Default)]
+struct GuildQueues {
+    guilds: VecDeque<(u64, VecDeque<OcrJob>)>,
+}
+
+impl GuildQueues {
+    fn push(&mut self, job: OcrJob) {
+        match self.guilds.iter_mut().find(|(id, _)| *id == job.guild_id) {
+            Some((_, jobs)) => jobs.push_back(job),
+            None => self.guilds.push_back((job.guild_id, VecDeque::from([job]))),
+        }
+    }
+
+    fn pop(&mut self) -> Option<OcrJob> {
+        let (guild_id, mut jobs) = self.guilds.pop_front()?;
+        let job = jobs.pop_front();
+        if !jobs.is_empty() {
+            self.guilds.push_back((guild_id, jobs));
+        }
+
+        job
+    }
+
+    fn queued(&self, guild_id: u64) -> usize {
+        self.guilds
+            .iter()
+            .find(|(id, _)| *id == guild_id)
+            .map_or(0, |(_, jobs)| jobs.len())
+    }
+
+    /// The guild with the most queued jobs and their count
+    fn largest(&self) -> Option<(u64, usize)> {
+        self.guilds
+            .iter()
+            .map(|(id, jobs)| (*id, jobs.len()))
+            .max_by_key(|(_, len)| *len)
+    }
+
+    /// Removes the newest job of the guild
+    fn evict(&mut self, guild_id: u64) -> Option<OcrJob> {
+        let i = self.guilds.iter().position(|(id, _)| *id == guild_id)?;
+        let job = self.guilds[i].1.pop_back();
+        if self.guilds[i].1.is_empty() {
+            self.guilds.remove(i);
+        }
+
+        job
+    }
+}
+
+#[derive(Default)]
+struct OcrQueue {
+    /// Indexed by [`OcrPriority`]
+    levels: [GuildQueues; 3],
+    len: usize,
+}
+
+impl OcrQueue {
+    fn pop(&mut self) -> Option<OcrJob> {
+        let job = self.levels.iter_mut().rev().find_map(GuildQueues::pop)?;
+        self.len -= 1;
+        Some(job)
+    }
+
+    /// Makes room for a job of the guild when the queue is full.
+    /// The newest job of the guild with the most queued jobs is dropped if it has a lower priority,
+    /// or the same priority and more queued jobs than the guild of the new job.
+    fn make_room(&mut self, guild_id: u64, priority: OcrPriority) -> Option<OcrJob> {
+        let level = self.levels.iter().position(|l| !l.guilds.is_empty())?;
+        if level > priority as usize {
+            return None;
+        }
+
+        let (largest, count) = self.levels[level].largest()?;
+        if level == priority as usize && count <= self.levels[level].queued(guild_id) + 1 {
+            return None;
+        }
+
+        let job = self.levels[level].evict(largest)?;
+        self.len -= 1;
+        Some(job)
+    }
+}
+
+#[derive(Default)]
+struct OcrCounters {
+    active: usize,
+    completed: u64,
+    rejected: u64,
+    cache_hits: u64,
+    /// (time spent queued, time spent processing) of the latest jobs
+    latency: VecDeque<(Duration, Duration)>,
+}
+
+/// A snapshot of the pool for `stats`
+#[derive(Debug, Clone)]
+pub struct OcrPoolStats {
+    pub workers: usize,
+    pub active: usize,
+    pub queued: usize,
+    pub queue_size: usize,
+    pub completed: u64,
+    pub rejected: u64,
+    pub cache_hits: u64,
+    pub cached_texts: usize,
+    pub avg_wait: Duration,
+    pub avg_processing: Duration,
+}
+
+struct PoolInner {
+    limits: OcrLimits,
+    engine: Box<dyn OcrEngine>,
+    queue: Mutex<OcrQueue>,
+    counters: Mutex<OcrCounters>,
+    text_cache: Mutex<OcrTextCache>,
+    notify: Notify,
+}
+
+/// Runs OCR on a fixed number of workers so an image raid queues up instead of pinning every core.
+/// Jobs beyond the queue size are rejected with [`OcrError::QueueFull`].
+/// Extracted texts are cached by image hash across guilds, so a known image never reaches a worker again.
+pub struct OcrPool {
+    inner: Arc<PoolInner>,
+}
+
+impl std::fmt::Debug for OcrPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OcrPool")
+            .field("engine", &self.inner.engine.name())
+            .field("limits", &self.inner.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OcrPool {
+    /// Creates the pool and spawns its workers, has to be called inside the runtime
+    pub fn new(limits: OcrLimits, engine: Box<dyn OcrEngine>) -> Self {
+        let inner = Arc::new(PoolInner {
+            limits,
+            engine,
+            queue: Mutex::new(OcrQueue::default()),
+            counters: Mutex::new(OcrCounters::default()),
+            text_cache: Mutex::new(OcrTextCache::new()),
+            notify: Notify::new(),
+        });
+
+        for _ in 0..limits.workers {
+            let inner = inner.clone();
+            tokio::spawn(async move { worker(inner).await });
+        }
+
+        Self { inner }
+    }
+
+    /// Returns the cached text of the image, or queues it and waits for its text
+    pub async fn extract(
+        &self,
+        guild_id: u64,
+        priority: OcrPriority,
+        bytes: Vec<u8>,
+    ) -> Result<OcrOutput, OcrError> {
+        if bytes.len() as u64 > self.inner.limits.max_image_size {
+            self.inner.counters.lock().await.rejected += 1;
+            return Err(OcrError::ImageTooLarge);
+        }
+
+        let image_hash = sha256_hex(&bytes);
+        if let Some(output) = self.cached_text(&image_hash).await {
+            return Ok(output);
+        }
+
+        let (tx, rx) = oneshot::channel();
+        let job = OcrJob {
+            guild_id,
+            bytes,
+            queued_at: Instant::now(),
+            result: tx,
+        };
+
+        {
+            let mut queue = self.inner.queue.lock().await;
+            if queue.len >= self.inner.limits.queue_size {
+                let Some(evicted) = queue.make_room(guild_id, priority) else {
+                    drop(queue);
+                    self.inner.counters.lock().await.rejected += 1;
+                    return Err(OcrError::QueueFull);
+                };
+
+                let _ = evicted.result.send(Err(OcrError::QueueFull));
+                self.inner.counters.lock().await.rejected += 1;
+            }
+
+            queue.levels[priority as usize].push(job);
+            queue.len += 1;
+        }
+        self.inner.notify.notify_one();
+
+        let output = rx.await.unwrap_or(Err(OcrError::QueueFull))?;
+
+        // empty text may only mean the engine is disabled or failed, a real backend has to see the image again
+        if output.text.trim().is_empty() || self.inner.engine.name() == "none" {
+            return Ok(output);
+        }
+
+        let cached = CachedText {
+            text: output.text.clone(),
+            steps: output.steps.clone(),
+        };
+        db_store_ocr_text(&image_hash, &cached).await;
+        self.inner
+            .text_cache
+            .lock()
+            .await
+            .insert(image_hash, cached);
+
+        Ok(output)
+    }
+
+    async fn cached_text(&self, image_hash: &str) -> Option<OcrOutput> {
+        let in_memory = self.inner.text_cache.lock().await.get(image_hash).cloned();
+        let cached = match in_memory {
+            Some(cached) => cached,
+            None => {
+                let cached = db_get_ocr_text(image_hash).await?;
+                self.inner
+                    .text_cache
+                    .lock()
+                    .await
+                    .insert(image_hash.to_string(), cached.clone());
+                cached
+            }
+        };
+
+        self.inner.counters.lock().await.cache_hits += 1;
+        Some(OcrOutput {
+            text: cached.text,
+            steps: cached.steps,
+            cached: true,
+        })
+    }
+
+    pub async fn stats(&self) -> OcrPoolStats {
+        let queued = self.inner.queue.lock().await.len;
+        let cached_texts = self.inner.text_cache.lock().await.len();
+        let counters = self.inner.counters.lock().await;
+
+        let samples = counters.latency.len().max(1) as u32;
+        let (wait, processing) = counters
+            .latency
+            .iter()
+            .fold((Duration::ZERO, Duration::ZERO), |acc, (w, p)| {
+                (acc.0 + *w, acc.1 + *p)
+            });
+
+        OcrPoolStats {
+            workers: self.inner.limits.workers,
+            active: counters.active,
+            queued,
+            queue_size: self.inner.limits.queue_size,
+            completed: counters.completed,
+            rejected: counters.rejected,
+            cache_hits: counters.cache_hits,
+            cached_texts,
+            avg_wait: wait / samples,
+            avg_processing: processing / samples,
+        }
+    }
+}
+
+async fn worker(inner: Arc<PoolInner>) {
+    loop {
+        let job = loop {
+            if let Some(job) = inner.queue.lock().await.pop() {
+                break job;
+            }
+            inner.notify.notified().await;
+        };
+
+        // the message was handled some other way in the meantime
+        if job.result.is_closed() {
+            continue;
+        }
+
+        let waited = job.queued_at.elapsed();
+        inner.counters.lock().await.active += 1;
+        let started = Instant::now();
+
+        let max_pixels = inner.limits.max_pixels;
+        let bytes = job.bytes;
+        let result = match tokio::task::spawn_blocking(move || preprocess(bytes, max_pixels)).await
+        {
+            Ok(Ok(prepared)) => recognize(&*inner.engine, prepared).await,
+            Ok(Err(err)) => Err(err),
+            Err(err) => {
+                warn!(
+                    "OCR preprocessing panicked; guild = {}; err = {err:?}",
+                    job.guild_id
+                );
+                Err(OcrError::ExtractionError(err.to_string()))
+            }
+        };
+
+        {
+            let mut counters = inner.counters.lock().await;
+            counters.active -= 1;
+            counters.completed += 1;
+            if counters.latency.len() >= LATENCY_SAMPLES {
+                counters.latency.pop_front();
+            }
+            counters.latency.push_back((waited, started.elapsed()));
+        }
+
+        let _ = job.result.send(result);
+    }
+}
+
+/// Runs OCR on every prepared image, joining the distinct texts in order
+async fn recognize(engine: &dyn OcrEngine, prepared: Preprocessed) -> Result<OcrOutput, OcrError> {
+    let mut texts: Vec<String> = vec![];
+    for image in &prepared.images {
+        let text = engine.extract_text(image).await?;
+        if !text.is_empty() && !texts.contains(&text) {
+            texts.push(text);
+        }
+    }
+
+    Ok(OcrOutput {
+        text: texts.join(" "),
+        steps: prepared.steps,
+        cached: false,
+    })
+}
@@ -0,0 +1,241 @@
+use std::io::Cursor;
+
+use image::{
+    AnimationDecoder, DynamicImage, GrayImage, ImageFormat, ImageReader,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    imageops::{self, FilterType},
+};
+
+use crate::utils::{
+    image_hash::{PERCEPTUAL_HASH_MAX_DISTANCE, dhash, hamming_distance},
+    ocr::OcrError,
+};
+
+/// Distinct frames taken from an animation, frames similar to an already taken one are skipped
+const MAX_FRAMES: usize = 4;
+/// Frames of an animation that are looked at, later frames are ignored
+const MAX_SCANNED_FRAMES: usize = 120;
+/// Images whose shorter side is below this many pixels are upscaled
+const UPSCALE_BELOW: u32 = 600;
+const MAX_UPSCALE: u32 = 3;
+/// Images taller than this many times their width are split into parts
+const SPLIT_RATIO: u32 = 3;
+const MAX_PARTS: u32 = 6;
+/// Rows shared by neighbouring parts so a line on the cut is not lost
+const PART_OVERLAP: u32 = 48;
+/// Images whose luminance spans less than this are stretched to the full range
+const LOW_CONTRAST_RANGE: u8 = 160;
+/// Images above this many times the pixel budget are rejected before decoding, as every frame is decoded at full size
+const MAX_BUDGET_FACTOR: u64 = 2;
+
+/// An image prepared for OCR
+pub struct Preprocessed {
+    /// PNG encoded images in reading order: the frames of animations and the parts of split images
+    pub images: Vec<Vec<u8>>,
+    /// The applied steps, e.g. `grayscale` or `upscaled 2x`
+    pub steps: Vec<String>,
+}
+
+/// Prepares an image for OCR: samples distinct frames of animated GIF, APNG and WebP images,
+/// converts to grayscale, normalizes low contrast, splits tall screenshots, then upscales small
+/// and downscales images above the pixel budget. Formats the `image` crate can't read are passed through untouched.
+pub fn preprocess(bytes: Vec<u8>, max_pixels: u64) -> Result<Preprocessed, OcrError> {
+    let reader = ImageReader::new(Cursor::new(&bytes))
+        .with_guessed_format()
+        .ok();
+    let format = reader.as_ref().and_then(ImageReader::format);
+    let dimensions = reader.and_then(|reader| reader.into_dimensions().ok());
+
+    // unknown formats are left to the OCR backend
+    let (Some(format), Some((width, height))) = (format, dimensions) else {
+        return Ok(Preprocessed {
+            images: vec![bytes],
+            steps: vec![],
+        });
+    };
+
+    if width as u64 * height as u64 > max_pixels.saturating_mul(MAX_BUDGET_FACTOR) {
+        return Err(OcrError::ImageTooLarge);
+    }
+
+    let mut steps = vec![];
+    let mut frames = sample_frames(&bytes, format);
+    if frames.is_empty() {
+        frames.push(
+            image::load_from_memory_with_format(&bytes, format)
+                .map_err(|e| OcrError::ExtractionError(e.to_string()))?,
+        );
+    } else {
+        steps.push(format!("sampled {} distinct frames", frames.len()));
+    }
+    steps.push(String::from("grayscale"));
+
+    let mut images = vec![];
+    for frame in frames {
+        let mut gray = frame.to_luma8();
+        if normalize_contrast(&mut gray) {
+            push_step(&mut steps, String::from("contrast normalized"));
+        }
+
+        let parts = split_tall(gray);
+        if parts.len() > 1 {
+            push_step(&mut steps, format!("split into {} parts", parts.len()));
+        }
+
+        for part in parts {
+            let part = fit_size(part, max_pixels, &mut steps);
+
+            let mut out = Vec::new();
+            part.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+                .map_err(|e| OcrError::ExtractionError(e.to_string()))?;
+            images.push(out);
+        }
+    }
+
+    Ok(Preprocessed { images, steps })
+}
+
+fn push_step(steps: &mut Vec<String>, step: String) {
+    if !steps.contains(&step) {
+        steps.push(step);
+    }
+}
+
+/// The distinct frames of an animated image, empty if the image is not animated
+fn sample_frames(bytes: &[u8], format: ImageFormat) -> Vec<DynamicImage> {
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(bytes)).map(|d| d.into_frames()),
+        ImageFormat::Png => match PngDecoder::new(Cursor::new(bytes)) {
+            Ok(decoder) if decoder.is_apng().unwrap_or(false) => {
+                decoder.apng().map(|d| d.into_frames())
+            }
+            _ => return vec![],
+        },
+        ImageFormat::WebP => match WebPDecoder::new(Cursor::new(bytes)) {
+            Ok(decoder) if decoder.has_animation() => Ok(decoder.into_frames()),
+            _ => return vec![],
+        },
+        _ => return vec![],
+    };
+
+    let Ok(frames) = frames else {
+        return vec![];
+    };
+
+    let mut sampled: Vec<(u64, DynamicImage)> = vec![];
+    for frame in frames.take(MAX_SCANNED_FRAMES) {
+        let Ok(frame) = frame else {
+            break;
+        };
+
+        let image = DynamicImage::ImageRgba8(frame.into_buffer());
+        let hash = dhash(&image);
+        if sampled
+            .iter()
+            .any(|(h, _)| hamming_distance(*h, hash) <= PERCEPTUAL_HASH_MAX_DISTANCE)
+        {
+            continue;
+        }
+
+        sampled.push((hash, image));
+        if sampled.len() >= MAX_FRAMES {
+            break;
+        }
+    }
+
+    sampled.into_iter().map(|(_, image)| image).collect()
+}
+
+/// Stretches the luminance of low contrast images to the full range, ignoring the darkest and brightest 1%.
+/// Returns whether the image was changed.
+fn normalize_contrast(image: &mut GrayImage) -> bool {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+
+    let cutoff = image.width() as u64 * image.height() as u64 / 100;
+    let low = percentile(&histogram, cutoff, 0..256);
+    let high = percentile(&histogram, cutoff, (0..256).rev());
+
+    // solid images have nothing to stretch
+    if high <= low || high - low >= LOW_CONTRAST_RANGE {
+        return false;
+    }
+
+    let range = (high - low) as f32;
+    for pixel in image.pixels_mut() {
+        let value = pixel.0[0].clamp(low, high) - low;
+        pixel.0[0] = (value as f32 * 255.0 / range) as u8;
+    }
+
+    true
+}
+
+/// The first luminance level, walking the levels in the given order, past `cutoff` pixels
+fn percentile(histogram: &[u64; 256], cutoff: u64, mut levels: impl Iterator<Item = usize>) -> u8 {
+    let mut seen = 0;
+    levels
+        .find(|&level| {
+            seen += histogram[level];
+            seen > cutoff
+        })
+        .unwrap_or(0) as u8
+}
+
+/// Splits images taller than [`SPLIT_RATIO`] times their width into overlapping parts
+fn split_tall(image: GrayImage) -> Vec<GrayImage> {
+    let (width, height) = image.dimensions();
+    if height <= width.saturating_mul(SPLIT_RATIO) {
+        return vec![image];
+    }
+
+    let part_height = (width * 2).max(height.div_ceil(MAX_PARTS));
+    let parts = height.div_ceil(part_height);
+
+    (0..parts)
+        .map(|i| {
+            let y = (i * part_height).saturating_sub(PART_OVERLAP);
+            let bottom = ((i + 1) * part_height).min(height);
+            imageops::crop_imm(&image, 0, y, width, bottom - y).to_image()
+        })
+        .collect()
+}
+
+/// Downscales images above the pixel budget and upscales small ones, as far as the budget allows
+fn fit_size(image: GrayImage, max_pixels: u64, steps: &mut Vec<String>) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let pixels = width as u64 * height as u64;
+
+    if pixels > max_pixels {
+        push_step(steps, String::from("downscaled to the pixel budget"));
+        let scale = (max_pixels as f64 / pixels as f64).sqrt();
+        return imageops::resize(
+            &image,
+            ((width as f64 * scale) as u32).max(1),
+            ((height as f64 * scale) as u32).max(1),
+            FilterType::Triangle,
+        );
+    }
+
+    let shorter = width.min(height).max(1);
+    if shorter >= UPSCALE_BELOW {
+        return image;
+    }
+
+    let mut factor = UPSCALE_BELOW.div_ceil(shorter).min(MAX_UPSCALE);
+    while factor > 1 && pixels * (factor * factor) as u64 > max_pixels {
+        factor -= 1;
+    }
+    if factor <= 1 {
+        return image;
+    }
+
+    push_step(steps, format!("upscaled {factor}x"));
+    imageops::resize(
+        &image,
+        width * factor,
+        height * factor,
+        FilterType::CatmullRom,
+    )
+}
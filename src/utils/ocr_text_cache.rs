@@ -0,0 +1,108 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use sqlx::Row;
+
+use crate::{SQL, utils::consume_pgsql_error};
+
+const OCR_TEXT_CACHE_MAX: usize = 5000;
+/// How long extracted texts are reused, in memory and in `ocr_text_cache`
+pub const OCR_TEXT_TTL: Duration = Duration::from_secs(60 * 60 * 24 * 7);
+
+/// Text extracted from an image earlier, shared by every guild
+#[derive(Debug, Clone)]
+pub struct CachedText {
+    pub text: String,
+    pub steps: Vec<String>,
+}
+
+/// Bounded FIFO of image hash → extracted text, in front of `ocr_text_cache`
+pub struct OcrTextCache {
+    entries: HashMap<String, (CachedText, Instant)>,
+    order: VecDeque<String>,
+}
+
+impl OcrTextCache {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&self, image_hash: &str) -> Option<&CachedText> {
+        self.entries
+            .get(image_hash)
+            .filter(|(_, stored_at)| stored_at.elapsed() < OCR_TEXT_TTL)
+            .map(|(text, _)| text)
+    }
+
+    pub fn insert(&mut self, image_hash: String, text: CachedText) {
+        if !self.entries.contains_key(&image_hash) {
+            if self.entries.len() >= OCR_TEXT_CACHE_MAX
+                && let Some(old) = self.order.pop_front()
+            {
+                self.entries.remove(&old);
+            }
+            self.order.push_back(image_hash.clone());
+        }
+
+        self.entries.insert(image_hash, (text, Instant::now()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+pub async fn db_get_ocr_text(image_hash: &str) -> Option<CachedText> {
+    let result = sqlx::query(
+        "SELECT text, steps FROM ocr_text_cache \
+         WHERE image_hash = $1 AND created_at > NOW() - make_interval(secs => $2)",
+    )
+    .bind(image_hash)
+    .bind(OCR_TEXT_TTL.as_secs() as f64)
+    .fetch_optional(&*SQL)
+    .await;
+
+    match result {
+        Ok(row) => row.map(|row| CachedText {
+            text: row.get("text"),
+            steps: row.get("steps"),
+        }),
+        Err(err) => {
+            consume_pgsql_error("OCR TEXT CACHE FETCH".into(), err);
+            None
+        }
+    }
+}
+
+pub async fn db_store_ocr_text(image_hash: &str, text: &CachedText) {
+    if let Err(err) = sqlx::query(
+        "INSERT INTO ocr_text_cache (image_hash, text, steps) VALUES ($1, $2, $3) \
+         ON CONFLICT (image_hash) DO UPDATE SET text = $2, steps = $3, created_at = NOW()",
+    )
+    .bind(image_hash)
+    .bind(&text.text)
+    .bind(&text.steps)
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("OCR TEXT CACHE STORE".into(), err);
+    }
+}
+
+/// Deletes texts older than [`OCR_TEXT_TTL`]
+pub async fn db_prune_ocr_texts() {
+    if let Err(err) = sqlx::query(
+        "DELETE FROM ocr_text_cache WHERE created_at < NOW() - make_interval(secs => $1)",
+    )
+    .bind(OCR_TEXT_TTL.as_secs() as f64)
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("OCR TEXT CACHE PRUNE".into(), err);
+    }
+}
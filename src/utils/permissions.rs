@@ -22,6 +22,23 @@ pub fn check_guild_permission(
     permissions.contains(Permissions::ADMINISTRATOR) || permissions.contains(permission)
 }
 
+/// Same as [`check_guild_permission`], the guild is taken from the cache or fetched if it is not cached.
+pub async fn has_guild_permission(ctx: &Context, member: &Member, permission: Permissions) -> bool {
+    let guild = ctx
+        .cache
+        .guild(member.guild_id)
+        .map(|g| (g.owner_id, g.roles.clone()));
+    let (owner_id, roles) = match guild {
+        Some(guild) => guild,
+        None => match member.guild_id.to_partial_guild(ctx).await {
+            Ok(partial) => (partial.owner_id, partial.roles),
+            Err(_) => return false,
+        },
+    };
+
+    check_guild_permission(member.guild_id, owner_id, &roles, member, permission)
+}
+
 /// Checks if a member has a permission in a guilds channel. Respects channel overrides.
 pub fn check_channel_permission(
     guild_id: GuildId,
@@ -0,0 +1,356 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use chrono::{TimeDelta, Utc};
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    utils::{
+        consume_pgsql_error,
+        normalize::{NormalizeStep, normalize},
+        time_string,
+    },
+};
+
+/// What is changed in a guild while it is in raid mode
+#[derive(Debug, Clone, Default)]
+pub struct RaidLockdown {
+    /// Raises the verification level to high
+    pub raise_verification: bool,
+    pub pause_invites: bool,
+    /// Channels @everyone can not send messages in during the raid
+    pub lock_channels: Vec<u64>,
+}
+
+impl RaidLockdown {
+    /// e.g. `raise verification, pause invites, lock <#1>`, or `nothing`
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if self.raise_verification {
+            parts.push(String::from("raise verification"));
+        }
+        if self.pause_invites {
+            parts.push(String::from("pause invites"));
+        }
+        if !self.lock_channels.is_empty() {
+            parts.push(format!(
+                "lock {}",
+                self.lock_channels
+                    .iter()
+                    .map(|id| format!("<#{id}>"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if parts.is_empty() {
+            String::from("nothing")
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Per guild raid thresholds, a limit of 0 disables that check
+#[derive(Debug, Clone)]
+pub struct RaidFilter {
+    /// Joins within the period that trigger raid mode
+    pub join_limit: u32,
+    /// Suspicious joins within the period that trigger raid mode.
+    /// A join is suspicious if the account is new or shares its name or avatar with another recent join.
+    pub suspicious_limit: u32,
+    pub period: Duration,
+    /// Accounts younger than this are suspicious, zero to disable
+    pub min_account_age: Duration,
+    pub lockdown: RaidLockdown,
+}
+
+impl RaidFilter {
+    pub fn describe(&self) -> String {
+        let limit = |n: u32| match n {
+            0 => String::from("off"),
+            n => n.to_string(),
+        };
+        let age = if self.min_account_age.is_zero() {
+            String::from("off")
+        } else {
+            time_string(TimeDelta::from_std(self.min_account_age).unwrap_or_default())
+                .trim_start_matches("for ")
+                .to_string()
+        };
+
+        format!(
+            "Joins: {} | Suspicious joins: {} | Period: {}s | Minimum account age: {age}\n-# Lockdown: {}",
+            limit(self.join_limit),
+            limit(self.suspicious_limit),
+            self.period.as_secs(),
+            self.lockdown.describe()
+        )
+    }
+}
+
+/// A member who joined recently
+#[derive(Debug, Clone)]
+pub struct RecentJoin {
+    pub user_id: u64,
+    /// Unix timestamp of the account creation
+    pub created_at: i64,
+    pub name: String,
+    pub avatar: Option<String>,
+    at: Instant,
+}
+
+impl RecentJoin {
+    pub fn new(user_id: u64, created_at: i64, name: String, avatar: Option<String>) -> Self {
+        Self {
+            user_id,
+            created_at,
+            name,
+            avatar,
+            at: Instant::now(),
+        }
+    }
+}
+
+/// A member of a raid burst and why they are considered suspicious, empty if they only joined during the burst
+#[derive(Debug, Clone)]
+pub struct RaidSuspect {
+    pub user_id: u64,
+    pub created_at: i64,
+    pub name: String,
+    pub reasons: Vec<&'static str>,
+}
+
+pub enum RaidCheck {
+    Clear,
+    /// The guild entered raid mode
+    Triggered {
+        filter: RaidFilter,
+        trigger: String,
+        suspects: Vec<RaidSuspect>,
+    },
+    /// The guild already is in raid mode, every join is a suspect
+    Ongoing,
+}
+
+/// Tracks recent joins per guild and checks them against the guilds raid filter
+#[derive(Default)]
+pub struct RaidCache {
+    filters: HashMap<u64, RaidFilter>,
+    recent: HashMap<u64, VecDeque<RecentJoin>>,
+    /// Guilds currently in raid mode
+    active: HashSet<u64>,
+}
+
+impl RaidCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn populate_from_db(&mut self) {
+        let res = match sqlx::query(
+            "SELECT guild_id, join_limit, suspicious_limit, period, min_account_age, raise_verification, pause_invites, lock_channels FROM raid_filters",
+        )
+        .fetch_all(&*SQL)
+        .await
+        {
+            Ok(d) => d,
+            Err(err) => {
+                consume_pgsql_error("POPULATE RAID CACHE".into(), err);
+                return;
+            }
+        };
+
+        for record in res {
+            self.filters.insert(
+                record.get::<i64, _>("guild_id") as u64,
+                RaidFilter {
+                    join_limit: record.get::<i32, _>("join_limit") as u32,
+                    suspicious_limit: record.get::<i32, _>("suspicious_limit") as u32,
+                    period: Duration::from_secs(record.get::<i32, _>("period") as u64),
+                    min_account_age: Duration::from_secs(
+                        record.get::<i64, _>("min_account_age") as u64
+                    ),
+                    lockdown: RaidLockdown {
+                        raise_verification: record.get("raise_verification"),
+                        pause_invites: record.get("pause_invites"),
+                        lock_channels: record
+                            .get::<Vec<i64>, _>("lock_channels")
+                            .into_iter()
+                            .map(|id| id as u64)
+                            .collect(),
+                    },
+                },
+            );
+        }
+
+        match sqlx::query("SELECT guild_id FROM raid_lockdowns")
+            .fetch_all(&*SQL)
+            .await
+        {
+            Ok(rows) => {
+                self.active.extend(
+                    rows.into_iter()
+                        .map(|row| row.get::<i64, _>("guild_id") as u64),
+                );
+            }
+            Err(err) => consume_pgsql_error("POPULATE RAID LOCKDOWNS".into(), err),
+        }
+    }
+
+    pub fn get(&self, guild_id: u64) -> Option<RaidFilter> {
+        self.filters.get(&guild_id).cloned()
+    }
+
+    pub fn set(&mut self, guild_id: u64, filter: RaidFilter) {
+        self.filters.insert(guild_id, filter);
+    }
+
+    pub fn remove(&mut self, guild_id: u64) -> Option<RaidFilter> {
+        self.recent.remove(&guild_id);
+        self.filters.remove(&guild_id)
+    }
+
+    pub fn is_active(&self, guild_id: u64) -> bool {
+        self.active.contains(&guild_id)
+    }
+
+    /// Leaves raid mode, returns false if the guild was not in raid mode
+    pub fn end(&mut self, guild_id: u64) -> bool {
+        self.active.remove(&guild_id)
+    }
+
+    /// Records a join and checks the guilds recent joins against its filter.
+    /// On a trigger the guild enters raid mode and its join history is cleared.
+    pub fn track(&mut self, guild_id: u64, join: RecentJoin) -> RaidCheck {
+        if self.active.contains(&guild_id) {
+            return RaidCheck::Ongoing;
+        }
+
+        let Some(filter) = self.filters.get(&guild_id) else {
+            return RaidCheck::Clear;
+        };
+
+        let history = self.recent.entry(guild_id).or_default();
+        while history
+            .front()
+            .is_some_and(|j| join.at.duration_since(j.at) > filter.period)
+        {
+            history.pop_front();
+        }
+        history.push_back(join);
+
+        let suspects = suspects(history, filter.min_account_age);
+        let suspicious = suspects.iter().filter(|s| !s.reasons.is_empty()).count() as u32;
+
+        let (trigger, suspects) =
+            if filter.join_limit != 0 && history.len() as u32 >= filter.join_limit {
+                (
+                    format!(
+                        "{} joins within {}s",
+                        history.len(),
+                        filter.period.as_secs()
+                    ),
+                    suspects,
+                )
+            } else if filter.suspicious_limit != 0 && suspicious >= filter.suspicious_limit {
+                (
+                    format!(
+                        "{suspicious} suspicious joins within {}s",
+                        filter.period.as_secs()
+                    ),
+                    suspects
+                        .into_iter()
+                        .filter(|s| !s.reasons.is_empty())
+                        .collect(),
+                )
+            } else {
+                return RaidCheck::Clear;
+            };
+
+        let filter = filter.clone();
+        self.recent.remove(&guild_id);
+        self.active.insert(guild_id);
+
+        RaidCheck::Triggered {
+            filter,
+            trigger,
+            suspects,
+        }
+    }
+
+    /// Drops joins older than their guilds period
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let filters = &self.filters;
+
+        self.recent.retain(|guild_id, history| {
+            let Some(filter) = filters.get(guild_id) else {
+                return false;
+            };
+
+            history.retain(|j| now.duration_since(j.at) <= filter.period);
+            !history.is_empty()
+        });
+    }
+}
+
+/// Letters of the name without confusables, leetspeak, digits and symbols, e.g. `Fr33 N1tro_22` to `freenitro`
+fn name_skeleton(name: &str) -> String {
+    normalize(name, &NormalizeStep::ALL)
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn suspects(joins: &VecDeque<RecentJoin>, min_account_age: Duration) -> Vec<RaidSuspect> {
+    let now = Utc::now().timestamp();
+    let skeletons = joins
+        .iter()
+        .map(|j| name_skeleton(&j.name))
+        .collect::<Vec<_>>();
+
+    joins
+        .iter()
+        .enumerate()
+        .map(|(i, join)| {
+            let mut reasons = vec![];
+
+            if !min_account_age.is_zero()
+                && now - join.created_at < min_account_age.as_secs() as i64
+            {
+                reasons.push("new account");
+            }
+
+            let skeleton = &skeletons[i];
+            if skeleton.chars().count() >= 3
+                && skeletons
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| i != j && other == skeleton)
+            {
+                reasons.push("similar name");
+            }
+
+            if join.avatar.is_some()
+                && joins
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| i != j && other.avatar == join.avatar)
+            {
+                reasons.push("same avatar");
+            }
+
+            RaidSuspect {
+                user_id: join.user_id,
+                created_at: join.created_at,
+                name: join.name.clone(),
+                reasons,
+            }
+        })
+        .collect()
+}
@@ -1,9 +1,22 @@
 use std::collections::{HashMap, VecDeque};
 use std::time::{Duration, Instant};
 
+use chrono::TimeDelta;
 use regex::Regex;
+use serenity::all::Message;
 
-use crate::{SQL, database::ActionType, utils::consume_pgsql_error};
+use crate::{
+    SQL,
+    database::ActionType,
+    utils::{
+        consume_pgsql_error,
+        image_hash::{PERCEPTUAL_HASH_MAX_DISTANCE, hamming_distance},
+        normalize::{NormalizeStep, normalize, parse_steps},
+        rule_condition::RuleCondition,
+        rule_regex::build_rule_regex,
+        time_string,
+    },
+};
 
 const IMAGE_HASH_CACHE_MAX: usize = 10000;
 const OCR_RESULT_CACHE_MAX: usize = 1000;
@@ -13,7 +26,14 @@ const OCR_RESULT_CACHE_MAX: usize = 1000;
 pub struct OcrDebugEntry {
     /// The raw OCR text extracted from the image.
     pub text: String,
-    /// If the text matched a rule: (rule name, rule id, matched pattern).
+    /// The text after normalization, with the steps of the matched rule or the default steps.
+    /// None if it is identical to the raw text or no OCR ran.
+    pub normalized: Option<String>,
+    /// The preprocessing steps applied before OCR, empty if no OCR ran.
+    pub preprocessing: Vec<String>,
+    /// Whether the text came from the OCR text cache instead of a fresh OCR run.
+    pub cached_text: bool,
+    /// If the text matched a rule: (rule name, rule id, matched pattern or condition).
     pub matched: Option<(String, String, String)>,
 }
 
@@ -48,11 +68,11 @@ impl OcrResultCache {
     }
 }
 
-
-
 pub struct ImageHashCache {
     entries: HashMap<(u64, String), Option<String>>,
     order: VecDeque<(u64, String)>,
+    /// (guild_id, perceptual hash, rule_id) of images which matched a rule, oldest first
+    perceptual: VecDeque<(u64, u64, String)>,
 }
 
 impl ImageHashCache {
@@ -60,9 +80,37 @@ impl ImageHashCache {
         Self {
             entries: HashMap::new(),
             order: VecDeque::new(),
+            perceptual: VecDeque::new(),
         }
     }
 
+    /// Finds the rule of the closest known matching image within [`PERCEPTUAL_HASH_MAX_DISTANCE`]
+    pub fn get_similar(&self, guild_id: u64, perceptual_hash: u64) -> Option<&str> {
+        self.perceptual
+            .iter()
+            .filter(|(g, _, _)| *g == guild_id)
+            .map(|(_, hash, rule_id)| (hamming_distance(*hash, perceptual_hash), rule_id))
+            .filter(|(distance, _)| *distance <= PERCEPTUAL_HASH_MAX_DISTANCE)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, rule_id)| rule_id.as_str())
+    }
+
+    pub fn insert_perceptual(&mut self, guild_id: u64, perceptual_hash: u64, rule_id: String) {
+        if self
+            .perceptual
+            .iter()
+            .any(|(g, hash, _)| *g == guild_id && *hash == perceptual_hash)
+        {
+            return;
+        }
+
+        if self.perceptual.len() >= IMAGE_HASH_CACHE_MAX {
+            self.perceptual.pop_front();
+        }
+        self.perceptual
+            .push_back((guild_id, perceptual_hash, rule_id));
+    }
+
     pub fn get(&self, guild_id: u64, image_hash: &str) -> Option<&Option<String>> {
         self.entries.get(&(guild_id, image_hash.to_string()))
     }
@@ -93,6 +141,7 @@ impl ImageHashCache {
         }
 
         self.order.retain(|k| self.entries.contains_key(k));
+        self.perceptual.retain(|(_, _, id)| id != rule_id);
     }
 
     fn byte_footprint(&self) -> usize {
@@ -105,12 +154,24 @@ impl ImageHashCache {
                 .map(|(k, v)| k.1.capacity() + v.as_ref().map(|s| s.capacity()).unwrap_or(0))
                 .sum::<usize>()
             + self.order.capacity() * std::mem::size_of::<(u64, String)>()
+            + self.perceptual.capacity() * std::mem::size_of::<(u64, u64, String)>()
+            + self
+                .perceptual
+                .iter()
+                .map(|(_, _, id)| id.capacity())
+                .sum::<usize>()
     }
 }
 
 pub struct RuleCache {
     ocr: Vec<Rule>,
-    recent_triggers: HashMap<(String, u64), Instant>,
+    text: Vec<Rule>,
+    /// Matched against usernames, global names and nicknames
+    name: Vec<Rule>,
+    /// Matched against the attachments of messages, their pattern holds an [`AttachmentFilter`](crate::utils::attachment_filter::AttachmentFilter)
+    attachment: Vec<Rule>,
+    /// Punishment times per (rule id, user id)
+    recent_triggers: HashMap<(String, u64), VecDeque<Instant>>,
     pub image_hash_cache: ImageHashCache,
 }
 
@@ -118,54 +179,224 @@ impl RuleCache {
     pub fn new() -> Self {
         Self {
             ocr: Vec::new(),
+            text: Vec::new(),
+            name: Vec::new(),
+            attachment: Vec::new(),
             recent_triggers: HashMap::new(),
             image_hash_cache: ImageHashCache::new(),
         }
     }
 
-    pub fn check_debounce(&mut self, rule_id: String, user_id: u64) -> bool {
-        let key = (rule_id, user_id);
-        if let Some(last_triggered) = self.recent_triggers.get(&key) {
-            if last_triggered.elapsed() < Duration::from_secs(15) {
+    /// Records a punishment of the user by the rule if the rules cooldown allows it.
+    /// Returns false while the debounce window is running or the user reached the rules punishment limit.
+    pub fn check_cooldown(&mut self, rule: &Rule, user_id: u64) -> bool {
+        let now = Instant::now();
+        let cooldown = &rule.cooldown;
+        let history = self
+            .recent_triggers
+            .entry((rule.id.clone(), user_id))
+            .or_default();
+
+        if history
+            .back()
+            .is_some_and(|at| now.duration_since(*at) < cooldown.debounce)
+        {
+            return false;
+        }
+
+        if cooldown.max_punishments != 0 {
+            while history
+                .front()
+                .is_some_and(|at| now.duration_since(*at) > cooldown.period)
+            {
+                history.pop_front();
+            }
+
+            if history.len() as u32 >= cooldown.max_punishments {
                 return false;
             }
+        } else {
+            history.clear();
         }
 
+        history.push_back(now);
+
         if self.recent_triggers.len() > 1000 {
-            self.recent_triggers
-                .retain(|_, v| v.elapsed() < Duration::from_secs(15));
+            let rules = self
+                .ocr
+                .iter()
+                .chain(self.text.iter())
+                .chain(self.name.iter())
+                .chain(self.attachment.iter());
+            let windows = rules
+                .map(|r| (r.id.as_str(), r.cooldown.window()))
+                .collect::<HashMap<_, _>>();
+
+            self.recent_triggers.retain(|(rule_id, _), history| {
+                let window = windows.get(rule_id.as_str()).copied().unwrap_or_default();
+                history
+                    .back()
+                    .is_some_and(|at| now.duration_since(*at) < window)
+            });
         }
 
-        self.recent_triggers.insert(key, Instant::now());
         true
     }
 
-    pub fn insert_ocr(&mut self, rule: Rule) {
-        self.ocr.push(rule);
+    /// Inserts a rule into the list matching its `automod_rules.type` value
+    pub fn insert(&mut self, kind: &str, mut rule: Rule) {
+        rule.compile();
+        match kind {
+            "ocr" => self.ocr.push(rule),
+            "text" => self.text.push(rule),
+            "name" => self.name.push(rule),
+            "attachment" => self.attachment.push(rule),
+            _ => {}
+        };
     }
 
     pub fn remove(&mut self, id: &str) {
         self.ocr.retain(|r| r.id != id);
+        self.text.retain(|r| r.id != id);
+        self.name.retain(|r| r.id != id);
+        self.attachment.retain(|r| r.id != id);
         self.image_hash_cache.invalidate_rule(id);
     }
 
     pub fn get_by_id(&self, id: &str) -> Option<&Rule> {
-        self.ocr.iter().find(|r| r.id == id)
+        self.ocr
+            .iter()
+            .chain(self.text.iter())
+            .chain(self.name.iter())
+            .chain(self.attachment.iter())
+            .find(|r| r.id == id)
     }
 
     pub fn has_ocr_rules(&self, guild_id: u64) -> bool {
         self.ocr.iter().any(|r| r.guild_id == guild_id)
     }
 
-    pub fn matches(&self, guild_id: u64, input: String) -> Option<Rule> {
+    /// Whether an OCR rule of the guild does not apply in the scope, matches of it are not visible to [`RuleCache::matches`]
+    pub fn has_exempt_ocr_rules(&self, guild_id: u64, scope: &RuleScope) -> bool {
+        self.ocr
+            .iter()
+            .any(|r| r.guild_id == guild_id && r.exemptions.exempts(scope))
+    }
+
+    pub fn has_text_rules(&self, guild_id: u64) -> bool {
+        self.text.iter().any(|r| r.guild_id == guild_id)
+    }
+
+    pub fn has_name_rules(&self, guild_id: u64) -> bool {
+        self.name.iter().any(|r| r.guild_id == guild_id)
+    }
+
+    pub fn has_attachment_rules(&self, guild_id: u64) -> bool {
+        self.attachment.iter().any(|r| r.guild_id == guild_id)
+    }
+
+    /// The attachment rules of the guild which apply in the scope, matching needs the attachments themselves
+    pub fn attachment_rules(&self, guild_id: u64, scope: &RuleScope) -> Vec<Rule> {
+        self.attachment
+            .iter()
+            .filter(|r| r.guild_id == guild_id && !r.exemptions.exempts(scope))
+            .cloned()
+            .collect()
+    }
+
+    pub fn matches(&self, guild_id: u64, input: String, scope: &RuleScope) -> Option<Rule> {
         for rule in &self.ocr {
-            if rule.guild_id == guild_id && rule.matches(&input) {
+            if rule.guild_id == guild_id && !rule.exemptions.exempts(scope) && rule.matches(&input)
+            {
                 return Some(rule.clone());
             }
         }
         None
     }
 
+    pub fn matches_text(&self, guild_id: u64, input: &str, scope: &RuleScope) -> Option<Rule> {
+        self.text
+            .iter()
+            .find(|r| r.guild_id == guild_id && !r.exemptions.exempts(scope) && r.matches(input))
+            .cloned()
+    }
+
+    /// Returns the first name rule matching any of the given names, together with the matched name
+    pub fn matches_name(
+        &self,
+        guild_id: u64,
+        names: &[String],
+        scope: &RuleScope,
+    ) -> Option<(Rule, String)> {
+        self.name
+            .iter()
+            .filter(|r| r.guild_id == guild_id && !r.exemptions.exempts(scope))
+            .find_map(|r| {
+                names
+                    .iter()
+                    .find(|name| r.matches(name))
+                    .map(|name| (r.clone(), name.clone()))
+            })
+    }
+
+    pub fn set_exemptions(&mut self, id: &str, exemptions: RuleExemptions) {
+        if let Some(rule) = self
+            .ocr
+            .iter_mut()
+            .chain(self.text.iter_mut())
+            .chain(self.name.iter_mut())
+            .chain(self.attachment.iter_mut())
+            .find(|r| r.id == id)
+        {
+            rule.exemptions = exemptions;
+        }
+    }
+
+    /// Replaces the normalization steps of a rule, image hashes it matched before have to be checked again.
+    /// The stored hashes are dropped separately through [`db_clear_image_hashes`].
+    pub fn set_normalization(&mut self, id: &str, steps: Vec<NormalizeStep>) {
+        if let Some(rule) = self
+            .ocr
+            .iter_mut()
+            .chain(self.text.iter_mut())
+            .chain(self.name.iter_mut())
+            .chain(self.attachment.iter_mut())
+            .find(|r| r.id == id)
+        {
+            rule.normalization = steps;
+        }
+        self.image_hash_cache.invalidate_rule(id);
+    }
+
+    /// Replaces the condition of a rule, image hashes it matched before have to be checked again.
+    /// The stored hashes are dropped separately through [`db_clear_image_hashes`].
+    pub fn set_condition(&mut self, id: &str, condition: Option<RuleCondition>) {
+        if let Some(rule) = self
+            .ocr
+            .iter_mut()
+            .chain(self.text.iter_mut())
+            .chain(self.name.iter_mut())
+            .find(|r| r.id == id)
+        {
+            rule.condition = condition;
+            rule.compile();
+        }
+        self.image_hash_cache.invalidate_rule(id);
+    }
+
+    pub fn set_cooldown(&mut self, id: &str, cooldown: RuleCooldown) {
+        if let Some(rule) = self
+            .ocr
+            .iter_mut()
+            .chain(self.text.iter_mut())
+            .chain(self.name.iter_mut())
+            .chain(self.attachment.iter_mut())
+            .find(|r| r.id == id)
+        {
+            rule.cooldown = cooldown;
+        }
+    }
+
     pub async fn populate_from_db(&mut self) {
         let res = match sqlx::query(
             "
@@ -181,7 +412,17 @@ impl RuleCache {
                 duration,
                 silent,
                 day_clear_amount,
-                log_channel_id
+                log_channel_id,
+                exempt_roles,
+                exempt_users,
+                channels,
+                channels_include_only,
+                normalization,
+                debounce,
+                max_punishments,
+                punishment_period,
+                delete_during_cooldown,
+                condition
             FROM automod_rules;
         ",
         )
@@ -198,40 +439,16 @@ impl RuleCache {
         use sqlx::Row;
         res.into_iter().for_each(|record| {
             let punishment_type: ActionType = record.get("punishment_type");
-            let punish = match punishment_type {
-                ActionType::Softban => Punishment::Softban {
-                    reason: record.get("reason"),
-                    day_clear_amount: record
-                        .get::<Option<i16>, _>("day_clear_amount")
-                        .unwrap_or(0) as u8,
-                    silent: record.get::<Option<bool>, _>("silent").unwrap_or(false),
-                },
-                ActionType::Ban => Punishment::Ban {
-                    reason: record.get("reason"),
-                    day_clear_amount: record
-                        .get::<Option<i16>, _>("day_clear_amount")
-                        .unwrap_or(0) as u8,
-                    duration: record.get::<Option<i64>, _>("duration").unwrap_or(0) as u64,
-                    silent: record.get::<Option<bool>, _>("silent").unwrap_or(false),
-                },
-                ActionType::Kick => Punishment::Kick {
-                    reason: record.get("reason"),
-                    silent: record.get::<Option<bool>, _>("silent").unwrap_or(false),
-                },
-                ActionType::Mute => Punishment::Mute {
-                    reason: record.get("reason"),
-                    duration: record.get::<Option<i64>, _>("duration").unwrap_or(0) as u64,
-                    silent: record.get::<Option<bool>, _>("silent").unwrap_or(false),
-                },
-                ActionType::Log => Punishment::Log {
-                    reason: record.get("reason"),
-                    channel_id: record.get::<Option<i64>, _>("log_channel_id").unwrap_or(0) as u64,
-                },
-                _ => Punishment::Warn {
-                    reason: record.get("reason"),
-                    silent: record.get::<Option<bool>, _>("silent").unwrap_or(false),
-                },
-            };
+            let punish = Punishment::from_parts(
+                &punishment_type.to_string(),
+                record.get("reason"),
+                record
+                    .get::<Option<i16>, _>("day_clear_amount")
+                    .unwrap_or(0) as u8,
+                record.get::<Option<i64>, _>("duration").unwrap_or(0) as u64,
+                record.get::<Option<bool>, _>("silent").unwrap_or(false),
+                record.get::<Option<i64>, _>("log_channel_id").unwrap_or(0) as u64,
+            );
 
             let rule = Rule {
                 name: record.get("name"),
@@ -240,25 +457,42 @@ impl RuleCache {
                 is_regex: record.get("is_regex"),
                 guild_id: record.get::<i64, _>("guild_id") as u64,
                 punishment: punish,
+                exemptions: RuleExemptions::from_row(&record),
+                normalization: parse_steps(&record.get::<Vec<String>, _>("normalization")),
+                cooldown: RuleCooldown::from_row(&record),
+                condition: record
+                    .get::<Option<serde_json::Value>, _>("condition")
+                    .and_then(|value| serde_json::from_value(value).ok()),
+                fuzzy: record.get::<String, _>("type") == "ocr",
+                regexes: HashMap::new(),
             };
 
-            match record.get::<String, _>("type").as_str() {
-                "ocr" => self.ocr.push(rule),
-                _ => {}
-            };
+            self.insert(record.get::<String, _>("type").as_str(), rule);
         });
     }
 
     pub fn byte_footprint(&self) -> usize {
         std::mem::size_of::<Self>()
-            + self.ocr.capacity() * std::mem::size_of::<Rule>()
+            + (self.ocr.capacity()
+                + self.text.capacity()
+                + self.name.capacity()
+                + self.attachment.capacity())
+                * std::mem::size_of::<Rule>()
             + self
                 .ocr
                 .iter()
+                .chain(self.text.iter())
+                .chain(self.name.iter())
+                .chain(self.attachment.iter())
                 .map(|r| r.byte_footprint() - std::mem::size_of::<Rule>())
                 .sum::<usize>()
             + self.recent_triggers.capacity()
-                * std::mem::size_of::<((String, u64), Instant)>()
+                * std::mem::size_of::<((String, u64), VecDeque<Instant>)>()
+            + self
+                .recent_triggers
+                .values()
+                .map(|h| h.capacity() * std::mem::size_of::<Instant>())
+                .sum::<usize>()
             + self.image_hash_cache.byte_footprint()
     }
 }
@@ -282,20 +516,116 @@ pub async fn db_check_image_hash(guild_id: u64, image_hash: &str) -> Option<Stri
     }
 }
 
-pub async fn db_record_image_hash(guild_id: u64, image_hash: &str, rule_id: &str) {
+/// Looks up the rule of the closest stored image hash within [`PERCEPTUAL_HASH_MAX_DISTANCE`] bits
+pub async fn db_check_perceptual_hash(guild_id: u64, perceptual_hash: u64) -> Option<String> {
+    let result = sqlx::query(
+        "SELECT rule_id FROM ocr_image_hashes \
+         WHERE guild_id = $1 AND perceptual_hash IS NOT NULL \
+         AND bit_count((perceptual_hash # $2)::bit(64)) <= $3 \
+         ORDER BY bit_count((perceptual_hash # $2)::bit(64)) ASC LIMIT 1",
+    )
+    .bind(guild_id as i64)
+    .bind(perceptual_hash as i64)
+    .bind(PERCEPTUAL_HASH_MAX_DISTANCE as i64)
+    .fetch_optional(&*SQL)
+    .await;
+
+    match result {
+        Ok(Some(row)) => {
+            use sqlx::Row;
+            Some(row.get("rule_id"))
+        }
+        _ => None,
+    }
+}
+
+pub async fn db_record_image_hash(
+    guild_id: u64,
+    image_hash: &str,
+    perceptual_hash: Option<u64>,
+    rule_id: &str,
+) {
     let _ = sqlx::query(
         "INSERT INTO ocr_image_hashes \
-             (image_hash, rule_id, guild_id) \
-         VALUES ($1, $2, $3) \
-         ON CONFLICT (image_hash, rule_id) DO NOTHING",
+             (image_hash, rule_id, guild_id, perceptual_hash) \
+         VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (image_hash, rule_id) DO UPDATE SET perceptual_hash = COALESCE(ocr_image_hashes.perceptual_hash, $4)",
     )
     .bind(image_hash)
     .bind(rule_id)
     .bind(guild_id as i64)
+    .bind(perceptual_hash.map(|h| h as i64))
     .execute(&*SQL)
     .await;
 }
 
+/// Forgets the images a rule matched, for when what the rule matches changed
+pub async fn db_clear_image_hashes(rule_id: &str) {
+    if let Err(err) = sqlx::query("DELETE FROM ocr_image_hashes WHERE rule_id = $1")
+        .bind(rule_id)
+        .execute(&*SQL)
+        .await
+    {
+        consume_pgsql_error("CLEAR IMAGE HASHES".into(), err);
+    }
+}
+
+/// How a rule match was found, stored with every rule hit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RuleHitSource {
+    Text,
+    Ocr,
+    HashCache,
+    DbHash,
+    PerceptualHash,
+    Name,
+    Attachment,
+}
+
+impl RuleHitSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RuleHitSource::Text => "text",
+            RuleHitSource::Ocr => "ocr",
+            RuleHitSource::HashCache => "hash_cache",
+            RuleHitSource::DbHash => "db_hash",
+            RuleHitSource::PerceptualHash => "perceptual_hash",
+            RuleHitSource::Name => "name",
+            RuleHitSource::Attachment => "attachment",
+        }
+    }
+}
+
+/// Records a rule match for the rule statistics, `action_id` is set if the match was punished.
+/// `msg` is the matched message, name rules match members instead.
+pub async fn db_record_rule_hit(
+    rule_id: &str,
+    guild_id: u64,
+    user_id: u64,
+    msg: Option<&Message>,
+    source: RuleHitSource,
+    punished: bool,
+    action_id: Option<&str>,
+) {
+    if let Err(err) = sqlx::query(
+        "INSERT INTO rule_hits (rule_id, guild_id, user_id, channel_id, message_id, source, punished, action_id) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(rule_id)
+    .bind(guild_id as i64)
+    .bind(user_id as i64)
+    .bind(msg.map(|m| m.channel_id.get() as i64))
+    .bind(msg.map(|m| m.id.get() as i64))
+    .bind(source.name())
+    .bind(punished)
+    .bind(action_id)
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error("RECORD RULE HIT".into(), err);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Punishment {
     Warn {
@@ -326,6 +656,71 @@ pub enum Punishment {
         reason: String,
         channel_id: u64,
     },
+    /// Replaces the members nickname with a placeholder
+    Rename {
+        reason: String,
+        silent: bool,
+    },
+}
+
+impl Punishment {
+    /// Builds a punishment from the values stored in `automod_rules`, `kind` being the `action_type`
+    pub fn from_parts(
+        kind: &str,
+        reason: String,
+        day_clear_amount: u8,
+        duration: u64,
+        silent: bool,
+        channel_id: u64,
+    ) -> Self {
+        match kind {
+            "ban" => Punishment::Ban {
+                reason,
+                day_clear_amount,
+                duration,
+                silent,
+            },
+            "softban" => Punishment::Softban {
+                reason,
+                day_clear_amount,
+                silent,
+            },
+            "kick" => Punishment::Kick { reason, silent },
+            "mute" => Punishment::Mute {
+                reason,
+                duration,
+                silent,
+            },
+            "log" => Punishment::Log { reason, channel_id },
+            "rename" => Punishment::Rename { reason, silent },
+            _ => Punishment::Warn { reason, silent },
+        }
+    }
+
+    pub fn reason(&self) -> &str {
+        match self {
+            Punishment::Warn { reason, .. }
+            | Punishment::Kick { reason, .. }
+            | Punishment::Ban { reason, .. }
+            | Punishment::Softban { reason, .. }
+            | Punishment::Mute { reason, .. }
+            | Punishment::Log { reason, .. }
+            | Punishment::Rename { reason, .. } => reason,
+        }
+    }
+
+    /// The `action_type` name of the punishment, `log` for log only punishments
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Punishment::Warn { .. } => "warn",
+            Punishment::Kick { .. } => "kick",
+            Punishment::Ban { .. } => "ban",
+            Punishment::Softban { .. } => "softban",
+            Punishment::Mute { .. } => "mute",
+            Punishment::Log { .. } => "log",
+            Punishment::Rename { .. } => "rename",
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -336,16 +731,267 @@ pub struct Rule {
     pub is_regex: bool,
     pub guild_id: u64,
     pub punishment: Punishment,
+    pub exemptions: RuleExemptions,
+    /// Applied to the input (and plain text patterns) before matching
+    pub normalization: Vec<NormalizeStep>,
+    pub cooldown: RuleCooldown,
+    /// Replaces the single pattern when set, see [`RuleCondition`]
+    pub condition: Option<RuleCondition>,
+    /// Whether plain patterns tolerate a few wrong characters, only OCR output is noisy enough for it.
+    /// Text and name rules match plain patterns exactly (case insensitive).
+    pub fuzzy: bool,
+    /// The compiled regexes of the pattern and the condition by their source, filled by [`Rule::compile`]
+    pub regexes: HashMap<String, Regex>,
+}
+
+/// How often a rule may punish the same user
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleCooldown {
+    /// Minimum time between two punishments
+    pub debounce: Duration,
+    /// Punishments per user within the period, 0 for no limit
+    pub max_punishments: u32,
+    pub period: Duration,
+    /// Whether matched messages are still deleted while the rule can not punish the user
+    pub delete_during_cooldown: bool,
+}
+
+impl Default for RuleCooldown {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_secs(15),
+            max_punishments: 0,
+            period: Duration::ZERO,
+            delete_during_cooldown: true,
+        }
+    }
+}
+
+impl RuleCooldown {
+    /// Reads the cooldown columns of an `automod_rules` row
+    pub fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        use sqlx::Row;
+
+        Self {
+            debounce: Duration::from_secs(row.get::<i32, _>("debounce") as u64),
+            max_punishments: row.get::<i32, _>("max_punishments") as u32,
+            period: Duration::from_secs(row.get::<i64, _>("punishment_period") as u64),
+            delete_during_cooldown: row.get("delete_during_cooldown"),
+        }
+    }
+
+    /// How long punishment times have to be remembered
+    fn window(&self) -> Duration {
+        if self.max_punishments == 0 {
+            self.debounce
+        } else {
+            self.debounce.max(self.period)
+        }
+    }
+
+    /// e.g. `Debounce: 15 seconds | Max punishments: 3 per 1 day | During cooldown: delete`
+    pub fn describe(&self) -> String {
+        let duration = |d: Duration| {
+            time_string(TimeDelta::from_std(d).unwrap_or_default())
+                .trim_start_matches("for ")
+                .to_string()
+        };
+
+        let debounce = if self.debounce.is_zero() {
+            String::from("off")
+        } else {
+            duration(self.debounce)
+        };
+        let max = if self.max_punishments == 0 {
+            String::from("unlimited")
+        } else {
+            format!("{} per {}", self.max_punishments, duration(self.period))
+        };
+
+        format!(
+            "Debounce: {debounce} | Max punishments: {max} | During cooldown: {}",
+            if self.delete_during_cooldown {
+                "delete"
+            } else {
+                "ignore"
+            }
+        )
+    }
+}
+
+/// Where and by whom a message was sent, used to check rule exemptions
+#[derive(Clone, Debug, Default)]
+pub struct RuleScope {
+    /// 0 for matches outside of channels, e.g. member names
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub role_ids: Vec<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RuleExemptions {
+    pub roles: Vec<u64>,
+    pub users: Vec<u64>,
+    /// Channels the rule is skipped in, or the only channels it applies in if `include_only` is set
+    pub channels: Vec<u64>,
+    pub include_only: bool,
+}
+
+impl RuleExemptions {
+    /// Reads the exemption columns of an `automod_rules` row
+    pub fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        use sqlx::Row;
+        let ids = |column: &str| {
+            row.get::<Vec<i64>, _>(column)
+                .into_iter()
+                .map(|id| id as u64)
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            roles: ids("exempt_roles"),
+            users: ids("exempt_users"),
+            channels: ids("channels"),
+            include_only: row.get("channels_include_only"),
+        }
+    }
+
+    pub fn exempts(&self, scope: &RuleScope) -> bool {
+        if self.users.contains(&scope.user_id)
+            || scope.role_ids.iter().any(|r| self.roles.contains(r))
+        {
+            return true;
+        }
+
+        if scope.channel_id == 0 {
+            return false;
+        }
+
+        self.include_only != self.channels.contains(&scope.channel_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty()
+            && self.users.is_empty()
+            && self.channels.is_empty()
+            && !self.include_only
+    }
+
+    /// e.g. `Exempt roles: <@&1> | Only in: <#2>`, empty if there are no exemptions
+    pub fn describe(&self) -> String {
+        let list = |ids: &[u64], prefix: &str| {
+            if ids.is_empty() {
+                return String::from("none");
+            }
+            ids.iter()
+                .map(|id| format!("<{prefix}{id}>"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut parts = vec![];
+        if !self.roles.is_empty() {
+            parts.push(format!("Exempt roles: {}", list(&self.roles, "@&")));
+        }
+        if !self.users.is_empty() {
+            parts.push(format!("Exempt users: {}", list(&self.users, "@")));
+        }
+        if self.include_only {
+            parts.push(format!("Only in: {}", list(&self.channels, "#")));
+        } else if !self.channels.is_empty() {
+            parts.push(format!("Exempt channels: {}", list(&self.channels, "#")));
+        }
+
+        parts.join(" | ")
+    }
 }
 
 impl Rule {
+    /// A rule that is only used for matching and never stored or enforced, e.g. for dry runs
+    pub fn preview(guild_id: u64, pattern: String, is_regex: bool, fuzzy: bool) -> Self {
+        Self {
+            name: String::from("preview"),
+            id: String::new(),
+            pattern,
+            is_regex,
+            guild_id,
+            punishment: Punishment::Log {
+                reason: String::new(),
+                channel_id: 0,
+            },
+            exemptions: RuleExemptions::default(),
+            normalization: NormalizeStep::DEFAULT.to_vec(),
+            cooldown: RuleCooldown::default(),
+            condition: None,
+            fuzzy,
+            regexes: HashMap::new(),
+        }
+        .compiled()
+    }
+
+    /// Compiles the regexes of the rule once, so matching does not have to
+    pub fn compile(&mut self) {
+        let condition_patterns = self
+            .condition
+            .as_ref()
+            .map(|c| c.regex_patterns())
+            .unwrap_or_default();
+        let patterns = self
+            .is_regex
+            .then_some(self.pattern.as_str())
+            .into_iter()
+            .chain(condition_patterns);
+
+        let mut regexes = HashMap::new();
+        for pattern in patterns {
+            if !regexes.contains_key(pattern)
+                && let Ok(regex) = build_rule_regex(pattern)
+            {
+                regexes.insert(pattern.to_string(), regex);
+            }
+        }
+        self.regexes = regexes;
+    }
+
+    fn compiled(mut self) -> Self {
+        self.compile();
+        self
+    }
+
+    pub fn normalize(&self, input: &str) -> String {
+        normalize(input, &self.normalization)
+    }
+
     pub fn matches(&self, input: &str) -> bool {
-        if self.is_regex {
-            Regex::new(&self.pattern)
-                .map(|re| re.is_match(input))
-                .unwrap_or(false)
+        let input = self.normalize(input);
+
+        match &self.condition {
+            Some(condition) => condition
+                .evaluate(&mut |pattern, is_regex| self.pattern_matches(pattern, is_regex, &input)),
+            None => self.pattern_matches(&self.pattern, self.is_regex, &input),
+        }
+    }
+
+    /// Matches a single pattern against already normalized input
+    fn pattern_matches(&self, pattern: &str, is_regex: bool, input: &str) -> bool {
+        if is_regex {
+            self.regexes
+                .get(pattern)
+                .is_some_and(|re| re.is_match(input))
+        } else if self.fuzzy {
+            fuzzy_substring_match(&self.normalize(pattern), input, 0.95)
         } else {
-            fuzzy_substring_match(&self.pattern, input, 0.95)
+            input
+                .to_lowercase()
+                .contains(&self.normalize(pattern).to_lowercase())
+        }
+    }
+
+    /// What the rule matches as shown in logs, the written condition if it has one
+    pub fn describe_pattern(&self) -> String {
+        match &self.condition {
+            Some(condition) => condition.describe(),
+            None => self.pattern.clone(),
         }
     }
 
@@ -360,8 +1006,24 @@ impl Rule {
                 | Punishment::Ban { reason, .. }
                 | Punishment::Softban { reason, .. }
                 | Punishment::Mute { reason, .. }
-                | Punishment::Log { reason, .. } => reason.capacity(),
+                | Punishment::Log { reason, .. }
+                | Punishment::Rename { reason, .. } => reason.capacity(),
             }
+            + (self.exemptions.roles.capacity()
+                + self.exemptions.users.capacity()
+                + self.exemptions.channels.capacity())
+                * std::mem::size_of::<u64>()
+            + self.normalization.capacity() * std::mem::size_of::<NormalizeStep>()
+            + self
+                .condition
+                .as_ref()
+                .map(|c| c.byte_footprint())
+                .unwrap_or(0)
+            + self
+                .regexes
+                .keys()
+                .map(|k| k.capacity() + std::mem::size_of::<Regex>())
+                .sum::<usize>()
     }
 }
 
@@ -409,3 +1071,195 @@ fn fuzzy_substring_match(rule: &str, input: &str, threshold: f64) -> bool {
     let min_dist = *dp.iter().skip(1).min().unwrap_or(&m);
     min_dist <= max_errors
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_patterns_match_case_insensitive_substrings() {
+        let rule = Rule::preview(1, String::from("Free Nitro"), false, false);
+        assert!(rule.matches("get your FREE NITRO here"));
+        assert!(!rule.matches("get your free nitr0 here"));
+    }
+
+    #[test]
+    fn input_is_normalized_before_matching() {
+        let rule = Rule::preview(1, String::from("nitro"), false, false);
+        assert!(rule.matches("ｎｉｔｒｏ"));
+        assert!(rule.matches("n\u{200B}itr\u{043E}"));
+        assert!(rule.matches("nítrö"));
+    }
+
+    #[test]
+    fn fuzzy_patterns_tolerate_wrong_characters() {
+        let pattern = String::from("claim your free discord nitro gift");
+        let input = "claim your free discord nltro gift now";
+        assert!(Rule::preview(1, pattern.clone(), false, true).matches(input));
+        assert!(!Rule::preview(1, pattern, false, false).matches(input));
+
+        assert!(!Rule::preview(1, String::from("nitro"), false, true).matches("steam"));
+    }
+
+    #[test]
+    fn regex_patterns_are_compiled_once() {
+        let rule = Rule::preview(1, String::from(r"n[i1]tro\s+gift"), true, false);
+        assert_eq!(rule.regexes.len(), 1);
+        assert!(rule.matches("n1tro   gift"));
+        assert!(!rule.matches("nitro"));
+
+        let broken = Rule::preview(1, String::from("n[itro"), true, false);
+        assert!(broken.regexes.is_empty());
+        assert!(!broken.matches("n[itro"));
+    }
+
+    #[test]
+    fn conditions_replace_the_pattern() {
+        let mut rule = Rule::preview(1, String::from("unused"), false, false);
+        rule.condition =
+            Some(RuleCondition::parse("steam AND (/g[i1]ft/ OR nitro) AND NOT official").unwrap());
+        rule.compile();
+
+        assert_eq!(rule.regexes.len(), 1);
+        assert!(rule.matches("STEAM g1ft"));
+        assert!(rule.matches("steam nitro"));
+        assert!(!rule.matches("official steam gift"));
+        assert!(!rule.matches("unused"));
+        assert_eq!(
+            rule.describe_pattern(),
+            "steam AND (/g[i1]ft/ OR nitro) AND NOT official"
+        );
+    }
+
+    #[test]
+    fn cache_matches_rules_of_the_guild_and_kind() {
+        let mut cache = RuleCache::new();
+        cache.insert(
+            "text",
+            Rule::preview(1, String::from("nitro"), false, false),
+        );
+
+        let scope = RuleScope::default();
+        assert!(cache.matches_text(1, "free nitro", &scope).is_some());
+        assert!(cache.matches_text(2, "free nitro", &scope).is_none());
+        assert!(
+            cache
+                .matches(1, String::from("free nitro"), &scope)
+                .is_none()
+        );
+    }
+
+    fn cooldown_rule(cooldown: RuleCooldown) -> Rule {
+        let mut rule = Rule::preview(1, String::from("nitro"), false, false);
+        rule.id = String::from("rule");
+        rule.cooldown = cooldown;
+        rule
+    }
+
+    #[test]
+    fn debounce_blocks_repeated_punishments() {
+        let mut cache = RuleCache::new();
+        let rule = cooldown_rule(RuleCooldown::default());
+
+        assert!(cache.check_cooldown(&rule, 1));
+        assert!(!cache.check_cooldown(&rule, 1));
+        assert!(cache.check_cooldown(&rule, 2));
+    }
+
+    #[test]
+    fn punishment_limit_applies_per_period() {
+        let mut cache = RuleCache::new();
+        let rule = cooldown_rule(RuleCooldown {
+            debounce: Duration::ZERO,
+            max_punishments: 2,
+            period: Duration::from_secs(3600),
+            delete_during_cooldown: true,
+        });
+
+        assert!(cache.check_cooldown(&rule, 1));
+        assert!(cache.check_cooldown(&rule, 1));
+        assert!(!cache.check_cooldown(&rule, 1));
+        assert!(cache.check_cooldown(&rule, 2));
+
+        let mut other = rule.clone();
+        other.id = String::from("other");
+        assert!(cache.check_cooldown(&other, 1));
+    }
+
+    #[test]
+    fn expired_punishments_leave_the_period() {
+        let mut cache = RuleCache::new();
+        let rule = cooldown_rule(RuleCooldown {
+            debounce: Duration::ZERO,
+            max_punishments: 1,
+            period: Duration::from_millis(20),
+            delete_during_cooldown: true,
+        });
+
+        assert!(cache.check_cooldown(&rule, 1));
+        assert!(!cache.check_cooldown(&rule, 1));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.check_cooldown(&rule, 1));
+    }
+
+    #[test]
+    fn no_cooldown_never_blocks() {
+        let mut cache = RuleCache::new();
+        let rule = cooldown_rule(RuleCooldown {
+            debounce: Duration::ZERO,
+            ..Default::default()
+        });
+
+        for _ in 0..10 {
+            assert!(cache.check_cooldown(&rule, 1));
+        }
+    }
+
+    #[test]
+    fn describes_cooldowns() {
+        assert_eq!(
+            RuleCooldown::default().describe(),
+            "Debounce: 15 seconds | Max punishments: unlimited | During cooldown: delete"
+        );
+        assert_eq!(
+            RuleCooldown {
+                debounce: Duration::ZERO,
+                max_punishments: 3,
+                period: Duration::from_secs(86400),
+                delete_during_cooldown: false,
+            }
+            .describe(),
+            "Debounce: off | Max punishments: 3 per 1 day | During cooldown: ignore"
+        );
+    }
+
+    #[test]
+    fn exemptions_skip_rules() {
+        let exemptions = RuleExemptions {
+            roles: vec![10],
+            users: vec![20],
+            channels: vec![30],
+            include_only: false,
+        };
+        let scope = |channel_id, user_id, role_ids: Vec<u64>| RuleScope {
+            channel_id,
+            user_id,
+            role_ids,
+        };
+
+        assert!(exemptions.exempts(&scope(1, 20, vec![])));
+        assert!(exemptions.exempts(&scope(1, 2, vec![10])));
+        assert!(exemptions.exempts(&scope(30, 2, vec![])));
+        assert!(!exemptions.exempts(&scope(1, 2, vec![])));
+        assert!(!exemptions.exempts(&scope(0, 2, vec![])));
+
+        let only_in = RuleExemptions {
+            channels: vec![30],
+            include_only: true,
+            ..Default::default()
+        };
+        assert!(!only_in.exempts(&scope(30, 2, vec![])));
+        assert!(only_in.exempts(&scope(1, 2, vec![])));
+        assert!(!only_in.exempts(&scope(0, 2, vec![])));
+    }
+}
@@ -0,0 +1,726 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::rule_regex::build_rule_regex;
+
+/// Patterns a single condition may combine
+pub const MAX_CONDITION_PATTERNS: usize = 20;
+/// Maximum depth of nested groups and `NOT`s
+const MAX_CONDITION_DEPTH: usize = 8;
+
+/// Patterns combined with `AND`, `OR`, `NOT` and minimum match counts, stored as JSON in
+/// `automod_rules.condition` and written as e.g. `steam AND (gift OR nitro) AND NOT official`
+/// or `2 OF (gift, nitro, "free steam")`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum RuleCondition {
+    /// A single pattern, matched like the pattern of a rule without a condition
+    Pattern {
+        pattern: String,
+        #[serde(default)]
+        is_regex: bool,
+    },
+    All {
+        conditions: Vec<RuleCondition>,
+    },
+    Any {
+        conditions: Vec<RuleCondition>,
+    },
+    Not {
+        condition: Box<RuleCondition>,
+    },
+    /// Matches if at least `count` of the conditions match
+    AtLeast {
+        count: usize,
+        conditions: Vec<RuleCondition>,
+    },
+}
+
+/// Why a condition could not be parsed
+#[derive(Debug, Clone)]
+pub struct ConditionError {
+    pub reason: String,
+    /// Character offset and length of the broken part of the condition, if known
+    pub span: Option<(usize, usize)>,
+}
+
+impl ConditionError {
+    fn new(reason: impl Into<String>, start: usize, length: usize) -> Self {
+        Self {
+            reason: reason.into(),
+            span: Some((start, length)),
+        }
+    }
+}
+
+impl RuleCondition {
+    /// Parses the written form of a condition. `AND` binds stronger than `OR`, keywords are case insensitive
+    /// and patterns can be quoted (`"free nitro"`) or written as a regex (`/n[i1]tro/`).
+    pub fn parse(input: &str) -> Result<Self, ConditionError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            end: input.chars().count(),
+        };
+
+        let condition = parser.or(0)?;
+        if let Some(token) = parser.tokens.get(parser.index) {
+            return Err(ConditionError::new(
+                format!("expected AND or OR, found {}", token.kind.describe()),
+                token.start,
+                token.length,
+            ));
+        }
+
+        let patterns = condition.pattern_count();
+        if patterns > MAX_CONDITION_PATTERNS {
+            return Err(ConditionError {
+                reason: format!(
+                    "a condition can combine at most {MAX_CONDITION_PATTERNS} patterns, found {patterns}"
+                ),
+                span: None,
+            });
+        }
+
+        if condition.evaluate(&mut |_, _| false) {
+            return Err(ConditionError {
+                reason: String::from(
+                    "the condition matches text without any of its patterns, it needs a pattern that has to be present",
+                ),
+                span: None,
+            });
+        }
+
+        Ok(condition)
+    }
+
+    /// Evaluates the condition, `matches` is called with (pattern, is_regex) for the patterns that decide the result
+    pub fn evaluate(&self, matches: &mut impl FnMut(&str, bool) -> bool) -> bool {
+        match self {
+            RuleCondition::Pattern { pattern, is_regex } => matches(pattern, *is_regex),
+            RuleCondition::All { conditions } => conditions.iter().all(|c| c.evaluate(matches)),
+            RuleCondition::Any { conditions } => conditions.iter().any(|c| c.evaluate(matches)),
+            RuleCondition::Not { condition } => !condition.evaluate(matches),
+            RuleCondition::AtLeast { count, conditions } => {
+                let mut hits = 0;
+                for condition in conditions {
+                    if condition.evaluate(matches) {
+                        hits += 1;
+                        if hits >= *count {
+                            return true;
+                        }
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// The regex patterns of the condition, so they can be compiled once when the rule is loaded
+    pub fn regex_patterns(&self) -> Vec<&str> {
+        match self {
+            RuleCondition::Pattern { pattern, is_regex } => {
+                if *is_regex {
+                    vec![pattern.as_str()]
+                } else {
+                    vec![]
+                }
+            }
+            RuleCondition::Not { condition } => condition.regex_patterns(),
+            RuleCondition::All { conditions }
+            | RuleCondition::Any { conditions }
+            | RuleCondition::AtLeast { conditions, .. } => {
+                conditions.iter().flat_map(|c| c.regex_patterns()).collect()
+            }
+        }
+    }
+
+    pub fn pattern_count(&self) -> usize {
+        match self {
+            RuleCondition::Pattern { .. } => 1,
+            RuleCondition::Not { condition } => condition.pattern_count(),
+            RuleCondition::All { conditions }
+            | RuleCondition::Any { conditions }
+            | RuleCondition::AtLeast { conditions, .. } => {
+                conditions.iter().map(|c| c.pattern_count()).sum()
+            }
+        }
+    }
+
+    /// The written form of the condition, which [`RuleCondition::parse`] reads back
+    pub fn describe(&self) -> String {
+        self.describe_within(0)
+    }
+
+    /// `AND` and `OR` only need parentheses inside of operators that bind stronger than themselves
+    fn describe_within(&self, parent_precedence: u8) -> String {
+        let (precedence, text) = match self {
+            RuleCondition::Pattern { pattern, is_regex } => (
+                3,
+                if *is_regex {
+                    format!("/{pattern}/")
+                } else {
+                    quote_pattern(pattern)
+                },
+            ),
+            RuleCondition::Any { conditions } => (
+                1,
+                conditions
+                    .iter()
+                    .map(|c| c.describe_within(1))
+                    .collect::<Vec<_>>()
+                    .join(" OR "),
+            ),
+            RuleCondition::All { conditions } => (
+                2,
+                conditions
+                    .iter()
+                    .map(|c| c.describe_within(2))
+                    .collect::<Vec<_>>()
+                    .join(" AND "),
+            ),
+            RuleCondition::Not { condition } => {
+                (3, format!("NOT {}", condition.describe_within(3)))
+            }
+            RuleCondition::AtLeast { count, conditions } => (
+                3,
+                format!(
+                    "{count} OF ({})",
+                    conditions
+                        .iter()
+                        .map(|c| c.describe_within(0))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+        };
+
+        if precedence < parent_precedence {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    pub fn byte_footprint(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + match self {
+                RuleCondition::Pattern { pattern, .. } => pattern.capacity(),
+                RuleCondition::Not { condition } => condition.byte_footprint(),
+                RuleCondition::All { conditions }
+                | RuleCondition::Any { conditions }
+                | RuleCondition::AtLeast { conditions, .. } => {
+                    conditions.iter().map(|c| c.byte_footprint()).sum::<usize>()
+                }
+            }
+    }
+}
+
+/// Quotes plain patterns that would otherwise be read as keywords, regexes or several tokens
+fn quote_pattern(pattern: &str) -> String {
+    let needs_quotes = pattern.is_empty()
+        || pattern.starts_with('/')
+        || Keyword::parse(pattern).is_some()
+        || pattern
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | ',' | '"' | '\\'));
+
+    if needs_quotes {
+        format!("\"{}\"", pattern.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        pattern.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+    And,
+    Or,
+    Not,
+    Of,
+}
+
+impl Keyword {
+    fn parse(word: &str) -> Option<Self> {
+        match word.to_uppercase().as_str() {
+            "AND" => Some(Keyword::And),
+            "OR" => Some(Keyword::Or),
+            "NOT" => Some(Keyword::Not),
+            "OF" => Some(Keyword::Of),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Open,
+    Close,
+    Comma,
+    Keyword(Keyword),
+    /// An unquoted word, which may also be the count of `OF`
+    Word(String),
+    Quoted(String),
+    Regex(String),
+}
+
+impl TokenKind {
+    fn describe(&self) -> String {
+        match self {
+            TokenKind::Open => String::from("`(`"),
+            TokenKind::Close => String::from("`)`"),
+            TokenKind::Comma => String::from("`,`"),
+            TokenKind::Keyword(keyword) => format!("`{}`", format!("{keyword:?}").to_uppercase()),
+            TokenKind::Word(word) => format!("`{word}`"),
+            TokenKind::Quoted(text) => format!("`\"{text}\"`"),
+            TokenKind::Regex(pattern) => format!("`/{pattern}/`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConditionToken {
+    kind: TokenKind,
+    /// Character offset and length within the condition
+    start: usize,
+    length: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<ConditionToken>, ConditionError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let is_delimiter = |c: char| c.is_whitespace() || matches!(c, '(' | ')' | ',');
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let kind = match chars[i] {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => {
+                i += 1;
+                TokenKind::Open
+            }
+            ')' => {
+                i += 1;
+                TokenKind::Close
+            }
+            ',' => {
+                i += 1;
+                TokenKind::Comma
+            }
+            '"' => {
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => {
+                            return Err(ConditionError::new("unclosed quote", start, 1));
+                        }
+                        Some('"') => break,
+                        Some('\\') if i + 1 < chars.len() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(c) => {
+                            text.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+
+                if text.trim().is_empty() {
+                    return Err(ConditionError::new("empty pattern", start, i - start));
+                }
+                TokenKind::Quoted(text)
+            }
+            '/' => {
+                // the regex ends at the first slash followed by a delimiter, so it may contain slashes itself
+                let Some(end) = (i + 1..chars.len()).find(|&j| {
+                    chars[j] == '/' && chars.get(j + 1).is_none_or(|c| is_delimiter(*c))
+                }) else {
+                    return Err(ConditionError::new("unclosed regex", start, 1));
+                };
+                let pattern = chars[i + 1..end].iter().collect::<String>();
+                i = end + 1;
+
+                if pattern.is_empty() {
+                    return Err(ConditionError::new("empty regex", start, 2));
+                }
+                if let Err(rejection) = build_rule_regex(&pattern) {
+                    let (offset, length) = rejection.span.unwrap_or((0, pattern.chars().count()));
+                    return Err(ConditionError::new(
+                        format!("invalid regex: {}", rejection.reason),
+                        start + 1 + offset,
+                        length.max(1),
+                    ));
+                }
+                TokenKind::Regex(pattern)
+            }
+            _ => {
+                while i < chars.len() && !is_delimiter(chars[i]) && chars[i] != '"' {
+                    i += 1;
+                }
+                let word = chars[start..i].iter().collect::<String>();
+                match Keyword::parse(&word) {
+                    Some(keyword) => TokenKind::Keyword(keyword),
+                    None => TokenKind::Word(word),
+                }
+            }
+        };
+
+        tokens.push(ConditionToken {
+            kind,
+            start,
+            length: i - start,
+        });
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<ConditionToken>,
+    index: usize,
+    /// Character count of the input, where errors about a missing token point to
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.index).map(|t| &t.kind)
+    }
+
+    fn next(&mut self) -> Option<ConditionToken> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn unexpected(&self, token: Option<ConditionToken>, expected: &str) -> ConditionError {
+        match token {
+            Some(token) => ConditionError::new(
+                format!("expected {expected}, found {}", token.kind.describe()),
+                token.start,
+                token.length,
+            ),
+            None => ConditionError::new(
+                format!("expected {expected}, found the end of the condition"),
+                self.end,
+                1,
+            ),
+        }
+    }
+
+    fn or(&mut self, depth: usize) -> Result<RuleCondition, ConditionError> {
+        let mut conditions = vec![self.and(depth)?];
+        while self.peek() == Some(&TokenKind::Keyword(Keyword::Or)) {
+            self.index += 1;
+            conditions.push(self.and(depth)?);
+        }
+
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => RuleCondition::Any { conditions },
+        })
+    }
+
+    fn and(&mut self, depth: usize) -> Result<RuleCondition, ConditionError> {
+        let mut conditions = vec![self.unary(depth)?];
+        while self.peek() == Some(&TokenKind::Keyword(Keyword::And)) {
+            self.index += 1;
+            conditions.push(self.unary(depth)?);
+        }
+
+        Ok(match conditions.len() {
+            1 => conditions.remove(0),
+            _ => RuleCondition::All { conditions },
+        })
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<RuleCondition, ConditionError> {
+        if depth > MAX_CONDITION_DEPTH {
+            let token = self.tokens.get(self.index).cloned();
+            return Err(match token {
+                Some(token) => ConditionError::new(
+                    format!("conditions can be nested at most {MAX_CONDITION_DEPTH} levels deep"),
+                    token.start,
+                    token.length,
+                ),
+                None => self.unexpected(None, "a pattern"),
+            });
+        }
+
+        let Some(token) = self.next() else {
+            return Err(self.unexpected(None, "a pattern"));
+        };
+
+        match token.kind {
+            TokenKind::Keyword(Keyword::Not) => Ok(RuleCondition::Not {
+                condition: Box::new(self.unary(depth + 1)?),
+            }),
+            TokenKind::Open => {
+                let condition = self.or(depth + 1)?;
+                match self.next() {
+                    Some(ConditionToken {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => Ok(condition),
+                    Some(other) => Err(self.unexpected(Some(other), "`)`")),
+                    None => Err(ConditionError::new("unclosed group", token.start, 1)),
+                }
+            }
+            TokenKind::Word(ref word) if self.peek() == Some(&TokenKind::Keyword(Keyword::Of)) => {
+                self.index += 1;
+                self.at_least(word, &token, depth)
+            }
+            TokenKind::Word(pattern) | TokenKind::Quoted(pattern) => Ok(RuleCondition::Pattern {
+                pattern,
+                is_regex: false,
+            }),
+            TokenKind::Regex(pattern) => Ok(RuleCondition::Pattern {
+                pattern,
+                is_regex: true,
+            }),
+            _ => Err(self.unexpected(Some(token), "a pattern")),
+        }
+    }
+
+    /// `<count> OF (a, b, c)`, the count and `OF` have already been read
+    fn at_least(
+        &mut self,
+        count: &str,
+        count_token: &ConditionToken,
+        depth: usize,
+    ) -> Result<RuleCondition, ConditionError> {
+        let Ok(count) = count.parse::<usize>() else {
+            return Err(ConditionError::new(
+                format!("`{count}` is not a number"),
+                count_token.start,
+                count_token.length,
+            ));
+        };
+
+        let open = self.next();
+        if !matches!(
+            open,
+            Some(ConditionToken {
+                kind: TokenKind::Open,
+                ..
+            })
+        ) {
+            return Err(self.unexpected(open, "`(` after OF"));
+        }
+
+        let mut conditions = vec![self.or(depth + 1)?];
+        loop {
+            match self.next() {
+                Some(ConditionToken {
+                    kind: TokenKind::Comma,
+                    ..
+                }) => conditions.push(self.or(depth + 1)?),
+                Some(ConditionToken {
+                    kind: TokenKind::Close,
+                    ..
+                }) => break,
+                other => return Err(self.unexpected(other, "`,` or `)`")),
+            }
+        }
+
+        if count == 0 || count > conditions.len() {
+            return Err(ConditionError::new(
+                format!(
+                    "the count has to be between 1 and the number of conditions ({})",
+                    conditions.len()
+                ),
+                count_token.start,
+                count_token.length,
+            ));
+        }
+
+        Ok(RuleCondition::AtLeast { count, conditions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> RuleCondition {
+        RuleCondition::Pattern {
+            pattern: pattern.to_string(),
+            is_regex: false,
+        }
+    }
+
+    /// Evaluates the condition with plain substring matching
+    fn matches(condition: &RuleCondition, input: &str) -> bool {
+        condition.evaluate(&mut |pattern, is_regex| {
+            if is_regex {
+                build_rule_regex(pattern).unwrap().is_match(input)
+            } else {
+                input.contains(pattern)
+            }
+        })
+    }
+
+    #[test]
+    fn and_binds_stronger_than_or() {
+        let condition = RuleCondition::parse("a OR b and c").unwrap();
+        assert_eq!(
+            condition,
+            RuleCondition::Any {
+                conditions: vec![
+                    pattern("a"),
+                    RuleCondition::All {
+                        conditions: vec![pattern("b"), pattern("c")],
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn parses_groups_not_quotes_and_regexes() {
+        let condition =
+            RuleCondition::parse(r#"steam AND ("free nitro" OR /n[i1]tro/) AND NOT official"#)
+                .unwrap();
+        assert_eq!(
+            condition,
+            RuleCondition::All {
+                conditions: vec![
+                    pattern("steam"),
+                    RuleCondition::Any {
+                        conditions: vec![
+                            pattern("free nitro"),
+                            RuleCondition::Pattern {
+                                pattern: String::from("n[i1]tro"),
+                                is_regex: true,
+                            },
+                        ],
+                    },
+                    RuleCondition::Not {
+                        condition: Box::new(pattern("official")),
+                    },
+                ],
+            }
+        );
+
+        assert!(matches(&condition, "steam gift n1tro"));
+        assert!(matches(&condition, "free nitro on steam"));
+        assert!(!matches(&condition, "official steam nitro"));
+        assert!(!matches(&condition, "steam gift"));
+    }
+
+    #[test]
+    fn regex_may_contain_slashes() {
+        let condition = RuleCondition::parse("/a/b/ OR c").unwrap();
+        assert_eq!(condition.regex_patterns(), vec!["a/b"]);
+    }
+
+    #[test]
+    fn quoted_patterns_unescape() {
+        let condition = RuleCondition::parse(r#""say \"hi\"" AND x"#).unwrap();
+        assert_eq!(
+            condition,
+            RuleCondition::All {
+                conditions: vec![pattern("say \"hi\""), pattern("x")],
+            }
+        );
+    }
+
+    #[test]
+    fn at_least_counts_matches() {
+        let condition = RuleCondition::parse(r#"2 of (gift, nitro, "free steam")"#).unwrap();
+        assert_eq!(
+            condition,
+            RuleCondition::AtLeast {
+                count: 2,
+                conditions: vec![pattern("gift"), pattern("nitro"), pattern("free steam")],
+            }
+        );
+
+        assert!(matches(&condition, "nitro gift"));
+        assert!(matches(&condition, "free steam nitro"));
+        assert!(!matches(&condition, "nitro only"));
+    }
+
+    #[test]
+    fn describe_round_trips() {
+        for input in [
+            "steam AND (gift OR nitro) AND NOT official",
+            r#"2 OF (gift, nitro, "free steam")"#,
+            r#"/n[i1]tro/ OR "and" OR "say \"hi\"""#,
+            "NOT (a OR b) AND c",
+        ] {
+            let condition = RuleCondition::parse(input).unwrap();
+            assert_eq!(condition.describe(), input);
+            assert_eq!(
+                RuleCondition::parse(&condition.describe()).unwrap(),
+                condition
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_conditions_without_required_pattern() {
+        assert!(RuleCondition::parse("NOT official").is_err());
+        assert!(RuleCondition::parse("a OR NOT b").is_err());
+        assert!(RuleCondition::parse("a AND NOT b").is_ok());
+    }
+
+    #[test]
+    fn reports_error_spans() {
+        let error = RuleCondition::parse("a AND").unwrap_err();
+        assert_eq!(error.span, Some((5, 1)));
+
+        let error = RuleCondition::parse("a b").unwrap_err();
+        assert_eq!(error.reason, "expected AND or OR, found `b`");
+        assert_eq!(error.span, Some((2, 1)));
+
+        let error = RuleCondition::parse(r#"a AND "b"#).unwrap_err();
+        assert_eq!(error.reason, "unclosed quote");
+        assert_eq!(error.span, Some((6, 1)));
+
+        let error = RuleCondition::parse("(a OR b").unwrap_err();
+        assert_eq!(error.reason, "unclosed group");
+        assert_eq!(error.span, Some((0, 1)));
+
+        let error = RuleCondition::parse("a OR /b(/").unwrap_err();
+        assert!(error.reason.starts_with("invalid regex"));
+    }
+
+    #[test]
+    fn rejects_invalid_counts() {
+        assert!(RuleCondition::parse("0 OF (a, b)").is_err());
+        assert!(RuleCondition::parse("3 OF (a, b)").is_err());
+        let error = RuleCondition::parse("x OF (a, b)").unwrap_err();
+        assert_eq!(error.reason, "`x` is not a number");
+        assert_eq!(error.span, Some((0, 1)));
+    }
+
+    #[test]
+    fn limits_patterns_and_depth() {
+        let many = (0..=MAX_CONDITION_PATTERNS)
+            .map(|i| format!("p{i}"))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        assert!(RuleCondition::parse(&many).is_err());
+
+        let nested = format!(
+            "{}a{}",
+            "(".repeat(MAX_CONDITION_DEPTH + 1),
+            ")".repeat(MAX_CONDITION_DEPTH + 1)
+        );
+        assert!(RuleCondition::parse(&nested).is_err());
+        let nested = format!(
+            "{}a{}",
+            "(".repeat(MAX_CONDITION_DEPTH),
+            ")".repeat(MAX_CONDITION_DEPTH)
+        );
+        assert!(RuleCondition::parse(&nested).is_ok());
+    }
+}
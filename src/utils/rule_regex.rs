@@ -0,0 +1,66 @@
+use regex::{Regex, RegexBuilder};
+
+/// Compiled size limit of rule regexes, the regex crate defaults to 10 MB
+const RULE_REGEX_SIZE_LIMIT: usize = 256 * 1024;
+const RULE_REGEX_DFA_SIZE_LIMIT: usize = 1024 * 1024;
+/// Maximum depth of nested groups and repetitions
+const RULE_REGEX_NEST_LIMIT: u32 = 16;
+
+/// Why a rule regex was rejected
+#[derive(Debug, Clone)]
+pub struct RegexRejection {
+    pub reason: String,
+    /// Character offset and length of the broken part of the pattern, if known
+    pub span: Option<(usize, usize)>,
+}
+
+/// Compiles a rule regex within the size and nesting limits, so a single pattern can't
+/// take up megabytes of memory or blow up matching time for every message of a guild.
+pub fn build_rule_regex(pattern: &str) -> Result<Regex, RegexRejection> {
+    RegexBuilder::new(pattern)
+        .size_limit(RULE_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(RULE_REGEX_DFA_SIZE_LIMIT)
+        .nest_limit(RULE_REGEX_NEST_LIMIT)
+        .build()
+        .map_err(|err| match err {
+            regex::Error::Syntax(message) => syntax_rejection(pattern, &message),
+            regex::Error::CompiledTooBig(limit) => RegexRejection {
+                reason: format!(
+                    "the pattern is too complex, it compiles to more than {} KB",
+                    limit / 1024
+                ),
+                span: None,
+            },
+            err => RegexRejection {
+                reason: err.to_string(),
+                span: None,
+            },
+        })
+}
+
+/// Reads the reason and the span marked with `^` out of a regex syntax error, which looks like
+/// ```text
+/// regex parse error:
+///     a(b
+///      ^
+/// error: unclosed group
+/// ```
+fn syntax_rejection(pattern: &str, message: &str) -> RegexRejection {
+    let reason = message
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix("error: "))
+        .unwrap_or(message)
+        .to_string();
+
+    let span = message
+        .lines()
+        .find(|line| !line.trim().is_empty() && line.trim().chars().all(|c| c == '^'))
+        .map(|line| {
+            let start = line.chars().take_while(|c| *c == ' ').count();
+            (start.saturating_sub(4), line.trim().chars().count())
+        })
+        .filter(|(start, len)| start + len <= pattern.chars().count());
+
+    RegexRejection { reason, span }
+}
@@ -61,12 +61,43 @@ pub async fn upload_image_with_key(key: String, data: Vec<u8>, content_type: &st
     }
 }
 
+/// Detects the content type from the leading bytes of a file, only the first 16 bytes are inspected
 pub fn detect_content_type(data: &[u8]) -> &'static str {
     match data {
         d if d.starts_with(b"\x89PNG") => "image/png",
         d if d.starts_with(b"\xff\xd8\xff") => "image/jpeg",
         d if d.starts_with(b"GIF8") => "image/gif",
         d if d.len() > 11 && d.starts_with(b"RIFF") && &d[8..12] == b"WEBP" => "image/webp",
+        d if d.starts_with(b"BM") && d.len() > 14 => "image/bmp",
+        d if d.len() > 11 && d.starts_with(b"RIFF") && &d[8..12] == b"WAVE" => "audio/wav",
+        d if d.len() > 11 && d.starts_with(b"RIFF") && &d[8..12] == b"AVI " => "video/x-msvideo",
+        d if d.len() > 11 && &d[4..8] == b"ftyp" => match &d[8..12] {
+            b"avif" | b"avis" => "image/avif",
+            b"heic" | b"heix" | b"mif1" => "image/heic",
+            b"qt  " => "video/quicktime",
+            b"M4A " | b"M4B " | b"M4P " => "audio/mp4",
+            b"3gp4" | b"3gp5" | b"3gp6" | b"3g2a" => "video/3gpp",
+            b"isom" | b"iso2" | b"iso4" | b"iso5" | b"iso6" | b"mp41" | b"mp42" | b"avc1"
+            | b"M4V " | b"mmp4" | b"dash" | b"MSNV" => "video/mp4",
+            _ => "application/octet-stream",
+        },
+        d if d.starts_with(b"\x1a\x45\xdf\xa3") => "video/webm",
+        d if d.starts_with(b"ID3") || d.starts_with(b"\xff\xfb") => "audio/mpeg",
+        d if d.starts_with(b"OggS") => "audio/ogg",
+        d if d.starts_with(b"fLaC") => "audio/flac",
+        d if d.starts_with(b"%PDF") => "application/pdf",
+        d if d.starts_with(b"PK\x03\x04") || d.starts_with(b"PK\x05\x06") => "application/zip",
+        d if d.starts_with(b"Rar!\x1a\x07") => "application/vnd.rar",
+        d if d.starts_with(b"7z\xbc\xaf\x27\x1c") => "application/x-7z-compressed",
+        d if d.starts_with(b"\x1f\x8b") => "application/gzip",
+        d if d.starts_with(b"BZh") => "application/x-bzip2",
+        d if d.starts_with(b"\xfd7zXZ\x00") => "application/x-xz",
+        d if d.starts_with(b"MZ") => "application/x-msdownload",
+        d if d.starts_with(b"\x7fELF") => "application/x-executable",
+        d if d.starts_with(b"\xcf\xfa\xed\xfe") || d.starts_with(b"\xce\xfa\xed\xfe") => {
+            "application/x-mach-binary"
+        }
+        d if d.starts_with(b"#!") => "text/x-shellscript",
         _ => "application/octet-stream",
     }
 }
@@ -0,0 +1,310 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+    time::{Duration, Instant},
+};
+
+use sqlx::Row;
+
+use crate::{
+    SQL,
+    database::ActionType,
+    utils::{consume_pgsql_error, rule_cache::Punishment},
+};
+
+/// Per guild spam thresholds, a limit of 0 disables that check
+#[derive(Debug, Clone)]
+pub struct SpamFilter {
+    /// Messages by a user in a single channel within the period that trigger the filter
+    pub channel_limit: u32,
+    /// Messages by a user across the whole guild within the period that trigger the filter
+    pub guild_limit: u32,
+    /// Distinct channels the same content has to be posted in within the period to trigger the filter
+    pub duplicate_channels: u32,
+    pub period: Duration,
+    pub punishment: Punishment,
+}
+
+impl SpamFilter {
+    pub fn describe(&self) -> String {
+        let limit = |n: u32| match n {
+            0 => String::from("off"),
+            n => n.to_string(),
+        };
+
+        format!(
+            "Channel: {} | Server: {} | Duplicate channels: {} | Period: {}s",
+            limit(self.channel_limit),
+            limit(self.guild_limit),
+            limit(self.duplicate_channels),
+            self.period.as_secs()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpamTrigger {
+    ChannelRate,
+    GuildRate,
+    Duplicate,
+}
+
+impl std::fmt::Display for SpamTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpamTrigger::ChannelRate => write!(f, "channel message rate"),
+            SpamTrigger::GuildRate => write!(f, "server message rate"),
+            SpamTrigger::Duplicate => write!(f, "duplicate content across channels"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RecentMessage {
+    id: u64,
+    channel_id: u64,
+    /// Hash of the normalized content, None for messages without text
+    content_hash: Option<u64>,
+    at: Instant,
+}
+
+/// A triggered filter with the offending burst as (channel_id, message_id) pairs
+pub type SpamHit = (SpamFilter, SpamTrigger, Vec<(u64, u64)>);
+
+/// Tracks recent messages per (guild, user) and checks them against the guilds spam filter
+#[derive(Default)]
+pub struct SpamCache {
+    filters: HashMap<u64, SpamFilter>,
+    recent: HashMap<(u64, u64), VecDeque<RecentMessage>>,
+}
+
+impl SpamCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn populate_from_db(&mut self) {
+        let res = match sqlx::query(
+            "SELECT guild_id, channel_limit, guild_limit, duplicate_channels, period, reason, punishment_type, duration, day_clear_amount, silent, log_channel_id FROM spam_filters",
+        )
+        .fetch_all(&*SQL)
+        .await
+        {
+            Ok(d) => d,
+            Err(err) => {
+                consume_pgsql_error("POPULATE SPAM CACHE".into(), err);
+                return;
+            }
+        };
+
+        for record in res {
+            let punishment_type: ActionType = record.get("punishment_type");
+            let punishment = Punishment::from_parts(
+                &punishment_type.to_string(),
+                record
+                    .get::<Option<String>, _>("reason")
+                    .unwrap_or(String::from("No reason provided")),
+                record
+                    .get::<Option<i16>, _>("day_clear_amount")
+                    .unwrap_or(0) as u8,
+                record.get::<Option<i64>, _>("duration").unwrap_or(0) as u64,
+                record.get::<Option<bool>, _>("silent").unwrap_or(false),
+                record.get::<Option<i64>, _>("log_channel_id").unwrap_or(0) as u64,
+            );
+
+            self.filters.insert(
+                record.get::<i64, _>("guild_id") as u64,
+                SpamFilter {
+                    channel_limit: record.get::<i32, _>("channel_limit") as u32,
+                    guild_limit: record.get::<i32, _>("guild_limit") as u32,
+                    duplicate_channels: record.get::<i32, _>("duplicate_channels") as u32,
+                    period: Duration::from_secs(record.get::<i32, _>("period") as u64),
+                    punishment,
+                },
+            );
+        }
+    }
+
+    pub fn get(&self, guild_id: u64) -> Option<SpamFilter> {
+        self.filters.get(&guild_id).cloned()
+    }
+
+    pub fn set(&mut self, guild_id: u64, filter: SpamFilter) {
+        self.filters.insert(guild_id, filter);
+    }
+
+    pub fn remove(&mut self, guild_id: u64) -> Option<SpamFilter> {
+        self.recent.retain(|(g, _), _| *g != guild_id);
+        self.filters.remove(&guild_id)
+    }
+
+    /// Records a message and checks it against the guilds filter.
+    /// On a trigger the offending burst is returned as (channel_id, message_id) pairs and the users history is cleared,
+    /// so the following messages of the same burst do not punish again.
+    pub fn track(
+        &mut self,
+        guild_id: u64,
+        user_id: u64,
+        channel_id: u64,
+        message_id: u64,
+        content: &str,
+    ) -> Option<SpamHit> {
+        let filter = self.filters.get(&guild_id)?;
+        let now = Instant::now();
+
+        let normalized = content.trim().to_lowercase();
+        let content_hash = (!normalized.is_empty()).then(|| {
+            let mut hasher = DefaultHasher::new();
+            normalized.hash(&mut hasher);
+            hasher.finish()
+        });
+
+        let history = self.recent.entry((guild_id, user_id)).or_default();
+        while history
+            .front()
+            .is_some_and(|m| now.duration_since(m.at) > filter.period)
+        {
+            history.pop_front();
+        }
+
+        history.push_back(RecentMessage {
+            id: message_id,
+            channel_id,
+            content_hash,
+            at: now,
+        });
+
+        let burst = |pred: &dyn Fn(&RecentMessage) -> bool| {
+            history
+                .iter()
+                .filter(|m| pred(m))
+                .map(|m| (m.channel_id, m.id))
+                .collect::<Vec<_>>()
+        };
+
+        let in_channel = burst(&|m| m.channel_id == channel_id);
+
+        let trigger =
+            if filter.channel_limit != 0 && in_channel.len() as u32 >= filter.channel_limit {
+                Some((SpamTrigger::ChannelRate, in_channel))
+            } else if filter.guild_limit != 0 && history.len() as u32 >= filter.guild_limit {
+                Some((SpamTrigger::GuildRate, burst(&|_| true)))
+            } else if filter.duplicate_channels != 0 && content_hash.is_some() {
+                let duplicates = burst(&|m| m.content_hash == content_hash);
+                let channels = duplicates.iter().map(|(c, _)| *c).collect::<HashSet<_>>();
+
+                (channels.len() as u32 >= filter.duplicate_channels)
+                    .then_some((SpamTrigger::Duplicate, duplicates))
+            } else {
+                None
+            };
+
+        let (trigger, messages) = trigger?;
+        let filter = filter.clone();
+        self.recent.remove(&(guild_id, user_id));
+
+        Some((filter, trigger, messages))
+    }
+
+    /// Drops histories that have not seen a message within their guilds period
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        let filters = &self.filters;
+
+        self.recent.retain(|(guild_id, _), history| {
+            let Some(filter) = filters.get(guild_id) else {
+                return false;
+            };
+
+            history
+                .back()
+                .is_some_and(|m| now.duration_since(m.at) <= filter.period)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(channel_limit: u32, guild_limit: u32, duplicate_channels: u32) -> SpamCache {
+        let mut cache = SpamCache::new();
+        cache.set(
+            1,
+            SpamFilter {
+                channel_limit,
+                guild_limit,
+                duplicate_channels,
+                period: Duration::from_secs(60),
+                punishment: Punishment::Log {
+                    reason: String::new(),
+                    channel_id: 0,
+                },
+            },
+        );
+        cache
+    }
+
+    #[test]
+    fn channel_rate_triggers_at_the_limit() {
+        let mut cache = cache(3, 0, 0);
+
+        assert!(cache.track(1, 10, 100, 1, "a").is_none());
+        assert!(cache.track(1, 10, 200, 2, "b").is_none());
+        assert!(cache.track(1, 10, 100, 3, "c").is_none());
+        let (_, trigger, messages) = cache.track(1, 10, 100, 4, "d").unwrap();
+        assert_eq!(trigger, SpamTrigger::ChannelRate);
+        assert_eq!(messages, vec![(100, 1), (100, 3), (100, 4)]);
+    }
+
+    #[test]
+    fn guild_rate_counts_every_channel() {
+        let mut cache = cache(0, 3, 0);
+
+        assert!(cache.track(1, 10, 100, 1, "a").is_none());
+        assert!(cache.track(1, 10, 200, 2, "b").is_none());
+        let (_, trigger, messages) = cache.track(1, 10, 300, 3, "c").unwrap();
+        assert_eq!(trigger, SpamTrigger::GuildRate);
+        assert_eq!(messages, vec![(100, 1), (200, 2), (300, 3)]);
+    }
+
+    #[test]
+    fn duplicates_need_distinct_channels() {
+        let mut cache = cache(0, 0, 2);
+
+        assert!(cache.track(1, 10, 100, 1, "Free Nitro").is_none());
+        assert!(cache.track(1, 10, 100, 2, "free nitro").is_none());
+        assert!(cache.track(1, 10, 200, 3, "something else").is_none());
+        let (_, trigger, messages) = cache.track(1, 10, 200, 4, "  FREE NITRO ").unwrap();
+        assert_eq!(trigger, SpamTrigger::Duplicate);
+        assert_eq!(messages, vec![(100, 1), (100, 2), (200, 4)]);
+
+        assert!(cache.track(1, 10, 100, 5, "").is_none());
+        assert!(cache.track(1, 10, 200, 6, "").is_none());
+    }
+
+    #[test]
+    fn histories_are_per_user_and_cleared_on_trigger() {
+        let mut cache = cache(2, 0, 0);
+
+        assert!(cache.track(1, 10, 100, 1, "a").is_none());
+        assert!(cache.track(1, 20, 100, 2, "a").is_none());
+        assert!(cache.track(1, 10, 100, 3, "a").is_some());
+        assert!(cache.track(1, 10, 100, 4, "a").is_none());
+        assert!(cache.track(2, 10, 100, 5, "a").is_none());
+    }
+
+    #[test]
+    fn old_messages_leave_the_period() {
+        let mut cache = cache(2, 0, 0);
+        cache.filters.get_mut(&1).unwrap().period = Duration::from_millis(20);
+
+        assert!(cache.track(1, 10, 100, 1, "a").is_none());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.track(1, 10, 100, 2, "a").is_none());
+
+        std::thread::sleep(Duration::from_millis(30));
+        cache.prune();
+        assert!(cache.recent.is_empty());
+    }
+}
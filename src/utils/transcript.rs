@@ -314,6 +314,7 @@ pub async fn save_transcript(
             let ext = att.filename.rsplit('.').next().unwrap_or("png");
             let s3_url = upload_to_s3_async(guild_id_val, &att.url, ext);
             partial_attachments.push(PartialAttachment {
+                id: att.id.get(),
                 name: att.filename.clone(),
                 url: s3_url,
             });